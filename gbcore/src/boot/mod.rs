@@ -0,0 +1,45 @@
+use crate::CoreError;
+
+const DMG_BOOT_ROM_SIZE: usize = 0x100;
+const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+// DMG boot ROM: 256 bytes mapped at 0000-00FF
+// CGB boot ROM: 2304 bytes mapped at 0000-00FF and 0200-08FF,
+// 0100-01FF always reads the cartridge header.
+#[derive(Clone)]
+pub struct BootRom {
+    data: [u8; CGB_BOOT_ROM_SIZE],
+    cgb: bool,
+}
+
+impl BootRom {
+    pub fn new(data: &[u8]) -> Result<Self, CoreError> {
+        let cgb = match data.len() {
+            DMG_BOOT_ROM_SIZE => false,
+            CGB_BOOT_ROM_SIZE => true,
+            len => return Err(CoreError::InvalidBootRomSize(len)),
+        };
+        let mut boot_rom = Self {
+            data: [0; CGB_BOOT_ROM_SIZE],
+            cgb,
+        };
+        boot_rom.data[..data.len()].copy_from_slice(data);
+        Ok(boot_rom)
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        self.cgb
+    }
+
+    pub fn contains(&self, addr: u16) -> bool {
+        match addr {
+            0x0000..=0x00FF => true,
+            0x0200..=0x08FF => self.cgb,
+            _ => false,
+        }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        self.data[addr as usize]
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    CoreError, Memory,
+    cartridge::Cartridge,
+    debugger::Watchpoints,
+    gpu::{GPU, State, oam::OamDmaManager},
+    mmu::MMU,
+    serial::Serial,
+    timers::Timers,
+};
+
+// CPU view of the memory: every access first runs the rest of the system
+// for one M-cycle, so reads and writes happen at the right time within an
+// instruction.
+pub(crate) struct Bus<'a, C: Cartridge> {
+    pub mmu: &'a mut MMU<C>,
+    gpu: &'a mut GPU,
+    timers: &'a mut Timers,
+    serial: &'a mut Serial,
+    oam_manager: &'a mut OamDmaManager,
+    watchpoints: &'a mut Watchpoints,
+    // Cycles already run by the accesses of the current step
+    cycles: u8,
+    frame: bool,
+    // First error raised by a peripheral during the step
+    error: Option<CoreError>,
+}
+
+impl<'a, C: Cartridge> Bus<'a, C> {
+    pub fn new(
+        mmu: &'a mut MMU<C>,
+        gpu: &'a mut GPU,
+        timers: &'a mut Timers,
+        serial: &'a mut Serial,
+        oam_manager: &'a mut OamDmaManager,
+        watchpoints: &'a mut Watchpoints,
+    ) -> Self {
+        Self {
+            mmu,
+            gpu,
+            timers,
+            serial,
+            oam_manager,
+            watchpoints,
+            cycles: 0,
+            frame: false,
+            error: None,
+        }
+    }
+
+    // Run the cycles of a step not spent on memory accesses (internal
+    // cycles), returns true when a frame is done
+    pub fn finish(&mut self, cycles: u8) -> Result<bool, CoreError> {
+        if cycles > self.cycles {
+            self.advance(cycles - self.cycles);
+        }
+        self.end_step()
+    }
+
+    // LCD and timers are stopped with the CPU
+    pub fn idle(&mut self, cycles: u8) -> Result<bool, CoreError> {
+        if let State::Frame = self.gpu.idle(self.gpu_ticks(cycles)) {
+            self.frame = true;
+        }
+        self.end_step()
+    }
+
+    fn end_step(&mut self) -> Result<bool, CoreError> {
+        self.cycles = 0;
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(core::mem::take(&mut self.frame)),
+        }
+    }
+
+    // In double speed mode the PPU still runs at 4 MHz
+    fn gpu_ticks(&self, ticks: u8) -> u8 {
+        match self.mmu.speed.double_speed {
+            true => ticks / 2,
+            false => ticks,
+        }
+    }
+
+    fn advance(&mut self, ticks: u8) {
+        match self.gpu.tick(self.mmu, self.gpu_ticks(ticks)) {
+            Ok(State::Frame) => self.frame = true,
+            Ok(State::Default) => {}
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self.timers.tick(self.mmu, ticks);
+        self.serial.tick(self.mmu, ticks);
+        self.oam_manager.tick(self.mmu, ticks);
+    }
+
+    fn access(&mut self) {
+        self.advance(4);
+        self.cycles = self.cycles.saturating_add(4);
+    }
+}
+
+impl<C: Cartridge> Memory for Bus<'_, C> {
+    fn read(&mut self, addr: u16) -> u8 {
+        self.access();
+        let value = self.mmu.read(addr);
+        self.watchpoints.check(addr, value, false);
+        value
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.access();
+        self.watchpoints.check(addr, value, true);
+        self.mmu.write(addr, value);
+    }
+
+    fn internal(&mut self) {
+        self.access();
+    }
+}
@@ -0,0 +1,284 @@
+use super::{Cartridge, Peripherals};
+use crate::Memory;
+use core::ops::{Index, IndexMut};
+
+pub const CAMERA_WIDTH: usize = 128;
+pub const CAMERA_HEIGHT: usize = 112;
+
+// Captured picture, 2bpp tiles in RAM bank 0
+const PICTURE_OFFSET: usize = 0x0100;
+// 2 bits per pixel
+const PICTURE_SIZE: usize = CAMERA_WIDTH * CAMERA_HEIGHT / 4;
+
+// Edge enhancement ratios (A004 bits 4-6), in 1/4
+const EDGE_RATIOS: [i32; 8] = [2, 3, 4, 5, 8, 12, 16, 20];
+
+// Game Boy Camera (MAC-GBD mapper and M64282FP sensor)
+pub struct PocketCamera<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    ram: RAM,
+    rom: ROM,
+    rom_banks: usize,
+    peripherals: Peripherals,
+
+    rom_bank: usize,
+    // Bit 4 maps the camera registers at A000-BFFF
+    ram_bank: u8,
+    ram_enable: bool,
+
+    // A000-A035: control, gain, exposure, edge/invert, voltage, dithering matrix
+    registers: [u8; 0x36],
+    frame: [u8; CAMERA_WIDTH * CAMERA_HEIGHT],
+}
+
+impl<RAM, ROM> PocketCamera<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(ram: RAM, rom: ROM, rom_banks: usize, peripherals: Peripherals) -> Self {
+        Self {
+            ram,
+            rom,
+            rom_banks,
+            peripherals,
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_enable: false,
+            registers: [0; 0x36],
+            frame: [0; CAMERA_WIDTH * CAMERA_HEIGHT],
+        }
+    }
+
+    // Banks past the end of the RAM wrap around, the RAM must not be empty
+    fn ram_offset(&self, addr: u16) -> usize {
+        let bank = (self.ram_bank & 0x0F) as usize;
+        (bank * 0x2000 + (addr - 0xA000) as usize) % self.ram.as_ref().len()
+    }
+
+    // The cartridge is not clocked: the picture is ready as soon as the
+    // capture starts.
+    fn capture(&mut self) {
+        // Nowhere to store the picture
+        if self.ram.as_ref().len() < PICTURE_OFFSET + PICTURE_SIZE {
+            return;
+        }
+        self.peripherals.capture(&mut self.frame);
+
+        let exposure = u16::from_be_bytes([self.registers[2], self.registers[3]]) as i32;
+        let edge_mode = (self.registers[1] >> 5) & 0b11;
+        let edge_ratio = EDGE_RATIOS[((self.registers[4] >> 4) & 0b111) as usize];
+        let invert = self.registers[4] & 0x08 != 0;
+
+        let pixel = |frame: &[u8], x: isize, y: isize| -> i32 {
+            let x = x.clamp(0, CAMERA_WIDTH as isize - 1) as usize;
+            let y = y.clamp(0, CAMERA_HEIGHT as isize - 1) as usize;
+            frame[y * CAMERA_WIDTH + x] as i32 * exposure / 0x1000
+        };
+
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let (xi, yi) = (x as isize, y as isize);
+                let center = pixel(&self.frame, xi, yi);
+                let horizontal =
+                    2 * center - pixel(&self.frame, xi - 1, yi) - pixel(&self.frame, xi + 1, yi);
+                let vertical =
+                    2 * center - pixel(&self.frame, xi, yi - 1) - pixel(&self.frame, xi, yi + 1);
+                let edge = match edge_mode {
+                    0b01 => horizontal,
+                    0b10 => vertical,
+                    0b11 => horizontal + vertical,
+                    _ => 0,
+                };
+                let mut value = (center + edge * edge_ratio / 4).clamp(0, 255) as u8;
+                if invert {
+                    value = 255 - value;
+                }
+                let color = self.dither(x, y, value);
+                self.draw(x, y, color);
+            }
+        }
+    }
+
+    // 4x4 matrix of 3 thresholds each
+    fn dither(&self, x: usize, y: usize, value: u8) -> u8 {
+        let idx = 6 + ((y & 3) * 4 + (x & 3)) * 3;
+        let thresholds = &self.registers[idx..idx + 3];
+        match thresholds.iter().position(|t| value < *t) {
+            Some(0) => 3,
+            Some(1) => 2,
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    fn draw(&mut self, x: usize, y: usize, color: u8) {
+        let tile = (y / 8) * (CAMERA_WIDTH / 8) + x / 8;
+        let offset = PICTURE_OFFSET + tile * 16 + (y % 8) * 2;
+        let bit = 0x80 >> (x % 8);
+        let ram = self.ram.as_mut();
+        for (plane, byte) in ram[offset..offset + 2].iter_mut().enumerate() {
+            match (color >> plane) & 0x01 {
+                0 => *byte &= !bit,
+                _ => *byte |= bit,
+            }
+        }
+    }
+}
+
+impl<RAM, ROM> Cartridge for PocketCamera<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank % self.rom_banks,
+        }
+    }
+}
+
+impl<RAM, ROM> Memory for PocketCamera<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–7FFF — ROM Bank 00-3F
+            0x4000..=0x7FFF => {
+                self.rom[(self.rom_bank % self.rom_banks) * 0x4000 + addr as usize - 0x4000]
+            }
+            // A000–BFFF — Camera registers, only A000 can be read
+            0xA000..=0xBFFF if self.ram_bank & 0x10 != 0 => match addr & 0x7F {
+                0x00 => self.registers[0],
+                _ => 0x00,
+            },
+            // A000–BFFF — RAM Bank 00–0F
+            0xA000..=0xBFFF if self.ram.as_ref().is_empty() => 0xFF,
+            0xA000..=0xBFFF => self.ram[self.ram_offset(addr)],
+            _ => panic!("PocketCamera read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–1FFF — RAM write enable
+            0x0000..=0x1FFF => self.ram_enable = value & 0x0F == 0x0A,
+            // 2000–3FFF — ROM Bank Number
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x3F) as usize,
+            // 4000–5FFF — RAM Bank Number / camera registers
+            0x4000..=0x5FFF => self.ram_bank = value & 0x1F,
+            0x6000..=0x7FFF => {}
+            0xA000..=0xBFFF if self.ram_bank & 0x10 != 0 => match (addr & 0x7F) as usize {
+                0x00 => {
+                    self.registers[0] = value & 0x07;
+                    if value & 0x01 != 0 {
+                        self.capture();
+                        self.registers[0] &= !0x01;
+                    }
+                }
+                reg @ 0x01..=0x35 => self.registers[reg] = value,
+                _ => {}
+            },
+            0xA000..=0xBFFF => {
+                if self.ram_enable && !self.ram.as_ref().is_empty() {
+                    let offset = self.ram_offset(addr);
+                    self.ram[offset] = value;
+                }
+            }
+            _ => panic!("PocketCamera write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::ImageSource;
+    extern crate alloc;
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    // Capture with the same thresholds for every pixel
+    fn capture(cam: &mut PocketCamera<Vec<u8>, Vec<u8>>, edge_invert: u8) -> [u8; 2] {
+        cam.write(0x4000, 0x10);
+        cam.write(0xA002, 0x10);
+        cam.write(0xA003, 0x00);
+        cam.write(0xA004, edge_invert);
+        for pixel in 0..16 {
+            for (i, threshold) in [0x40, 0x80, 0xC0].into_iter().enumerate() {
+                cam.write(0xA006 + pixel * 3 + i as u16, threshold);
+            }
+        }
+        cam.write(0xA000, 0x03);
+        // Done as soon as started
+        assert_eq!(cam.read(0xA000), 0x02);
+        cam.write(0x4000, 0x00);
+        let ram = cam.ram().unwrap();
+        let last = PICTURE_OFFSET + PICTURE_SIZE - 2;
+        assert_eq!(ram[PICTURE_OFFSET..PICTURE_OFFSET + 2], ram[last..last + 2]);
+        [ram[PICTURE_OFFSET], ram[PICTURE_OFFSET + 1]]
+    }
+
+    #[test]
+    fn capture_dithers_the_picture() {
+        // Mid gray without image source
+        let mut cam =
+            PocketCamera::new(vec![0; 0x20000], vec![0; 0x8000], 2, Peripherals::default());
+        assert_eq!(capture(&mut cam, 0x00), [0xFF, 0x00]);
+        assert_eq!(capture(&mut cam, 0x08), [0x00, 0xFF]);
+    }
+
+    // Gray picture with a white column at x = 64
+    struct Stripe;
+
+    impl ImageSource for Stripe {
+        fn capture(&mut self, frame: &mut [u8; CAMERA_WIDTH * CAMERA_HEIGHT]) {
+            for (i, pixel) in frame.iter_mut().enumerate() {
+                *pixel = if i % CAMERA_WIDTH == 64 { 0xFF } else { 0x80 };
+            }
+        }
+    }
+
+    #[test]
+    fn edge_enhancement() {
+        let peripherals = Peripherals {
+            camera: Some(Box::new(Stripe)),
+            ..Default::default()
+        };
+        let mut cam = PocketCamera::new(vec![0; 0x20000], vec![0; 0x8000], 2, peripherals);
+        // Tile 7 holds x = 56-63, the column left of the stripe
+        let left = PICTURE_OFFSET + 7 * 16;
+        capture(&mut cam, 0x00);
+        assert_eq!(cam.ram().unwrap()[left..left + 2], [0xFF, 0x00]);
+        // Horizontal enhancement darkens the gray next to the white
+        cam.write(0x4000, 0x10);
+        cam.write(0xA001, 0x20);
+        capture(&mut cam, 0x00);
+        assert_eq!(cam.ram().unwrap()[left..left + 2], [0xFE, 0x01]);
+    }
+
+    #[test]
+    fn capture_without_ram() {
+        let mut cam = PocketCamera::new(vec![], vec![0; 0x8000], 2, Peripherals::default());
+        cam.write(0x4000, 0x10);
+        cam.write(0xA000, 0x01);
+        assert_eq!(cam.read(0xA000), 0x00);
+        cam.write(0x4000, 0x00);
+        assert_eq!(cam.read(0xA000), 0xFF);
+    }
+}
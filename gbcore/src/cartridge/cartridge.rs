@@ -1,6 +1,6 @@
 use crate::CoreError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CartridgeType {
     ROMOnly,
     MBC1,
@@ -32,6 +32,28 @@ pub enum CartridgeType {
     HuC1RamBattery,
 }
 
+impl CartridgeType {
+    pub fn has_battery(&self) -> bool {
+        matches!(
+            self,
+            CartridgeType::MBC1RamBattery
+                | CartridgeType::MBC2Battery
+                | CartridgeType::RomRamBattery
+                | CartridgeType::MMM01RamBattery
+                | CartridgeType::MBC3TimerBattery
+                | CartridgeType::MBC3TimerRamBattery
+                | CartridgeType::MBC3RamBattery
+                | CartridgeType::MBC5RamBattery
+                | CartridgeType::MBC5RumbleRamBattery
+                | CartridgeType::MBC7SensorRumbleRamBattery
+                | CartridgeType::HuC3
+                | CartridgeType::HuC1RamBattery
+                | CartridgeType::BandaiTama5
+                | CartridgeType::MBC6
+        )
+    }
+}
+
 impl TryFrom<u8> for CartridgeType {
     type Error = CoreError;
 
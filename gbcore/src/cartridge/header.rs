@@ -0,0 +1,273 @@
+use super::{CartridgeType, RamType, RomType};
+use crate::CoreError;
+
+extern crate alloc;
+use alloc::string::String;
+
+const HEADER_END: usize = 0x150;
+
+// Bitmap checked by the boot ROM at 0x104-0x133
+pub(crate) const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// Nintendo logo of a header starting at `offset`
+pub(crate) fn has_logo(data: &[u8], offset: usize) -> bool {
+    data.get(offset + 0x104..offset + 0x134) == Some(&NINTENDO_LOGO[..])
+}
+
+// 0x143 - CGB flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbFlag {
+    // DMG game, runs in DMG compatibility mode on CGB
+    Dmg,
+    // 0x80: CGB enhanced, still works on DMG
+    Enhanced,
+    // 0xC0: CGB only
+    CgbOnly,
+}
+
+impl From<u8> for CgbFlag {
+    fn from(value: u8) -> Self {
+        match value {
+            0xC0 => CgbFlag::CgbOnly,
+            0x80 => CgbFlag::Enhanced,
+            _ => CgbFlag::Dmg,
+        }
+    }
+}
+
+impl CgbFlag {
+    pub fn supports_cgb(&self) -> bool {
+        *self != CgbFlag::Dmg
+    }
+}
+
+// 0x14A - Destination code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    Japan,
+    Overseas,
+}
+
+// Inconsistencies that real hardware ignores (except the logo and the header
+// checksum, checked by the boot ROM)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderWarning {
+    InvalidLogo,
+    HeaderChecksum { expected: u8, computed: u8 },
+    GlobalChecksum { expected: u16, computed: u16 },
+}
+
+// Cartridge header (0x100-0x14F)
+#[derive(Debug, Clone)]
+pub struct CartridgeHeader {
+    // 0x100-0x103: usually NOP; JP 0x150
+    pub entry_point: [u8; 4],
+    // 0x104-0x133
+    pub logo_valid: bool,
+    // 0x134-0x143 (0x134-0x13E when there is a manufacturer code)
+    pub title: String,
+    // 0x13F-0x142: only on some CGB games
+    pub manufacturer_code: Option<[u8; 4]>,
+    pub cgb_flag: CgbFlag,
+    // 0x144-0x145: used when old_licensee is 0x33
+    pub new_licensee: [u8; 2],
+    // 0x146
+    pub sgb_flag: bool,
+    // 0x147-0x149
+    pub cart_type: CartridgeType,
+    pub rom_type: RomType,
+    pub ram_type: RamType,
+    pub destination: Destination,
+    // 0x14B
+    pub old_licensee: u8,
+    // 0x14C
+    pub mask_rom_version: u8,
+    // 0x14D
+    pub header_checksum: u8,
+    // 0x14E-0x14F, big endian
+    pub global_checksum: u16,
+
+    computed_header_checksum: u8,
+    // Only known when the whole ROM is parsed
+    computed_global_checksum: Option<u16>,
+}
+
+impl CartridgeHeader {
+    // `data` starts at 0x0000: the header alone (0x150 bytes) or the whole ROM,
+    // in which case the global checksum is verified too.
+    pub fn parse(data: &[u8]) -> Result<Self, CoreError> {
+        if data.len() < HEADER_END {
+            return Err(CoreError::TruncatedHeader(data.len()));
+        }
+
+        let cgb_flag = CgbFlag::from(data[0x143]);
+        let manufacturer_code = match cgb_flag {
+            CgbFlag::Dmg => None,
+            _ => {
+                let code: [u8; 4] = data[0x13F..0x143].try_into().unwrap();
+                code.iter()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                    .then_some(code)
+            }
+        };
+        let title_end = match (manufacturer_code, cgb_flag) {
+            (Some(_), _) => 0x13F,
+            (None, CgbFlag::Dmg) => 0x144,
+            (None, _) => 0x143,
+        };
+        let title = data[0x134..title_end]
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| match c.is_ascii_graphic() || *c == b' ' {
+                true => *c as char,
+                false => '?',
+            })
+            .collect();
+
+        let computed_header_checksum = data[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+        let computed_global_checksum = (data.len() > HEADER_END).then(|| {
+            data.iter()
+                .enumerate()
+                .filter(|(addr, _)| *addr != 0x14E && *addr != 0x14F)
+                .fold(0u16, |sum, (_, b)| sum.wrapping_add(*b as u16))
+        });
+
+        Ok(Self {
+            entry_point: data[0x100..0x104].try_into().unwrap(),
+            logo_valid: has_logo(data, 0),
+            title,
+            manufacturer_code,
+            cgb_flag,
+            new_licensee: [data[0x144], data[0x145]],
+            sgb_flag: data[0x146] == 0x03,
+            cart_type: CartridgeType::try_from(data[0x147])?,
+            rom_type: RomType::try_from(data[0x148])?,
+            ram_type: RamType::try_from(data[0x149])?,
+            destination: match data[0x14A] {
+                0x00 => Destination::Japan,
+                _ => Destination::Overseas,
+            },
+            old_licensee: data[0x14B],
+            mask_rom_version: data[0x14C],
+            header_checksum: data[0x14D],
+            global_checksum: u16::from_be_bytes([data[0x14E], data[0x14F]]),
+            computed_header_checksum,
+            computed_global_checksum,
+        })
+    }
+
+    // Nintendo first party game (used by the CGB boot ROM for colorization)
+    pub fn licensee_is_nintendo(&self) -> bool {
+        match self.old_licensee {
+            0x01 => true,
+            0x33 => self.new_licensee == *b"01",
+            _ => false,
+        }
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = HeaderWarning> + '_ {
+        let logo = (!self.logo_valid).then_some(HeaderWarning::InvalidLogo);
+        let header = (self.header_checksum != self.computed_header_checksum).then_some(
+            HeaderWarning::HeaderChecksum {
+                expected: self.header_checksum,
+                computed: self.computed_header_checksum,
+            },
+        );
+        let global = self
+            .computed_global_checksum
+            .filter(|computed| *computed != self.global_checksum)
+            .map(|computed| HeaderWarning::GlobalChecksum {
+                expected: self.global_checksum,
+                computed,
+            });
+        logo.into_iter().chain(header).chain(global)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    // 32 KiB ROM with a valid logo and checksums
+    fn rom(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x143] = cgb_flag;
+        rom[0x14D] = rom[0x134..=0x14C]
+            .iter()
+            .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+        let global = rom.iter().fold(0u16, |sum, b| sum.wrapping_add(*b as u16));
+        rom[0x14E..0x150].copy_from_slice(&global.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    fn checksums() {
+        let mut data = rom(b"TETRIS", 0x00);
+        let header = CartridgeHeader::parse(&data).unwrap();
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.warnings().count(), 0);
+
+        // Outside the header: only the global checksum changes
+        data[0x4000] = 0x01;
+        let global = u16::from_be_bytes([data[0x14E], data[0x14F]]);
+        let warnings: Vec<_> = CartridgeHeader::parse(&data).unwrap().warnings().collect();
+        assert_eq!(
+            warnings,
+            [HeaderWarning::GlobalChecksum {
+                expected: global,
+                computed: global + 1,
+            }]
+        );
+        // Not verified on the header alone
+        let header = CartridgeHeader::parse(&data[..HEADER_END]).unwrap();
+        assert_eq!(header.warnings().count(), 0);
+
+        data[0x104] = 0;
+        data[0x14C] = 0x01;
+        let warnings: Vec<_> = CartridgeHeader::parse(&data[..HEADER_END])
+            .unwrap()
+            .warnings()
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                HeaderWarning::InvalidLogo,
+                HeaderWarning::HeaderChecksum {
+                    expected: data[0x14D],
+                    computed: data[0x14D].wrapping_sub(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn title_and_manufacturer_code() {
+        let header = CartridgeHeader::parse(&rom(b"POKEMON_GLDAAUE", 0x80)).unwrap();
+        assert_eq!(header.title, "POKEMON_GLD");
+        assert_eq!(header.manufacturer_code, Some(*b"AAUE"));
+
+        let header = CartridgeHeader::parse(&rom(b"GAME\x01aaaaaaa", 0xC0)).unwrap();
+        assert_eq!(header.title, "GAME?aaaaaaa");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.cgb_flag, CgbFlag::CgbOnly);
+    }
+
+    #[test]
+    fn truncated_header() {
+        assert!(matches!(
+            CartridgeHeader::parse(&[0; 0x14F]),
+            Err(CoreError::TruncatedHeader(0x14F))
+        ));
+    }
+}
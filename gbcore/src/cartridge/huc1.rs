@@ -0,0 +1,153 @@
+use super::{Cartridge, Peripherals};
+use crate::Memory;
+use core::ops::{Index, IndexMut};
+
+pub struct HuC1<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    ram: RAM,
+    rom: ROM,
+    rom_banks: usize,
+    ram_banks: usize,
+    peripherals: Peripherals,
+
+    rom_bank: usize,
+    ram_bank: usize,
+    // A000-BFFF accesses the infrared port instead of the RAM
+    ir_mode: bool,
+}
+
+impl<RAM, ROM> HuC1<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(ram: RAM, rom: ROM, rom_banks: usize, peripherals: Peripherals) -> Self {
+        let ram_banks = ram.as_ref().len() / 0x2000;
+        Self {
+            ram,
+            rom,
+            rom_banks,
+            ram_banks,
+            peripherals,
+            rom_bank: 1,
+            ram_bank: 0,
+            ir_mode: false,
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        match self.ram_banks {
+            0 => None,
+            n => Some((self.ram_bank % n) * 0x2000 + (addr - 0xA000) as usize),
+        }
+    }
+}
+
+impl<RAM, ROM> Cartridge for HuC1<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank % self.rom_banks,
+        }
+    }
+}
+
+impl<RAM, ROM> Memory for HuC1<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–7FFF — ROM Bank 00-3F
+            0x4000..=0x7FFF => {
+                self.rom[(self.rom_bank % self.rom_banks) * 0x4000 + addr as usize - 0x4000]
+            }
+            // A000–BFFF — IR receiver: 0xC1 when light is seen
+            0xA000..=0xBFFF if self.ir_mode => 0xC0 | self.peripherals.light_detected() as u8,
+            // A000–BFFF — RAM Bank 00–03
+            0xA000..=0xBFFF => match self.ram_offset(addr) {
+                Some(offset) => self.ram[offset],
+                None => 0xFF,
+            },
+            _ => panic!("HuC1 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–1FFF — 0x0E selects IR mode, anything else RAM mode
+            0x0000..=0x1FFF => self.ir_mode = value == 0x0E,
+            // 2000–3FFF — ROM Bank Number
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x3F) as usize,
+            // 4000–5FFF — RAM Bank Number
+            0x4000..=0x5FFF => self.ram_bank = (value & 0x03) as usize,
+            // 6000–7FFF — Nothing
+            0x6000..=0x7FFF => {}
+            // A000–BFFF — IR LED
+            0xA000..=0xBFFF if self.ir_mode => self.peripherals.set_led(value & 0x01 != 0),
+            // A000–BFFF — RAM Bank 00–03
+            0xA000..=0xBFFF => {
+                if let Some(offset) = self.ram_offset(addr) {
+                    self.ram[offset] = value;
+                }
+            }
+            _ => panic!("HuC1 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::InfraredLink;
+    extern crate alloc;
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    #[test]
+    fn banking() {
+        let mut rom = vec![0; 64 * 0x4000];
+        rom[0x3F * 0x4000] = 0x3F;
+        let mut cart = HuC1::new(vec![0; 0x8000], rom, 64, Peripherals::default());
+        cart.write(0x2000, 0xFF);
+        assert_eq!(cart.read(0x4000), 0x3F);
+        cart.write(0x4000, 0x07);
+        cart.write(0xA000, 0x12);
+        assert_eq!(cart.ram().unwrap()[3 * 0x2000], 0x12);
+    }
+
+    #[test]
+    fn infrared_link() {
+        let (a, b) = InfraredLink::pair();
+        let peripherals = |link| Peripherals {
+            infrared: Some(Box::new(link)),
+            ..Default::default()
+        };
+        let mut cart_a = HuC1::new(vec![0; 0x2000], vec![0; 0x8000], 2, peripherals(a));
+        let mut cart_b = HuC1::new(vec![0; 0x2000], vec![0; 0x8000], 2, peripherals(b));
+        cart_a.write(0x0000, 0x0E);
+        cart_b.write(0x0000, 0x0E);
+        assert_eq!(cart_b.read(0xA000), 0xC0);
+        cart_a.write(0xA000, 0x01);
+        assert_eq!(cart_b.read(0xA000), 0xC1);
+        // Back to RAM mode
+        cart_b.write(0x0000, 0x0A);
+        assert_eq!(cart_b.read(0xA000), 0x00);
+    }
+}
@@ -0,0 +1,276 @@
+use super::{Cartridge, Peripherals};
+use crate::Memory;
+use core::ops::{Index, IndexMut};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_DAY: u64 = 24 * 60 * SECONDS_PER_MINUTE;
+
+pub struct HuC3<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    ram: RAM,
+    rom: ROM,
+    rom_banks: usize,
+    ram_banks: usize,
+    peripherals: Peripherals,
+
+    // Selects what A000-BFFF accesses
+    mode: u8,
+    rom_bank: usize,
+    ram_bank: usize,
+
+    // RTC chip: 256 nibbles addressed by commands. 00-02 hold the minutes of
+    // the day and 03-05 the day counter when copied from/to the clock.
+    rtc_memory: [u8; 0x100],
+    rtc_addr: u8,
+    command: u8,
+    result: u8,
+    // Host time of day 0, minute 0
+    rtc_base: u64,
+}
+
+impl<RAM, ROM> HuC3<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(ram: RAM, rom: ROM, rom_banks: usize, mut peripherals: Peripherals) -> Self {
+        let ram_banks = ram.as_ref().len() / 0x2000;
+        let rtc_base = peripherals.now();
+        Self {
+            ram,
+            rom,
+            rom_banks,
+            ram_banks,
+            peripherals,
+            mode: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            rtc_memory: [0; 0x100],
+            rtc_addr: 0,
+            command: 0,
+            result: 0,
+            rtc_base,
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        match self.ram_banks {
+            0 => None,
+            n => Some((self.ram_bank % n) * 0x2000 + (addr - 0xA000) as usize),
+        }
+    }
+
+    // Copy the clock to RTC memory 00-05
+    fn latch_time(&mut self) {
+        let elapsed = self.peripherals.now().wrapping_sub(self.rtc_base);
+        let minutes = (elapsed % SECONDS_PER_DAY) / SECONDS_PER_MINUTE;
+        let days = (elapsed / SECONDS_PER_DAY) & 0xFFF;
+        for i in 0..3 {
+            self.rtc_memory[i] = ((minutes >> (i * 4)) & 0x0F) as u8;
+            self.rtc_memory[3 + i] = ((days >> (i * 4)) & 0x0F) as u8;
+        }
+    }
+
+    // Set the clock from RTC memory 00-05
+    fn set_time(&mut self) {
+        let mut minutes = 0;
+        let mut days = 0;
+        for i in 0..3 {
+            minutes |= (self.rtc_memory[i] as u64) << (i * 4);
+            days |= (self.rtc_memory[3 + i] as u64) << (i * 4);
+        }
+        let elapsed = days * SECONDS_PER_DAY + minutes * SECONDS_PER_MINUTE;
+        self.rtc_base = self.peripherals.now().wrapping_sub(elapsed);
+    }
+
+    fn exec_command(&mut self, value: u8) {
+        self.command = (value >> 4) & 0x07;
+        let arg = value & 0x0F;
+        match self.command {
+            // Read the nibble at the address, then increment it
+            0x1 => {
+                self.result = self.rtc_memory[self.rtc_addr as usize];
+                self.rtc_addr = self.rtc_addr.wrapping_add(1);
+            }
+            // Write the nibble at the address, then increment it
+            0x3 => {
+                self.rtc_memory[self.rtc_addr as usize] = arg;
+                self.rtc_addr = self.rtc_addr.wrapping_add(1);
+            }
+            // Address low / high nibble
+            0x4 => self.rtc_addr = (self.rtc_addr & 0xF0) | arg,
+            0x5 => self.rtc_addr = (self.rtc_addr & 0x0F) | (arg << 4),
+            // Extended commands
+            0x6 => match arg {
+                0x0 => self.latch_time(),
+                0x1 => self.set_time(),
+                0x2 => self.result = 0x01, // Status: ready
+                0xE => self.peripherals.play_tone(self.rtc_memory[0x27]),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+impl<RAM, ROM> Cartridge for HuC3<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank % self.rom_banks,
+        }
+    }
+    // Host time of day 0 (little endian), then the RTC memory
+    fn rtc(&self) -> Option<Vec<u8>> {
+        Some([&self.rtc_base.to_le_bytes()[..], &self.rtc_memory].concat())
+    }
+    fn set_rtc(&mut self, rtc: &[u8]) {
+        if let Some((base, memory)) = rtc.split_first_chunk::<8>()
+            && memory.len() == self.rtc_memory.len()
+        {
+            self.rtc_base = u64::from_le_bytes(*base);
+            self.rtc_memory.copy_from_slice(memory);
+        }
+    }
+}
+
+impl<RAM, ROM> Memory for HuC3<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–7FFF — ROM Bank 00-7F
+            0x4000..=0x7FFF => {
+                self.rom[(self.rom_bank % self.rom_banks) * 0x4000 + addr as usize - 0x4000]
+            }
+            0xA000..=0xBFFF => match self.mode {
+                // RAM
+                0x00 | 0x0A => match self.ram_offset(addr) {
+                    Some(offset) => self.ram[offset],
+                    None => 0xFF,
+                },
+                // Last command and its result
+                0x0C => 0x80 | (self.command << 4) | self.result,
+                // Semaphore: always ready
+                0x0D => 0xFF,
+                // IR receiver
+                0x0E => 0xC0 | self.peripherals.light_detected() as u8,
+                _ => 0xFF,
+            },
+            _ => panic!("HuC3 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–1FFF — A000-BFFF mode
+            0x0000..=0x1FFF => self.mode = value & 0x0F,
+            // 2000–3FFF — ROM Bank Number
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x7F) as usize,
+            // 4000–5FFF — RAM Bank Number
+            0x4000..=0x5FFF => self.ram_bank = (value & 0x03) as usize,
+            // 6000–7FFF — Nothing
+            0x6000..=0x7FFF => {}
+            0xA000..=0xBFFF => match self.mode {
+                // RAM
+                0x0A => {
+                    if let Some(offset) = self.ram_offset(addr) {
+                        self.ram[offset] = value;
+                    }
+                }
+                // RTC command
+                0x0B => self.exec_command(value),
+                // IR LED
+                0x0E => self.peripherals.set_led(value & 0x01 != 0),
+                _ => {}
+            },
+            _ => panic!("HuC3 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::peripherals::TestClock;
+    use alloc::vec;
+
+    type Cart = HuC3<Vec<u8>, Vec<u8>>;
+
+    fn command(cart: &mut Cart, value: u8) {
+        cart.write(0x0000, 0x0B);
+        cart.write(0xA000, value);
+    }
+
+    fn set_addr(cart: &mut Cart, addr: u8) {
+        command(cart, 0x40 | (addr & 0x0F));
+        command(cart, 0x50 | (addr >> 4));
+    }
+
+    fn read_nibbles(cart: &mut Cart, addr: u8, count: usize) -> Vec<u8> {
+        set_addr(cart, addr);
+        (0..count)
+            .map(|_| {
+                command(cart, 0x10);
+                cart.write(0x0000, 0x0C);
+                cart.read(0xA000) & 0x0F
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clock_survives_a_save() {
+        let clock = TestClock::default();
+        clock.advance(1_000_000);
+        let mut cart = HuC3::new(vec![0; 0x2000], vec![0; 0x8000], 2, clock.peripherals());
+        // Day 3, minute 90, then a tone at 0x27
+        set_addr(&mut cart, 0x00);
+        for nibble in [0xA, 0x5, 0x0, 0x3, 0x0, 0x0] {
+            command(&mut cart, 0x30 | nibble);
+        }
+        command(&mut cart, 0x61);
+        set_addr(&mut cart, 0x27);
+        command(&mut cart, 0x35);
+        let rtc = cart.rtc().unwrap();
+
+        clock.advance(86400 + 30 * 60);
+        let mut cart = HuC3::new(vec![0; 0x2000], vec![0; 0x8000], 2, clock.peripherals());
+        cart.set_rtc(&rtc);
+        command(&mut cart, 0x60);
+        // Minute 120 of day 4
+        assert_eq!(
+            read_nibbles(&mut cart, 0x00, 6),
+            [0x8, 0x7, 0x0, 0x4, 0x0, 0x0]
+        );
+        assert_eq!(read_nibbles(&mut cart, 0x27, 1), [0x5]);
+    }
+
+    #[test]
+    fn invalid_rtc_is_ignored() {
+        let clock = TestClock::default();
+        let mut cart = HuC3::new(vec![0; 0x2000], vec![0; 0x8000], 2, clock.peripherals());
+        let rtc = cart.rtc().unwrap();
+        cart.set_rtc(&[0xFF; 12]);
+        assert_eq!(cart.rtc().unwrap(), rtc);
+    }
+}
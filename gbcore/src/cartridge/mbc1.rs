@@ -4,13 +4,16 @@ use core::ops::{Index, IndexMut};
 
 pub struct MBC1<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
     banking_mode_select: bool,
+    // MBC1M: BANK2 is wired to ROM bank bits 4-5 instead of 5-6
+    multicart: bool,
 
     ram: RAM,
     rom: ROM,
+    rom_banks: usize,
 
     ram_bank: u8,
     rom_bank: u8,
@@ -24,14 +27,22 @@ where
 
 impl<RAM, ROM> MBC1<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
-    pub fn new(ram: RAM, rom: ROM, banking_mode_select: bool) -> Self {
+    pub fn new(
+        ram: RAM,
+        rom: ROM,
+        rom_banks: usize,
+        banking_mode_select: bool,
+        multicart: bool,
+    ) -> Self {
         Self {
             banking_mode_select,
+            multicart,
             ram,
             rom,
+            rom_banks,
             ram_bank: 0,
             lower_rom_bank: 0,
             rom_bank: 1,
@@ -49,41 +60,61 @@ where
         }
     }
     fn update_rom_bank(&mut self) {
-        match self.banked_reg {
-            true => {
-                self.lower_rom_bank = self.bank_or_upper_rom_bank_reg << 5;
-                self.rom_bank = self.rom_bank_reg;
-            }
-            false => {
-                self.lower_rom_bank = 0;
-                self.rom_bank = (self.bank_or_upper_rom_bank_reg << 5) + self.rom_bank_reg;
-            }
+        let (bank, shift) = match self.multicart {
+            true => (self.rom_bank_reg & 0b1111, 4),
+            false => (self.rom_bank_reg, 5),
         };
+        let upper_bank = self.bank_or_upper_rom_bank_reg << shift;
+        // Mode 1 also applies the upper bits to 0000-3FFF
+        self.lower_rom_bank = match self.banked_reg {
+            true => upper_bank,
+            false => 0,
+        };
+        self.rom_bank = upper_bank | bank;
+    }
+
+    // None without RAM. Banks past the end of the RAM wrap around.
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        let len = self.ram.as_ref().len();
+        (len != 0).then(|| (self.ram_bank as usize * 0x2000 + (addr - 0xA000) as usize) % len)
     }
 }
 
 impl<RAM, ROM> Cartridge for MBC1<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => self.lower_rom_bank as usize % self.rom_banks,
+            _ => self.rom_bank as usize % self.rom_banks,
+        }
+    }
 }
 
 impl<RAM, ROM> Memory for MBC1<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
             // 0000–3FFF — ROM Bank X0
-            0x0000..=0x3FFF => self.rom[self.lower_rom_bank as usize * 0x4000 + addr as usize],
+            0x0000..=0x3FFF => self.rom[self.rom_bank(addr) * 0x4000 + addr as usize],
             // 4000–7FFF — ROM Bank 01-7F
-            0x4000..=0x7FFF => self.rom[self.rom_bank as usize * 0x4000 + addr as usize - 0x4000],
+            0x4000..=0x7FFF => self.rom[self.rom_bank(addr) * 0x4000 + addr as usize - 0x4000],
             // A000–BFFF — RAM Bank 00–03, if any
-            0xA000..=0xBFFF => match self.ram_enable {
-                true => self.ram[self.ram_bank as usize * 0x2000 + addr as usize - 0xA000],
-                false => 0x00,
+            0xA000..=0xBFFF => match (self.ram_enable, self.ram_offset(addr)) {
+                (true, Some(offset)) => self.ram[offset],
+                (true, None) => 0xFF,
+                (false, _) => 0x00,
             },
             _ => panic!("MBC1 read out of range: {:#04x}", addr),
         }
@@ -122,8 +153,10 @@ where
             }
             // A000–BFFF — RAM Bank 00–03, if any
             0xA000..=0xBFFF => {
-                if self.ram_enable {
-                    self.ram[self.ram_bank as usize * 0x2000 + addr as usize - 0xA000] = value;
+                if self.ram_enable
+                    && let Some(offset) = self.ram_offset(addr)
+                {
+                    self.ram[offset] = value;
                 }
             }
 
@@ -131,3 +164,60 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::numbered_banks;
+    extern crate alloc;
+    use alloc::vec;
+
+    #[test]
+    fn mode_1_banking() {
+        let mut mbc = MBC1::new(vec![0; 0x8000], numbered_banks(128), 128, true, false);
+        mbc.write(0x2000, 0x00);
+        mbc.write(0x4000, 0x02);
+        assert_eq!(mbc.read(0x4000), 0x41);
+        assert_eq!(mbc.read(0x0000), 0x00);
+        // The upper bits select the bank at 0000-3FFF and the RAM bank
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read(0x0000), 0x40);
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0xA000, 0x12);
+        mbc.write(0x6000, 0x00);
+        assert_eq!(mbc.read(0xA000), 0x00);
+        assert_eq!(mbc.read(0x0000), 0x00);
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read(0xA000), 0x12);
+    }
+
+    #[test]
+    fn multicart_banking() {
+        let mut rom = numbered_banks(64);
+        for game in 0..4 {
+            rom[game * 0x40000 + 0x104..game * 0x40000 + 0x134]
+                .copy_from_slice(&crate::cartridge::header::NINTENDO_LOGO);
+        }
+        assert!(crate::cartridge::is_mbc1_multicart(&rom));
+        assert!(!crate::cartridge::is_mbc1_multicart(&rom[..0x80000]));
+
+        let mut mbc = MBC1::new(vec![], rom, 64, true, true);
+        // BANK2 selects the game, BANK1 only has 4 bits
+        mbc.write(0x4000, 0x02);
+        mbc.write(0x2000, 0x13);
+        assert_eq!(mbc.read(0x4000), 0x23);
+        mbc.write(0x6000, 0x01);
+        assert_eq!(mbc.read(0x0000), 0x20);
+        // Bank 0 remaps to 1 before the mask: 0x10 selects the game's bank 0
+        mbc.write(0x2000, 0x10);
+        assert_eq!(mbc.read(0x4000), 0x20);
+    }
+
+    #[test]
+    fn enabled_ram_without_ram_chip() {
+        let mut mbc = MBC1::new(vec![], vec![0; 0x8000], 2, false, false);
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0xA000, 0x12);
+        assert_eq!(mbc.read(0xA000), 0xFF);
+    }
+}
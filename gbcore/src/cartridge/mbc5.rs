@@ -4,44 +4,67 @@ use core::ops::{Index, IndexMut};
 
 pub struct MBC5<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
     ram: RAM,
     rom: ROM,
+    rom_banks: usize,
 
     ram_bank: usize,
     rom_bank: usize,
 
     ram_enable: bool,
+    // Bit 3 of the RAM bank number drives the rumble motor
+    rumble: bool,
 }
 
 impl<RAM, ROM> MBC5<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
-    pub fn new(ram: RAM, rom: ROM) -> Self {
+    pub fn new(ram: RAM, rom: ROM, rom_banks: usize, rumble: bool) -> Self {
         Self {
             ram,
             rom,
+            rom_banks,
             ram_bank: 0,
             rom_bank: 0,
             ram_enable: false,
+            rumble,
         }
     }
+
+    // None without RAM. Banks past the end of the RAM wrap around.
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        let len = self.ram.as_ref().len();
+        (len != 0).then(|| (self.ram_bank * 0x2000 + (addr - 0xA000) as usize) % len)
+    }
 }
 
 impl<RAM, ROM> Cartridge for MBC5<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank % self.rom_banks,
+        }
+    }
 }
 
 impl<RAM, ROM> Memory for MBC5<RAM, ROM>
 where
-    RAM: IndexMut<usize, Output = u8>,
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
     fn read(&mut self, addr: u16) -> u8 {
@@ -49,11 +72,14 @@ where
             // 0000–3FFF — ROM Bank 00
             0x0000..=0x3FFF => self.rom[addr as usize],
             // 4000–7FFF —  ROM bank 00-1FF
-            0x4000..=0x7FFF => self.rom[self.rom_bank * 0x4000 + addr as usize - 0x4000],
+            0x4000..=0x7FFF => {
+                self.rom[(self.rom_bank % self.rom_banks) * 0x4000 + addr as usize - 0x4000]
+            }
             // A000–BFFF — RAM bank 00-0F, if any
-            0xA000..=0xBFFF => match self.ram_enable {
-                true => self.ram[self.ram_bank * 0x2000 + addr as usize - 0xA000],
-                false => 0x00,
+            0xA000..=0xBFFF => match (self.ram_enable, self.ram_offset(addr)) {
+                (true, Some(offset)) => self.ram[offset],
+                (true, None) => 0xFF,
+                (false, _) => 0x00,
             },
             _ => panic!("MBC5 read out of range: {:#04x}", addr),
         }
@@ -71,17 +97,40 @@ where
                     (self.rom_bank & !(1 << 8)) | ((((value & 0x01) != 0x00) as usize) << 8)
             }
             // 4000-5FFF - RAM bank number
-            0x4000..=0x5FFF => self.ram_bank = value as usize & 0x0F,
+            0x4000..=0x5FFF => {
+                let mask = if self.rumble { 0x07 } else { 0x0F };
+                self.ram_bank = value as usize & mask
+            }
             // 6000-7FFF - Latch Clock Data
             0x6000..=0x7FFF => {
                 // TODO
             }
             // A000–BFFF — RAM bank 00-0F, if any
-            0xA000..=0xBFFF => match self.ram_enable {
-                true => self.ram[self.ram_bank * 0x2000 + addr as usize - 0xA000] = value,
-                false => {}
-            },
+            0xA000..=0xBFFF => {
+                if self.ram_enable
+                    && let Some(offset) = self.ram_offset(addr)
+                {
+                    self.ram[offset] = value;
+                }
+            }
             _ => panic!("MBC5 write out of range: {:#04x}", addr),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate alloc;
+    use alloc::vec;
+
+    #[test]
+    fn ram_bank_past_ram_size_wraps() {
+        let mut mbc = MBC5::new(vec![0; 0x2000], vec![0; 0x8000], 2, false);
+        mbc.write(0x0000, 0x0A);
+        mbc.write(0x4000, 0x03);
+        mbc.write(0xA123, 0x12);
+        mbc.write(0x4000, 0x00);
+        assert_eq!(mbc.read(0xA123), 0x12);
+    }
+}
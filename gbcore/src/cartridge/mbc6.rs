@@ -0,0 +1,301 @@
+use super::Cartridge;
+use crate::Memory;
+use core::ops::Index;
+
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::vec;
+
+const FLASH_SIZE: usize = 0x100000;
+const FLASH_BANK_SIZE: usize = 0x2000;
+
+// Macronix MX29F008 command states
+#[derive(Clone, Copy, PartialEq)]
+enum FlashState {
+    Read,
+    // Unlock cycles: AA at 5555, 55 at 2AAA
+    Unlock1,
+    Unlock2,
+    // Manufacturer and device IDs instead of data
+    Id,
+    // 80 received, waiting for a second unlock then the erase command
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+    // A0 received, next write programs a byte
+    Program,
+}
+
+// Net de Get: two 8 KiB windows on ROM or flash, two 4 KiB RAM windows
+pub struct MBC6<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    rom: ROM,
+    rom_banks: usize,
+    // RAM followed by the flash, saved together
+    storage: Box<[u8]>,
+    ram_size: usize,
+
+    ram_enable: bool,
+    ram_bank: [usize; 2],
+    // ROM or flash bank for 4000-5FFF and 6000-7FFF
+    bank: [usize; 2],
+    flash_selected: [bool; 2],
+
+    flash_enable: bool,
+    flash_write_enable: bool,
+    flash_state: FlashState,
+}
+
+impl<ROM> MBC6<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(rom: ROM, rom_banks: usize, ram_size: usize) -> Self {
+        let mut storage = vec![0u8; ram_size + FLASH_SIZE].into_boxed_slice();
+        storage[ram_size..].fill(0xFF);
+        Self {
+            rom,
+            rom_banks,
+            storage,
+            ram_size,
+            ram_enable: false,
+            ram_bank: [0, 0],
+            bank: [0, 0],
+            flash_selected: [false, false],
+            flash_enable: false,
+            flash_write_enable: false,
+            flash_state: FlashState::Read,
+        }
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        let banks = self.ram_size / 0x1000;
+        if !self.ram_enable || banks == 0 {
+            return None;
+        }
+        let window = ((addr - 0xA000) / 0x1000) as usize;
+        Some((self.ram_bank[window] % banks) * 0x1000 + (addr as usize & 0x0FFF))
+    }
+
+    // Bank mapped in a window, wrapped to the size of the ROM or flash
+    fn banked(&self, window: usize) -> usize {
+        match self.flash_selected[window] {
+            true => self.bank[window] % (FLASH_SIZE / FLASH_BANK_SIZE),
+            false => self.bank[window] % (self.rom_banks * 2),
+        }
+    }
+
+    fn flash_addr(&self, window: usize, addr: u16) -> usize {
+        self.banked(window) * FLASH_BANK_SIZE + (addr as usize & 0x1FFF)
+    }
+
+    fn read_banked(&self, addr: u16) -> u8 {
+        let window = ((addr - 0x4000) / 0x2000) as usize;
+        if self.flash_selected[window] {
+            let flash_addr = self.flash_addr(window, addr);
+            return match self.flash_state {
+                FlashState::Id => match flash_addr & 0x01 {
+                    0 => 0xC2, // Macronix
+                    _ => 0x81,
+                },
+                _ if self.flash_enable => self.storage[self.ram_size + flash_addr],
+                _ => 0xFF,
+            };
+        }
+        self.rom[self.banked(window) * 0x2000 + (addr as usize & 0x1FFF)]
+    }
+
+    fn write_flash(&mut self, window: usize, addr: u16, value: u8) {
+        if !self.flash_selected[window] || !self.flash_enable {
+            return;
+        }
+        let flash_addr = self.flash_addr(window, addr);
+        let command_addr = flash_addr & 0x7FFF;
+        self.flash_state = match (self.flash_state, command_addr, value) {
+            (_, _, 0xF0) => FlashState::Read,
+            (FlashState::Read | FlashState::Id, 0x5555, 0xAA) => FlashState::Unlock1,
+            (FlashState::Unlock1, 0x2AAA, 0x55) => FlashState::Unlock2,
+            (FlashState::Unlock2, 0x5555, 0x90) => FlashState::Id,
+            (FlashState::Unlock2, 0x5555, 0x80) => FlashState::Erase,
+            (FlashState::Unlock2, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Erase, 0x5555, 0xAA) => FlashState::EraseUnlock1,
+            (FlashState::EraseUnlock1, 0x2AAA, 0x55) => FlashState::EraseUnlock2,
+            // Sector erase, sectors are handled as 8 KiB banks
+            (FlashState::EraseUnlock2, _, 0x30) => {
+                if self.flash_write_enable {
+                    let start = self.ram_size + flash_addr - flash_addr % FLASH_BANK_SIZE;
+                    self.storage[start..start + FLASH_BANK_SIZE].fill(0xFF);
+                }
+                FlashState::Read
+            }
+            // Chip erase
+            (FlashState::EraseUnlock2, 0x5555, 0x10) => {
+                if self.flash_write_enable {
+                    self.storage[self.ram_size..].fill(0xFF);
+                }
+                FlashState::Read
+            }
+            // Programming can only clear bits
+            (FlashState::Program, _, _) => {
+                if self.flash_write_enable {
+                    self.storage[self.ram_size + flash_addr] &= value;
+                }
+                FlashState::Read
+            }
+            (FlashState::Id, _, _) => FlashState::Id,
+            _ => FlashState::Read,
+        };
+    }
+}
+
+impl<ROM> Cartridge for MBC6<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(&self.storage)
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.storage)
+    }
+    // 8 KiB banks, flash or ROM depending on the window
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => addr as usize / 0x2000,
+            _ => self.banked(((addr - 0x4000) / 0x2000) as usize),
+        }
+    }
+}
+
+impl<ROM> Memory for MBC6<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–5FFF / 6000–7FFF — ROM or flash bank A / B
+            0x4000..=0x7FFF => self.read_banked(addr),
+            // A000–AFFF / B000–BFFF — RAM bank A / B
+            0xA000..=0xBFFF => match self.ram_offset(addr) {
+                Some(offset) => self.storage[offset],
+                None => 0xFF,
+            },
+            _ => panic!("MBC6 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–03FF — RAM Enable
+            0x0000..=0x03FF => self.ram_enable = value & 0x0F == 0x0A,
+            // 0400–07FF / 0800–0BFF — RAM bank A / B
+            0x0400..=0x07FF => self.ram_bank[0] = (value & 0x07) as usize,
+            0x0800..=0x0BFF => self.ram_bank[1] = (value & 0x07) as usize,
+            // 0C00–0FFF — Flash enable
+            0x0C00..=0x0FFF => self.flash_enable = value & 0x01 != 0,
+            // 1000 — Flash write enable
+            0x1000 => self.flash_write_enable = value & 0x01 != 0,
+            0x1001..=0x1FFF => {}
+            // 2000–27FF / 3000–37FF — Bank A / B number
+            0x2000..=0x27FF => self.bank[0] = (value & 0x7F) as usize,
+            0x3000..=0x37FF => self.bank[1] = (value & 0x7F) as usize,
+            // 2800–2FFF / 3800–3FFF — Bank A / B source: 00 ROM, 08 flash
+            0x2800..=0x2FFF => self.flash_selected[0] = value == 0x08,
+            0x3800..=0x3FFF => self.flash_selected[1] = value == 0x08,
+            // 4000–5FFF / 6000–7FFF — Flash commands and data
+            0x4000..=0x5FFF => self.write_flash(0, addr, value),
+            0x6000..=0x7FFF => self.write_flash(1, addr, value),
+            0xA000..=0xBFFF => {
+                if let Some(offset) = self.ram_offset(addr) {
+                    self.storage[offset] = value;
+                }
+            }
+            _ => panic!("MBC6 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    type Cart = MBC6<Vec<u8>>;
+
+    // Flash in both windows: bank 2 puts 5555 in A, bank 1 puts 2AAA in B
+    fn flash_cart() -> Cart {
+        let mut cart = MBC6::new(vec![0; 0x8000], 2, 0x8000);
+        cart.write(0x0C00, 0x01);
+        cart.write(0x2000, 0x02);
+        cart.write(0x2800, 0x08);
+        cart.write(0x3000, 0x01);
+        cart.write(0x3800, 0x08);
+        cart
+    }
+
+    fn unlock(cart: &mut Cart) {
+        cart.write(0x5555, 0xAA);
+        cart.write(0x6AAA, 0x55);
+    }
+
+    fn command(cart: &mut Cart, command: u8) {
+        unlock(cart);
+        cart.write(0x5555, command);
+    }
+
+    fn program(cart: &mut Cart, addr: u16, value: u8) {
+        command(cart, 0xA0);
+        cart.write(addr, value);
+    }
+
+    #[test]
+    fn banked_rom() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x2000 * 3 + 0x10] = 0x12;
+        let mut cart = MBC6::new(rom, 2, 0);
+        cart.write(0x3000, 0x03);
+        assert_eq!(cart.read(0x6010), 0x12);
+        assert_eq!(cart.rom_bank(0x6010), 3);
+        // 4 banks of 8 KiB: bank 7 is a mirror of bank 3
+        cart.write(0x3000, 0x07);
+        assert_eq!(cart.read(0x6010), 0x12);
+        assert_eq!(cart.rom_bank(0x6010), 3);
+    }
+
+    #[test]
+    fn flash_commands() {
+        let mut cart = flash_cart();
+        command(&mut cart, 0x90);
+        assert_eq!([cart.read(0x4000), cart.read(0x4001)], [0xC2, 0x81]);
+        cart.write(0x4000, 0xF0);
+        assert_eq!(cart.read(0x4000), 0xFF);
+
+        // Write protected
+        program(&mut cart, 0x6010, 0x0F);
+        assert_eq!(cart.read(0x6010), 0xFF);
+
+        // Programming only clears bits
+        cart.write(0x1000, 0x01);
+        program(&mut cart, 0x6010, 0x0F);
+        program(&mut cart, 0x6010, 0xF3);
+        assert_eq!(cart.read(0x6010), 0x03);
+        assert_eq!(cart.ram().unwrap()[0x8000 + 0x2010], 0x03);
+
+        // Sector erase of bank 1 only
+        program(&mut cart, 0x4000, 0x00);
+        command(&mut cart, 0x80);
+        unlock(&mut cart);
+        cart.write(0x6000, 0x30);
+        assert_eq!(cart.read(0x6010), 0xFF);
+        assert_eq!(cart.read(0x4000), 0x00);
+
+        // Chip erase
+        command(&mut cart, 0x80);
+        command(&mut cart, 0x10);
+        assert_eq!(cart.read(0x4000), 0xFF);
+    }
+}
@@ -0,0 +1,391 @@
+use super::{Cartridge, Peripherals};
+use crate::Memory;
+use core::ops::Index;
+
+// Accelerometer value at rest and change for 1 g
+const ACCEL_CENTER: f32 = 0x81D0 as f32;
+const ACCEL_GRAVITY: f32 = 0x70 as f32;
+
+// Kirby Tilt 'n' Tumble, Command Master: accelerometer and serial EEPROM
+pub struct MBC7<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    rom: ROM,
+    rom_banks: usize,
+    peripherals: Peripherals,
+
+    rom_bank: usize,
+    ram_enable_1: bool,
+    ram_enable_2: bool,
+
+    // Latched accelerometer values
+    accel_x: u16,
+    accel_y: u16,
+    accel_erased: bool,
+
+    eeprom: Eeprom93LC56,
+}
+
+impl<ROM> MBC7<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(rom: ROM, rom_banks: usize, peripherals: Peripherals) -> Self {
+        Self {
+            rom,
+            rom_banks,
+            peripherals,
+            rom_bank: 1,
+            ram_enable_1: false,
+            ram_enable_2: false,
+            accel_x: 0x8000,
+            accel_y: 0x8000,
+            accel_erased: false,
+            eeprom: Eeprom93LC56::default(),
+        }
+    }
+
+    fn registers_enabled(&self) -> bool {
+        self.ram_enable_1 && self.ram_enable_2
+    }
+
+    fn latch_accelerometer(&mut self) {
+        let (x, y) = self.peripherals.tilt();
+        self.accel_x = (ACCEL_CENTER + x * ACCEL_GRAVITY) as u16;
+        self.accel_y = (ACCEL_CENTER + y * ACCEL_GRAVITY) as u16;
+    }
+}
+
+impl<ROM> Cartridge for MBC7<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    // The EEPROM content is the save data
+    fn ram(&self) -> Option<&[u8]> {
+        Some(&self.eeprom.data)
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.eeprom.data)
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank % self.rom_banks,
+        }
+    }
+}
+
+impl<ROM> Memory for MBC7<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–7FFF — ROM Bank 00-7F
+            0x4000..=0x7FFF => {
+                self.rom[(self.rom_bank % self.rom_banks) * 0x4000 + addr as usize - 0x4000]
+            }
+            // A000–AFFF — Registers, selected by address bits 4-7
+            0xA000..=0xAFFF if self.registers_enabled() => match addr & 0xF0 {
+                0x20 => self.accel_x as u8,
+                0x30 => (self.accel_x >> 8) as u8,
+                0x40 => self.accel_y as u8,
+                0x50 => (self.accel_y >> 8) as u8,
+                0x60 => 0x00,
+                0x80 => self.eeprom.read(),
+                _ => 0xFF,
+            },
+            0xA000..=0xBFFF => 0xFF,
+            _ => panic!("MBC7 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–1FFF — RAM Enable 1
+            0x0000..=0x1FFF => self.ram_enable_1 = value & 0x0F == 0x0A,
+            // 2000–3FFF — ROM Bank Number
+            0x2000..=0x3FFF => self.rom_bank = (value & 0x7F) as usize,
+            // 4000–5FFF — RAM Enable 2
+            0x4000..=0x5FFF => self.ram_enable_2 = value == 0x40,
+            0x6000..=0x7FFF => {}
+            0xA000..=0xAFFF if self.registers_enabled() => match addr & 0xF0 {
+                // Erase the latched values
+                0x00 if value == 0x55 => {
+                    self.accel_x = 0x8000;
+                    self.accel_y = 0x8000;
+                    self.accel_erased = true;
+                }
+                // Latch the accelerometer, once after each erase
+                0x10 if value == 0xAA && self.accel_erased => {
+                    self.latch_accelerometer();
+                    self.accel_erased = false;
+                }
+                0x80 => self.eeprom.write(value),
+                _ => {}
+            },
+            0xA000..=0xBFFF => {}
+            _ => panic!("MBC7 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+enum EepromState {
+    // Waiting for the start bit
+    Idle,
+    // Receiving the 2 bits opcode and 8 bits address
+    Command {
+        bits: u16,
+        count: u8,
+    },
+    // Sending a word, MSB first
+    Read {
+        word: u16,
+        count: u8,
+    },
+    // Receiving a word for an address, or for all of them
+    Write {
+        addr: Option<u8>,
+        word: u16,
+        count: u8,
+    },
+}
+
+// 2 Kbit serial EEPROM organised as 128 16-bit words
+struct Eeprom93LC56 {
+    data: [u8; 256],
+    state: EepromState,
+    write_enable: bool,
+
+    cs: bool,
+    clk: bool,
+    di: bool,
+    do_: bool,
+}
+
+impl Default for Eeprom93LC56 {
+    fn default() -> Self {
+        Self {
+            data: [0xFF; 256],
+            state: EepromState::Idle,
+            write_enable: false,
+            cs: false,
+            clk: false,
+            di: false,
+            do_: true,
+        }
+    }
+}
+
+impl Eeprom93LC56 {
+    // Bit 7: CS, bit 6: CLK, bit 1: DI, bit 0: DO
+    fn read(&self) -> u8 {
+        (self.cs as u8) << 7 | (self.clk as u8) << 6 | (self.di as u8) << 1 | self.do_ as u8
+    }
+
+    fn write(&mut self, value: u8) {
+        let cs = value & 0x80 != 0;
+        let clk = value & 0x40 != 0;
+        self.di = value & 0x02 != 0;
+
+        if !cs {
+            self.state = EepromState::Idle;
+        } else if clk && !self.clk {
+            self.clock_rising_edge();
+        }
+        self.cs = cs;
+        self.clk = clk;
+    }
+
+    fn word(&self, addr: u8) -> u16 {
+        let idx = (addr & 0x7F) as usize * 2;
+        u16::from_le_bytes([self.data[idx], self.data[idx + 1]])
+    }
+
+    fn set_word(&mut self, addr: u8, word: u16) {
+        if self.write_enable {
+            let idx = (addr & 0x7F) as usize * 2;
+            self.data[idx..idx + 2].copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    fn clock_rising_edge(&mut self) {
+        let di = self.di as u16;
+        self.state = match core::mem::replace(&mut self.state, EepromState::Idle) {
+            EepromState::Idle if self.di => EepromState::Command { bits: 0, count: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, count } => {
+                let bits = (bits << 1) | di;
+                match count + 1 {
+                    10 => self.exec_command((bits >> 8) as u8, bits as u8),
+                    count => EepromState::Command { bits, count },
+                }
+            }
+            EepromState::Read { word, count } => {
+                self.do_ = word & (0x8000 >> count) != 0;
+                match count + 1 {
+                    16 => EepromState::Idle,
+                    count => EepromState::Read { word, count },
+                }
+            }
+            EepromState::Write { addr, word, count } => {
+                let word = (word << 1) | di;
+                match (count + 1, addr) {
+                    (16, Some(addr)) => {
+                        self.set_word(addr, word);
+                        self.do_ = true; // Ready
+                        EepromState::Idle
+                    }
+                    (16, None) => {
+                        for addr in 0..128 {
+                            self.set_word(addr, word);
+                        }
+                        self.do_ = true;
+                        EepromState::Idle
+                    }
+                    (count, _) => EepromState::Write { addr, word, count },
+                }
+            }
+        };
+    }
+
+    fn exec_command(&mut self, opcode: u8, addr: u8) -> EepromState {
+        match (opcode, addr >> 6) {
+            // READ: a dummy 0 then the word
+            (0b10, _) => {
+                self.do_ = false;
+                EepromState::Read {
+                    word: self.word(addr),
+                    count: 0,
+                }
+            }
+            // WRITE
+            (0b01, _) => EepromState::Write {
+                addr: Some(addr),
+                word: 0,
+                count: 0,
+            },
+            // ERASE
+            (0b11, _) => {
+                self.set_word(addr, 0xFFFF);
+                self.do_ = true;
+                EepromState::Idle
+            }
+            // EWDS: write disable
+            (0b00, 0b00) => {
+                self.write_enable = false;
+                EepromState::Idle
+            }
+            // WRAL: write all
+            (0b00, 0b01) => EepromState::Write {
+                addr: None,
+                word: 0,
+                count: 0,
+            },
+            // ERAL: erase all
+            (0b00, 0b10) => {
+                for addr in 0..128 {
+                    self.set_word(addr, 0xFFFF);
+                }
+                self.do_ = true;
+                EepromState::Idle
+            }
+            // EWEN: write enable
+            _ => {
+                self.write_enable = true;
+                EepromState::Idle
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::Tilt;
+    extern crate alloc;
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    type Cart = MBC7<Vec<u8>>;
+
+    fn enabled(peripherals: Peripherals) -> Cart {
+        let mut cart = MBC7::new(vec![0; 0x8000], 2, peripherals);
+        cart.write(0x0000, 0x0A);
+        cart.write(0x4000, 0x40);
+        cart
+    }
+
+    // Clocks `bits` (MSB first) into the EEPROM, returns DO after each edge
+    fn clock(cart: &mut Cart, bits: u32, count: u8) -> u32 {
+        (0..count).rev().fold(0, |out, i| {
+            let di = ((bits >> i) as u8 & 0x01) << 1;
+            cart.write(0xA080, 0x80 | di);
+            cart.write(0xA080, 0xC0 | di);
+            (out << 1) | (cart.read(0xA080) & 0x01) as u32
+        })
+    }
+
+    // Start bit, opcode and address, then `data` bits
+    fn command(cart: &mut Cart, opcode: u32, addr: u8, data: u32, count: u8) -> u32 {
+        clock(cart, 0b100 | opcode, 3);
+        clock(cart, addr as u32, 8);
+        let out = clock(cart, data, count);
+        cart.write(0xA080, 0x00);
+        out
+    }
+
+    #[test]
+    fn eeprom() {
+        let mut cart = enabled(Peripherals::default());
+        // Write protected until EWEN
+        command(&mut cart, 0b01, 0x05, 0x1234, 16);
+        assert_eq!(command(&mut cart, 0b10, 0x05, 0, 16), 0xFFFF);
+        command(&mut cart, 0b00, 0xC0, 0, 0);
+        command(&mut cart, 0b01, 0x05, 0x1234, 16);
+        assert_eq!(command(&mut cart, 0b10, 0x05, 0, 16), 0x1234);
+        assert_eq!(cart.ram().unwrap()[10..12], [0x34, 0x12]);
+
+        // WRAL, then ERASE one word
+        command(&mut cart, 0b00, 0x40, 0xABCD, 16);
+        command(&mut cart, 0b11, 0x7F, 0, 0);
+        assert_eq!(command(&mut cart, 0b10, 0x05, 0, 16), 0xABCD);
+        assert_eq!(command(&mut cart, 0b10, 0x7F, 0, 16), 0xFFFF);
+
+        // ERAL ignored after EWDS
+        command(&mut cart, 0b00, 0x00, 0, 0);
+        command(&mut cart, 0b00, 0x80, 0, 0);
+        assert_eq!(command(&mut cart, 0b10, 0x00, 0, 16), 0xABCD);
+    }
+
+    struct Tilted;
+
+    impl Tilt for Tilted {
+        fn tilt(&mut self) -> (f32, f32) {
+            (1.0, -0.5)
+        }
+    }
+
+    #[test]
+    fn accelerometer_latch() {
+        let mut cart = enabled(Peripherals {
+            tilt: Some(Box::new(Tilted)),
+            ..Default::default()
+        });
+        // Only after an erase
+        cart.write(0xA010, 0xAA);
+        assert_eq!([cart.read(0xA020), cart.read(0xA030)], [0x00, 0x80]);
+        cart.write(0xA000, 0x55);
+        cart.write(0xA010, 0xAA);
+        assert_eq!([cart.read(0xA020), cart.read(0xA030)], [0x40, 0x82]);
+        assert_eq!([cart.read(0xA040), cart.read(0xA050)], [0x98, 0x81]);
+
+        // Registers need both enables
+        cart.write(0x4000, 0x00);
+        assert_eq!(cart.read(0xA020), 0xFF);
+    }
+}
@@ -0,0 +1,231 @@
+use super::Cartridge;
+use crate::Memory;
+use core::ops::{Index, IndexMut};
+
+// Multicart mapper. Starts unmapped with the last 32 KiB (the menu) at
+// 0000-7FFF. The menu sets up the bank bits and masks of a game, then maps
+// it: the game sees an MBC1 restricted to its own banks.
+pub struct MMM01<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    ram: RAM,
+    rom: ROM,
+    rom_banks: usize,
+    ram_banks: usize,
+
+    mapped: bool,
+    ram_enable: bool,
+    banked_reg: bool, // MBC1 banking mode
+
+    rom_bank_low: u8,  // Bits 0-4
+    rom_bank_mid: u8,  // Bits 5-6, set while unmapped
+    rom_bank_high: u8, // Bits 7-8, set while unmapped
+    rom_bank_mask: u8, // Bits 1-4 of rom_bank_low locked once mapped
+
+    ram_bank_low: u8,  // Bits 0-1
+    ram_bank_high: u8, // Bits 2-3, set while unmapped
+    ram_bank_mask: u8, // Bits of ram_bank_low locked once mapped
+
+    mode_write_disable: bool,
+}
+
+impl<RAM, ROM> MMM01<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(ram: RAM, rom: ROM, rom_banks: usize) -> Self {
+        let ram_banks = ram.as_ref().len() / 0x2000;
+        Self {
+            ram,
+            rom,
+            rom_banks,
+            ram_banks,
+            mapped: false,
+            ram_enable: false,
+            banked_reg: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            mode_write_disable: false,
+        }
+    }
+
+    fn rom_offset(&self, bank: usize, addr: u16) -> usize {
+        (bank % self.rom_banks) * 0x4000 + (addr as usize & 0x3FFF)
+    }
+
+    fn upper_rom_bank(&self) -> usize {
+        ((self.rom_bank_high as usize) << 7) | ((self.rom_bank_mid as usize) << 5)
+    }
+
+    // 0000-3FFF: first bank of the selected game
+    fn lower_rom_bank(&self) -> usize {
+        if !self.mapped {
+            return self.rom_banks - 2;
+        }
+        let fixed = (self.rom_bank_mask << 1) & self.rom_bank_low;
+        self.upper_rom_bank() | fixed as usize
+    }
+
+    // 4000-7FFF
+    fn rom_bank(&self) -> usize {
+        if !self.mapped {
+            return self.rom_banks - 1;
+        }
+        // Bank 0 -> 1 remapping only looks at the bits the game controls
+        let writable = !(self.rom_bank_mask << 1) & 0b11111;
+        let mut low = self.rom_bank_low;
+        if low & writable == 0 {
+            low |= 1;
+        }
+        self.upper_rom_bank() | low as usize
+    }
+
+    fn ram_offset(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enable || self.ram_banks == 0 {
+            return None;
+        }
+        let low = match self.banked_reg {
+            true => self.ram_bank_low,
+            false => self.ram_bank_low & self.ram_bank_mask,
+        };
+        let bank = ((self.ram_bank_high << 2) | low) as usize % self.ram_banks;
+        Some(bank * 0x2000 + (addr - 0xA000) as usize)
+    }
+
+    // Only the bits not locked by `mask` can change once mapped
+    fn masked(&self, current: u8, value: u8, mask: u8) -> u8 {
+        match self.mapped {
+            true => (current & mask) | (value & !mask),
+            false => value,
+        }
+    }
+}
+
+impl<RAM, ROM> Cartridge for MMM01<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(self.ram.as_ref()).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(self.ram.as_mut()).filter(|ram| !ram.is_empty())
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x0000..=0x3FFF => self.lower_rom_bank(),
+            _ => self.rom_bank(),
+        };
+        bank % self.rom_banks
+    }
+}
+
+impl<RAM, ROM> Memory for MMM01<RAM, ROM>
+where
+    RAM: IndexMut<usize, Output = u8> + AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => self.rom[self.rom_offset(self.lower_rom_bank(), addr)],
+            0x4000..=0x7FFF => self.rom[self.rom_offset(self.rom_bank(), addr)],
+            0xA000..=0xBFFF => match self.ram_offset(addr) {
+                Some(offset) => self.ram[offset],
+                None => 0xFF,
+            },
+            _ => panic!("MMM01 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            // 0000–1FFF — RAM Enable, RAM bank mask and map enable
+            0x0000..=0x1FFF => {
+                self.ram_enable = value & 0x0F == 0x0A;
+                if !self.mapped {
+                    self.ram_bank_mask = (value >> 4) & 0b11;
+                    self.mapped = value & 0b01000000 != 0;
+                }
+            }
+            // 2000–3FFF — ROM bank low (and mid while unmapped)
+            0x2000..=0x3FFF => {
+                self.rom_bank_low =
+                    self.masked(self.rom_bank_low, value & 0b11111, self.rom_bank_mask << 1);
+                if !self.mapped {
+                    self.rom_bank_mid = (value >> 5) & 0b11;
+                }
+            }
+            // 4000–5FFF — RAM bank low (and RAM/ROM bank high while unmapped)
+            0x4000..=0x5FFF => {
+                self.ram_bank_low =
+                    self.masked(self.ram_bank_low, value & 0b11, self.ram_bank_mask);
+                if !self.mapped {
+                    self.ram_bank_high = (value >> 2) & 0b11;
+                    self.rom_bank_high = (value >> 4) & 0b11;
+                    self.mode_write_disable = value & 0b01000000 != 0;
+                }
+            }
+            // 6000–7FFF — Banking mode (and ROM bank mask while unmapped)
+            0x6000..=0x7FFF => {
+                if !self.mapped {
+                    self.rom_bank_mask = (value >> 2) & 0b1111;
+                }
+                if !(self.mapped && self.mode_write_disable) {
+                    self.banked_reg = value & 0b1 == 1;
+                }
+            }
+            0xA000..=0xBFFF => {
+                if let Some(offset) = self.ram_offset(addr) {
+                    self.ram[offset] = value;
+                }
+            }
+            _ => panic!("MMM01 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::numbered_banks;
+    extern crate alloc;
+    use alloc::vec;
+
+    #[test]
+    fn menu_maps_a_game() {
+        let mut mmm01 = MMM01::new(vec![0; 0x8000], numbered_banks(64), 64);
+        // The menu is in the last 32 KiB
+        assert_eq!(mmm01.read(0x0000), 62);
+        assert_eq!(mmm01.read(0x4000), 63);
+
+        // 4 banks game starting at bank 32, with RAM bank 2
+        mmm01.write(0x2000, 0x20);
+        mmm01.write(0x4000, 0x02);
+        mmm01.write(0x6000, 0x0E << 2);
+        mmm01.write(0x0000, 0x70);
+        assert_eq!(mmm01.read(0x0000), 32);
+        assert_eq!(mmm01.read(0x4000), 33);
+
+        // The game only controls the bits out of the masks
+        mmm01.write(0x2000, 0x7F);
+        assert_eq!(mmm01.read(0x4000), 35);
+        mmm01.write(0x2000, 0x04);
+        assert_eq!(mmm01.read(0x4000), 33);
+        mmm01.write(0x4000, 0x1D);
+        mmm01.write(0x0000, 0x0A);
+        mmm01.write(0xA000, 0x12);
+        assert_eq!(mmm01.ram().unwrap()[2 * 0x2000], 0x12);
+        // Mapped for good
+        mmm01.write(0x0000, 0x00);
+        assert_eq!(mmm01.read(0x0000), 32);
+    }
+}
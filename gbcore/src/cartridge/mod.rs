@@ -1,33 +1,77 @@
+mod camera;
 mod cartridge;
+mod header;
+mod huc1;
+mod huc3;
 mod mbc1;
 mod mbc5;
+mod mbc6;
+mod mbc7;
+mod mmm01;
+mod peripherals;
 mod ram;
 mod rom;
 mod rom_only;
+mod storage;
+mod tama5;
 
 use crate::CoreError;
 use crate::Memory;
+use crate::cartridge::camera::PocketCamera;
+use crate::cartridge::huc1::HuC1;
+use crate::cartridge::huc3::HuC3;
 use crate::cartridge::mbc1::MBC1;
 use crate::cartridge::mbc5::MBC5;
+use crate::cartridge::mbc6::MBC6;
+use crate::cartridge::mbc7::MBC7;
+use crate::cartridge::mmm01::MMM01;
+use crate::cartridge::tama5::Tama5;
 use core::ops::Index;
 use core::ops::IndexMut;
 
+pub use self::camera::{CAMERA_HEIGHT, CAMERA_WIDTH};
 pub use self::cartridge::CartridgeType;
+pub use self::header::{CartridgeHeader, CgbFlag, Destination, HeaderWarning};
+pub use self::peripherals::{
+    Clock, ImageSource, Infrared, InfraredLink, Peripherals, Speaker, Tilt,
+};
 pub use self::ram::RamType;
 pub use self::rom::RomType;
+pub use self::storage::Rom;
 
 pub trait RealMemory {
     fn read(&self, addr: usize) -> u8;
     fn write(&mut self, addr: usize, value: u8);
 }
 
-pub trait Cartridge: Memory {}
+pub trait Cartridge: Memory {
+    // External RAM (A000-BFFF), if any
+    fn ram(&self) -> Option<&[u8]> {
+        None
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+    // ROM bank mapped at `addr` (0000-7FFF), in the mapper's bank size
+    fn rom_bank(&self, addr: u16) -> usize {
+        (addr >= 0x4000) as usize
+    }
+    // Clock chip state (HuC3, TAMA5...), to be saved with the RAM
+    fn rtc(&self) -> Option<Vec<u8>> {
+        None
+    }
+    // Restore a state returned by `rtc`, anything else is ignored
+    fn set_rtc(&mut self, _rtc: &[u8]) {}
+}
 
 extern crate alloc;
 use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
 use rom_only::RomOnly;
 
 pub struct DynCartridge {
+    pub header: CartridgeHeader,
     pub cart_type: CartridgeType,
     pub ram_type: RamType,
     pub rom_type: RomType,
@@ -35,51 +79,76 @@ pub struct DynCartridge {
 }
 
 impl DynCartridge {
-    pub fn new<ROM: Index<usize, Output = u8> + 'static>(rom: ROM) -> Result<Self, CoreError> {
-        let cart_type = CartridgeType::try_from(rom[0x147])?;
-        let rom_type = RomType::try_from(rom[0x148])?;
-        let ram_type = RamType::try_from(rom[0x149])?;
+    pub fn new<S: AsRef<[u8]> + 'static>(rom: S) -> Result<Self, CoreError> {
+        Self::with_peripherals(rom, Peripherals::default())
+    }
+
+    // Cartridge with access to host devices (clock, infrared...)
+    pub fn with_peripherals<S: AsRef<[u8]> + 'static>(
+        rom: S,
+        peripherals: Peripherals,
+    ) -> Result<Self, CoreError> {
+        let rom = Rom::new(rom);
+        let header = match mmm01_header(rom.as_ref()) {
+            Some(header) => CartridgeHeader::parse(header)?,
+            None => CartridgeHeader::parse(rom.as_ref())?,
+        };
+        let cart_type = header.cart_type;
+        let rom_type = header.rom_type;
+        let ram_type = header.ram_type;
+        let expected_len = rom_type.memory_size() * 1024;
+        if rom.as_ref().len() != expected_len {
+            return Err(CoreError::RomSizeMismatch(expected_len, rom.as_ref().len()));
+        }
         let ram = DynRam::new(&ram_type)?;
 
         let inner: Box<dyn Cartridge> = match cart_type {
-            CartridgeType::ROMOnly => Box::new(RomOnly::new(rom)),
-            CartridgeType::MBC1 => Box::new(MBC1::new(
-                ram,
-                rom,
-                rom_type.memory_size() > 512 || ram_type.memory_size() > 8,
-            )),
-            CartridgeType::MBC1Ram => todo!(),
-            CartridgeType::MBC1RamBattery => Box::new(MBC1::new(
-                ram,
+            CartridgeType::ROMOnly | CartridgeType::RomRam | CartridgeType::RomRamBattery => {
+                Box::new(RomOnly::new(ram, rom))
+            }
+            CartridgeType::MBC1 | CartridgeType::MBC1Ram | CartridgeType::MBC1RamBattery => {
+                let multicart = is_mbc1_multicart(rom.as_ref());
+                Box::new(MBC1::new(
+                    ram,
+                    rom,
+                    rom_type.nb_bank(),
+                    multicart || rom_type.memory_size() > 512 || ram_type.memory_size() > 8,
+                    multicart,
+                ))
+            }
+            CartridgeType::MBC5 | CartridgeType::MBC5Ram | CartridgeType::MBC5RamBattery => {
+                Box::new(MBC5::new(ram, rom, rom_type.nb_bank(), false))
+            }
+            CartridgeType::MBC5Rumble
+            | CartridgeType::MBC5RumbleRam
+            | CartridgeType::MBC5RumbleRamBattery => {
+                Box::new(MBC5::new(ram, rom, rom_type.nb_bank(), true))
+            }
+            CartridgeType::MMM01 | CartridgeType::MMM01Ram | CartridgeType::MMM01RamBattery => {
+                Box::new(MMM01::new(ram, rom, rom_type.nb_bank()))
+            }
+            CartridgeType::HuC1RamBattery => {
+                Box::new(HuC1::new(ram, rom, rom_type.nb_bank(), peripherals))
+            }
+            CartridgeType::HuC3 => Box::new(HuC3::new(ram, rom, rom_type.nb_bank(), peripherals)),
+            CartridgeType::MBC7SensorRumbleRamBattery => {
+                Box::new(MBC7::new(rom, rom_type.nb_bank(), peripherals))
+            }
+            CartridgeType::PocketCamera => {
+                Box::new(PocketCamera::new(ram, rom, rom_type.nb_bank(), peripherals))
+            }
+            CartridgeType::MBC6 => Box::new(MBC6::new(
                 rom,
-                rom_type.memory_size() > 512 || ram_type.memory_size() > 8,
+                rom_type.nb_bank(),
+                ram_type.memory_size() * 1024,
             )),
-            CartridgeType::MBC2 => todo!(),
-            CartridgeType::MBC2Battery => todo!(),
-            CartridgeType::RomRam => todo!(),
-            CartridgeType::RomRamBattery => todo!(),
-            CartridgeType::MMM01 => todo!(),
-            CartridgeType::MMM01Ram => todo!(),
-            CartridgeType::MMM01RamBattery => todo!(),
-            CartridgeType::MBC3TimerBattery => todo!(),
-            CartridgeType::MBC3TimerRamBattery => todo!(),
-            CartridgeType::MBC3 => todo!(),
-            CartridgeType::MBC3Ram => todo!(),
-            CartridgeType::MBC3RamBattery => todo!(),
-            CartridgeType::MBC5 => todo!(),
-            CartridgeType::MBC5Ram => todo!(),
-            CartridgeType::MBC5RamBattery => Box::new(MBC5::new(ram, rom)),
-            CartridgeType::MBC5Rumble => todo!(),
-            CartridgeType::MBC5RumbleRam => todo!(),
-            CartridgeType::MBC5RumbleRamBattery => todo!(),
-            CartridgeType::MBC6 => todo!(),
-            CartridgeType::MBC7SensorRumbleRamBattery => todo!(),
-            CartridgeType::PocketCamera => todo!(),
-            CartridgeType::BandaiTama5 => todo!(),
-            CartridgeType::HuC3 => todo!(),
-            CartridgeType::HuC1RamBattery => todo!(),
+            CartridgeType::BandaiTama5 => {
+                Box::new(Tama5::new(rom, rom_type.nb_bank(), peripherals))
+            }
+            t => return Err(CoreError::UnsupportedCartridge(t)),
         };
         Ok(Self {
+            header,
             inner,
             ram_type,
             rom_type,
@@ -88,7 +157,75 @@ impl DynCartridge {
     }
 }
 
-impl Cartridge for DynCartridge {}
+// MMM01 carts boot the menu in the last 32 KiB, its header describes the
+// cartridge (the one at 0x100 belongs to the first game).
+fn mmm01_header(rom: &[u8]) -> Option<&[u8]> {
+    let start = rom.len().checked_sub(0x8000)?;
+    let header = &rom[start..start + 0x150];
+    matches!(header[0x147], 0x0B..=0x0D).then_some(header)
+}
+
+// MBC1M compilations are 1 MiB ROMs made of 256 KiB games, each one with its
+// own header. The menu is the one in bank 0.
+fn is_mbc1_multicart(rom: &[u8]) -> bool {
+    if rom.len() != 0x100000 {
+        return false;
+    }
+    (0x40000..rom.len())
+        .step_by(0x40000)
+        .any(|offset| header::has_logo(rom, offset))
+}
+
+impl DynCartridge {
+    // RAM kept by the battery, to be saved and restored by the frontend
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        match self.cart_type.has_battery() {
+            true => self.inner.ram(),
+            false => None,
+        }
+    }
+
+    pub fn battery_ram_mut(&mut self) -> Option<&mut [u8]> {
+        match self.cart_type.has_battery() {
+            true => self.inner.ram_mut(),
+            false => None,
+        }
+    }
+
+    // Clock kept by the battery, saved after the battery RAM. It keeps
+    // running while the emulator is off as long as the host clock counts
+    // from the same point.
+    pub fn battery_rtc(&self) -> Option<Vec<u8>> {
+        match self.cart_type.has_battery() {
+            true => self.inner.rtc(),
+            false => None,
+        }
+    }
+
+    pub fn set_battery_rtc(&mut self, rtc: &[u8]) {
+        if self.cart_type.has_battery() {
+            self.inner.set_rtc(rtc);
+        }
+    }
+}
+
+impl Cartridge for DynCartridge {
+    fn ram(&self) -> Option<&[u8]> {
+        self.inner.ram()
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        self.inner.ram_mut()
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        self.inner.rom_bank(addr)
+    }
+    fn rtc(&self) -> Option<Vec<u8>> {
+        self.inner.rtc()
+    }
+    fn set_rtc(&mut self, rtc: &[u8]) {
+        self.inner.set_rtc(rtc);
+    }
+}
 
 impl Memory for DynCartridge {
     fn read(&mut self, addr: u16) -> u8 {
@@ -101,27 +238,15 @@ impl Memory for DynCartridge {
 }
 
 struct DynRam {
-    inner: Box<dyn IndexMut<usize, Output = u8>>,
+    inner: Box<[u8]>,
 }
 
 impl DynRam {
     fn new(t: &RamType) -> Result<Self, CoreError> {
         match t {
-            RamType::RamBankNoRam => Ok(Self {
-                inner: Box::new([0u8; 0]),
-            }),
-            RamType::RamBankUnused => todo!(),
-            RamType::RamBank8KByte => Ok(Self {
-                inner: Box::new([0u8; 0x2000]),
-            }),
-            RamType::RamBank32KByte => Ok(Self {
-                inner: Box::new([0u8; 0x2000 * 4]),
-            }),
-            RamType::RamBank128KByte => Ok(Self {
-                inner: Box::new([0u8; 0x2000 * 16]),
-            }),
-            RamType::RamBank64KByte => Ok(Self {
-                inner: Box::new([0u8; 0x2000 * 8]),
+            RamType::RamBankUnused => Err(CoreError::UnsupportedRamType(*t)),
+            _ => Ok(Self {
+                inner: vec![0u8; t.memory_size() * 1024].into_boxed_slice(),
             }),
         }
     }
@@ -140,3 +265,63 @@ impl IndexMut<usize> for DynRam {
         self.inner.index_mut(index)
     }
 }
+
+impl AsRef<[u8]> for DynRam {
+    fn as_ref(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl AsMut<[u8]> for DynRam {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.inner
+    }
+}
+
+// ROM of `count` 16 KiB banks, each one filled with its number
+#[cfg(test)]
+pub(crate) fn numbered_banks(count: usize) -> Vec<u8> {
+    (0..count).flat_map(|bank| [bank as u8; 0x4000]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 32 KiB ROM with a header byte changed
+    fn rom(addr: usize, value: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[addr] = value;
+        rom
+    }
+
+    #[test]
+    fn header_errors() {
+        let error = |rom: Vec<u8>| DynCartridge::new(rom).err();
+        assert!(matches!(
+            error(vec![0; 0x100]),
+            Some(CoreError::TruncatedHeader(0x100))
+        ));
+        assert!(matches!(
+            error(vec![0; 0x4000]),
+            Some(CoreError::RomSizeMismatch(0x8000, 0x4000))
+        ));
+        assert!(matches!(
+            error(rom(0x147, 0x04)),
+            Some(CoreError::UnknownCartridgeType(0x04))
+        ));
+        assert!(matches!(
+            error(rom(0x147, 0x11)),
+            Some(CoreError::UnsupportedCartridge(CartridgeType::MBC3))
+        ));
+        assert!(matches!(
+            error(rom(0x148, 0x20)),
+            Some(CoreError::UnknownRomType(0x20))
+        ));
+        assert!(matches!(
+            error(rom(0x149, 0x01)),
+            Some(CoreError::UnsupportedRamType(RamType::RamBankUnused))
+        ));
+        assert!(error(rom(0x147, 0x00)).is_none());
+    }
+}
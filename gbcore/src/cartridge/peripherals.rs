@@ -0,0 +1,139 @@
+use super::camera::{CAMERA_HEIGHT, CAMERA_WIDTH};
+
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+// Host time source for cartridge clocks (HuC3, MBC3, TAMA5...)
+pub trait Clock {
+    // Seconds elapsed since any fixed point in time
+    fn now(&mut self) -> u64;
+}
+
+// Infrared LED and receiver of HuC1/HuC3 cartridges
+pub trait Infrared {
+    fn set_led(&mut self, on: bool);
+    fn light_detected(&mut self) -> bool;
+}
+
+// Piezo speaker of HuC3 cartridges
+pub trait Speaker {
+    fn play_tone(&mut self, tone: u8);
+}
+
+// Accelerometer of MBC7 cartridges, in g: x > 0 when tilted right, y > 0
+// when the top of the console goes up
+pub trait Tilt {
+    fn tilt(&mut self) -> (f32, f32);
+}
+
+// Picture seen by the Game Boy Camera sensor: 128x112 gray levels,
+// row by row, 0 is black and 255 white
+pub trait ImageSource {
+    fn capture(&mut self, frame: &mut [u8; CAMERA_WIDTH * CAMERA_HEIGHT]);
+}
+
+// Host devices available to the cartridge, missing ones act as disconnected
+#[derive(Default)]
+pub struct Peripherals {
+    pub clock: Option<Box<dyn Clock>>,
+    pub infrared: Option<Box<dyn Infrared>>,
+    pub speaker: Option<Box<dyn Speaker>>,
+    pub tilt: Option<Box<dyn Tilt>>,
+    pub camera: Option<Box<dyn ImageSource>>,
+}
+
+impl Peripherals {
+    pub(crate) fn now(&mut self) -> u64 {
+        self.clock.as_mut().map_or(0, |c| c.now())
+    }
+
+    pub(crate) fn set_led(&mut self, on: bool) {
+        if let Some(ir) = self.infrared.as_mut() {
+            ir.set_led(on);
+        }
+    }
+
+    pub(crate) fn light_detected(&mut self) -> bool {
+        self.infrared.as_mut().is_some_and(|ir| ir.light_detected())
+    }
+
+    pub(crate) fn tilt(&mut self) -> (f32, f32) {
+        self.tilt.as_mut().map_or((0.0, 0.0), |t| t.tilt())
+    }
+
+    // Mid gray without camera
+    pub(crate) fn capture(&mut self, frame: &mut [u8; CAMERA_WIDTH * CAMERA_HEIGHT]) {
+        match self.camera.as_mut() {
+            Some(camera) => camera.capture(frame),
+            None => frame.fill(0x80),
+        }
+    }
+
+    pub(crate) fn play_tone(&mut self, tone: u8) {
+        if let Some(speaker) = self.speaker.as_mut() {
+            speaker.play_tone(tone);
+        }
+    }
+}
+
+// One end of an infrared link between two emulated cartridges, each one
+// sees the LED of the other.
+pub struct InfraredLink {
+    led: Rc<Cell<bool>>,
+    remote_led: Rc<Cell<bool>>,
+}
+
+impl InfraredLink {
+    pub fn pair() -> (Self, Self) {
+        let a = Rc::new(Cell::new(false));
+        let b = Rc::new(Cell::new(false));
+        (
+            Self {
+                led: a.clone(),
+                remote_led: b.clone(),
+            },
+            Self {
+                led: b,
+                remote_led: a,
+            },
+        )
+    }
+}
+
+impl Infrared for InfraredLink {
+    fn set_led(&mut self, on: bool) {
+        self.led.set(on);
+    }
+
+    fn light_detected(&mut self) -> bool {
+        self.remote_led.get()
+    }
+}
+
+// Clock moved by hand, in seconds
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct TestClock(pub Rc<Cell<u64>>);
+
+#[cfg(test)]
+impl TestClock {
+    pub fn peripherals(&self) -> Peripherals {
+        Peripherals {
+            clock: Some(Box::new(self.clone())),
+            ..Default::default()
+        }
+    }
+
+    pub fn advance(&self, seconds: u64) {
+        self.0.set(self.0.get() + seconds);
+    }
+}
+
+#[cfg(test)]
+impl Clock for TestClock {
+    fn now(&mut self) -> u64 {
+        self.0.get()
+    }
+}
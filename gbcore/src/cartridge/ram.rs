@@ -1,6 +1,6 @@
 use crate::CoreError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RamType {
     RamBankNoRam,
     RamBankUnused,
@@ -18,7 +18,7 @@ impl RamType {
             RamType::RamBank8KByte => 1,
             RamType::RamBank32KByte => 4,
             RamType::RamBank128KByte => 16,
-            RamType::RamBank64KByte => 68,
+            RamType::RamBank64KByte => 8,
         }
     }
 
@@ -1,6 +1,6 @@
 use crate::CoreError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RomType {
     RomBank32KByte,
     RomBank64KByte,
@@ -2,39 +2,93 @@ use super::Cartridge;
 use crate::Memory;
 use core::ops::Index;
 
-pub struct RomOnly<ROM>
+// No mapper: 32 KiB of ROM and up to 8 KiB of RAM (types 0x00, 0x08, 0x09)
+const RAM_SIZE: usize = 0x2000;
+
+pub struct RomOnly<RAM, ROM>
 where
+    RAM: AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
+    ram: RAM,
     rom: ROM,
 }
 
-impl<ROM> RomOnly<ROM>
+impl<RAM, ROM> RomOnly<RAM, ROM>
 where
+    RAM: AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
-    pub fn new(rom: ROM) -> Self {
-        Self { rom }
+    pub fn new(ram: RAM, rom: ROM) -> Self {
+        Self { ram, rom }
     }
 }
 
-impl<ROM> Cartridge for RomOnly<ROM> where ROM: Index<usize, Output = u8> {}
+impl<RAM, ROM> Cartridge for RomOnly<RAM, ROM>
+where
+    RAM: AsMut<[u8]> + AsRef<[u8]>,
+    ROM: Index<usize, Output = u8>,
+{
+    // Only the mapped 8 KiB, whatever the size in the header
+    fn ram(&self) -> Option<&[u8]> {
+        let ram = self.ram.as_ref();
+        Some(&ram[..ram.len().min(RAM_SIZE)]).filter(|ram| !ram.is_empty())
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        let ram = self.ram.as_mut();
+        let len = ram.len().min(RAM_SIZE);
+        Some(&mut ram[..len]).filter(|ram| !ram.is_empty())
+    }
+}
 
-impl<ROM> Memory for RomOnly<ROM>
+impl<RAM, ROM> Memory for RomOnly<RAM, ROM>
 where
+    RAM: AsMut<[u8]> + AsRef<[u8]>,
     ROM: Index<usize, Output = u8>,
 {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
             0x0000..=0x7FFF => self.rom[addr as usize],
+            // A000–BFFF — RAM, if any (open bus otherwise)
+            0xA000..=0xBFFF => self
+                .ram
+                .as_ref()
+                .get((addr - 0xA000) as usize)
+                .copied()
+                .unwrap_or(0xFF),
             _ => panic!("RomOnly read out of range: {:#04x}", addr),
         }
     }
 
-    fn write(&mut self, addr: u16, _: u8) {
+    fn write(&mut self, addr: u16, value: u8) {
         match addr {
             0x0000..=0x7FFF => {}
+            // A000–BFFF — RAM, if any
+            0xA000..=0xBFFF => {
+                if let Some(cell) = self.ram.as_mut().get_mut((addr - 0xA000) as usize) {
+                    *cell = value;
+                }
+            }
             _ => panic!("RomOnly write out of range: {:#04x}", addr),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{DynRam, RamType};
+    extern crate alloc;
+    use alloc::vec;
+
+    #[test]
+    fn ram_sizes() {
+        assert_eq!(RamType::RamBank64KByte.memory_size(), 64);
+        let ram = DynRam::new(&RamType::RamBank32KByte).unwrap();
+        let mut cart = RomOnly::new(ram, vec![0; 0x8000]);
+        // Only 8 KiB are mapped and saved
+        assert_eq!(cart.ram().map(<[u8]>::len), Some(0x2000));
+        cart.write(0xBFFF, 0x12);
+        assert_eq!(cart.read(0xBFFF), 0x12);
+    }
+}
@@ -0,0 +1,98 @@
+use core::ops::Index;
+
+// Nothing drives the data bus
+static OPEN_BUS: u8 = 0xFF;
+
+// ROM storage, any byte container: Vec<u8>, &'static [u8] (e.g. a ROM kept in
+// flash), Arc<[u8]> shared by several systems...
+//
+// Offsets past the end wrap around like the unconnected address lines of a
+// real cartridge, so a bank number too large for the ROM selects a mirrored
+// bank instead of panicking. An empty ROM reads 0xFF, like an empty slot.
+pub struct Rom<S>
+where
+    S: AsRef<[u8]>,
+{
+    data: S,
+    mask: usize,
+}
+
+impl<S> Rom<S>
+where
+    S: AsRef<[u8]>,
+{
+    pub fn new(data: S) -> Self {
+        let mask = data.as_ref().len().next_power_of_two() - 1;
+        Self { data, mask }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.as_ref().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.as_ref().is_empty()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.data
+    }
+}
+
+impl<S> Index<usize> for Rom<S>
+where
+    S: AsRef<[u8]>,
+{
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &u8 {
+        let data = self.data.as_ref();
+        if data.is_empty() {
+            return &OPEN_BUS;
+        }
+        let offset = index & self.mask;
+        // Sizes that are not a power of two (72, 80 or 96 banks)
+        match data.get(offset) {
+            Some(byte) => byte,
+            None => &data[offset % data.len()],
+        }
+    }
+}
+
+impl<S> AsRef<[u8]> for Rom<S>
+where
+    S: AsRef<[u8]>,
+{
+    fn as_ref(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::numbered_banks;
+
+    #[test]
+    fn mirrored_banks() {
+        let rom = Rom::new(numbered_banks(4));
+        assert_eq!(rom[0x4000 * 5 + 0x10], 1);
+        assert_eq!(rom[0x4000 * 0x1FF], 3);
+    }
+
+    #[test]
+    fn sizes_not_a_power_of_two() {
+        // 96 banks: the mask keeps 128, the missing ones wrap around
+        let rom = Rom::new(numbered_banks(96));
+        assert_eq!(rom[0x4000 * 95], 95);
+        assert_eq!(rom[0x4000 * 100], 4);
+        assert_eq!(rom[0x4000 * 130], 2);
+    }
+
+    #[test]
+    fn empty_rom() {
+        let rom = Rom::new([0u8; 0]);
+        assert!(rom.is_empty());
+        assert_eq!(rom[0x4000], 0xFF);
+    }
+}
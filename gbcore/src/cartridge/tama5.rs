@@ -0,0 +1,284 @@
+use super::{Cartridge, Peripherals};
+use crate::Memory;
+use core::ops::Index;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+// Registers selected through A001, accessed one nibble at a time via A000
+const REG_BANK_LO: u8 = 0x0;
+const REG_BANK_HI: u8 = 0x1;
+const REG_WRITE_LO: u8 = 0x4;
+const REG_WRITE_HI: u8 = 0x5;
+const REG_ADDR_HI: u8 = 0x6; // Bit 0: address bit 4, bits 1-3: command
+const REG_ADDR_LO: u8 = 0x7; // Writing it runs the command
+const REG_ACTIVE: u8 = 0xA;
+const REG_READ_LO: u8 = 0xC;
+const REG_READ_HI: u8 = 0xD;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const MONTH_DAYS: [u64; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+// Bandai TAMA5 (Game de Hakken!! Tamagotchi 3): 32 bytes of EEPROM and a
+// TC8521 style clock behind a nibble wide register interface.
+pub struct Tama5<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    rom: ROM,
+    rom_banks: usize,
+    peripherals: Peripherals,
+
+    registers: [u8; 0x10],
+    selected: u8,
+    read: u8,
+
+    eeprom: [u8; 0x20],
+    // Host time when the clock was set, and the time set then (in seconds)
+    rtc_base: u64,
+    rtc_set: u64,
+}
+
+impl<ROM> Tama5<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    pub fn new(rom: ROM, rom_banks: usize, mut peripherals: Peripherals) -> Self {
+        let rtc_base = peripherals.now();
+        Self {
+            rom,
+            rom_banks,
+            peripherals,
+            registers: [0; 0x10],
+            selected: 0,
+            read: 0,
+            eeprom: [0; 0x20],
+            rtc_base,
+            rtc_set: 0,
+        }
+    }
+
+    fn rom_bank(&self) -> usize {
+        let bank = (self.registers[REG_BANK_LO as usize] & 0x0F)
+            | ((self.registers[REG_BANK_HI as usize] & 0x01) << 4);
+        bank as usize % self.rom_banks
+    }
+
+    // BCD digits: seconds, minutes, hours, then day of week, day, month and
+    // year in the 0-0xC range. The time is counted from 1 January of year 0.
+    fn rtc_digit(&mut self, idx: u8) -> u8 {
+        let now = self.rtc_set + self.peripherals.now().wrapping_sub(self.rtc_base);
+        let (seconds, minutes, hours) = (now % 60, (now / 60) % 60, (now / 3600) % 24);
+        let days = now / SECONDS_PER_DAY;
+        let (day, month, year) = date(days);
+        let digit = match idx {
+            0x0 => seconds % 10,
+            0x1 => seconds / 10,
+            0x2 => minutes % 10,
+            0x3 => minutes / 10,
+            0x4 => hours % 10,
+            0x5 => hours / 10,
+            0x6 => days % 7,
+            0x7 => day % 10,
+            0x8 => day / 10,
+            0x9 => month % 10,
+            0xA => month / 10,
+            0xB => year % 10,
+            0xC => year / 10,
+            _ => 0,
+        };
+        digit as u8
+    }
+
+    // Only the time of day can be set, written as BCD digits 0-5
+    fn set_rtc_digit(&mut self, idx: u8, value: u8) {
+        let now = self.rtc_set + self.peripherals.now().wrapping_sub(self.rtc_base);
+        let unit = match idx {
+            0x0 => 1,
+            0x1 => 10,
+            0x2 => 60,
+            0x3 => 600,
+            0x4 => 3600,
+            0x5 => 36000,
+            _ => return,
+        };
+        let current = self.rtc_digit(idx) as u64;
+        self.rtc_set = now - current * unit + (value & 0x0F) as u64 * unit;
+        self.rtc_base = self.peripherals.now();
+    }
+
+    fn exec_command(&mut self) {
+        let addr_hi = self.registers[REG_ADDR_HI as usize];
+        let addr = ((addr_hi & 0x01) << 4) | (self.registers[REG_ADDR_LO as usize] & 0x0F);
+        let data = (self.registers[REG_WRITE_HI as usize] << 4)
+            | (self.registers[REG_WRITE_LO as usize] & 0x0F);
+        match addr_hi >> 1 {
+            0x0 => self.eeprom[addr as usize] = data,
+            0x1 => self.read = self.eeprom[addr as usize],
+            0x2 => self.set_rtc_digit(addr & 0x0F, data),
+            0x3 => self.read = self.rtc_digit(addr & 0x0F),
+            _ => {}
+        }
+    }
+}
+
+// Day (1-31), month (1-12) and year (0-99) of a day counted from 1 January
+// of year 0. Years divisible by 4 are leap years.
+fn date(days: u64) -> (u64, u64, u64) {
+    let leap = |year: u64| year.is_multiple_of(4);
+    // Whole 4 year cycles first
+    let mut year = days / (4 * 365 + 1) * 4;
+    let mut days = days % (4 * 365 + 1);
+    while days >= 365 + leap(year) as u64 {
+        days -= 365 + leap(year) as u64;
+        year += 1;
+    }
+    let mut month = 0;
+    while days >= MONTH_DAYS[month] + (month == 1 && leap(year)) as u64 {
+        days -= MONTH_DAYS[month] + (month == 1 && leap(year)) as u64;
+        month += 1;
+    }
+    (days + 1, month as u64 + 1, year % 100)
+}
+
+impl<ROM> Cartridge for Tama5<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    fn ram(&self) -> Option<&[u8]> {
+        Some(&self.eeprom)
+    }
+    fn ram_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.eeprom)
+    }
+    fn rom_bank(&self, addr: u16) -> usize {
+        match addr {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank(),
+        }
+    }
+    // Host time when the clock was set and the time set then (little endian)
+    fn rtc(&self) -> Option<Vec<u8>> {
+        Some([self.rtc_base.to_le_bytes(), self.rtc_set.to_le_bytes()].concat())
+    }
+    fn set_rtc(&mut self, rtc: &[u8]) {
+        if let Some((base, set)) = rtc.split_first_chunk::<8>()
+            && let Ok(set) = <[u8; 8]>::try_from(set)
+        {
+            self.rtc_base = u64::from_le_bytes(*base);
+            self.rtc_set = u64::from_le_bytes(set);
+        }
+    }
+}
+
+impl<ROM> Memory for Tama5<ROM>
+where
+    ROM: Index<usize, Output = u8>,
+{
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            // 0000–3FFF — ROM Bank 00
+            0x0000..=0x3FFF => self.rom[addr as usize],
+            // 4000–7FFF — ROM Bank 00-1F
+            0x4000..=0x7FFF => self.rom[self.rom_bank() * 0x4000 + addr as usize - 0x4000],
+            // A000 — Selected register, upper nibble reads as 1s
+            0xA000 => match self.selected {
+                REG_ACTIVE => 0xF1,
+                REG_READ_LO => 0xF0 | (self.read & 0x0F),
+                REG_READ_HI => 0xF0 | (self.read >> 4),
+                _ => 0xF0,
+            },
+            0xA001..=0xBFFF => 0xFF,
+            _ => panic!("TAMA5 read out of range: {:#04x}", addr),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x0000..=0x7FFF => {}
+            // A000 — Write the selected register
+            0xA000 => {
+                self.registers[self.selected as usize] = value & 0x0F;
+                if self.selected == REG_ADDR_LO {
+                    self.exec_command();
+                }
+            }
+            // A001 — Select a register
+            0xA001 => self.selected = value & 0x0F,
+            0xA002..=0xBFFF => {}
+            _ => panic!("TAMA5 write out of range: {:#04x}", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::peripherals::TestClock;
+    use alloc::vec;
+
+    type Cart = Tama5<Vec<u8>>;
+
+    fn set_register(cart: &mut Cart, reg: u8, value: u8) {
+        cart.write(0xA001, reg);
+        cart.write(0xA000, value);
+    }
+
+    // Command in bits 1-3 of the high address register
+    fn exec(cart: &mut Cart, command: u8, addr: u8, data: u8) -> u8 {
+        set_register(cart, REG_WRITE_LO, data & 0x0F);
+        set_register(cart, REG_WRITE_HI, data >> 4);
+        set_register(cart, REG_ADDR_HI, (command << 1) | (addr >> 4));
+        set_register(cart, REG_ADDR_LO, addr & 0x0F);
+        cart.write(0xA001, REG_READ_LO);
+        let lo = cart.read(0xA000) & 0x0F;
+        cart.write(0xA001, REG_READ_HI);
+        let hi = cart.read(0xA000) & 0x0F;
+        (hi << 4) | lo
+    }
+
+    fn date_digits(cart: &mut Cart) -> [u8; 6] {
+        [0x7, 0x8, 0x9, 0xA, 0xB, 0xC].map(|idx| exec(cart, 0x3, idx, 0) & 0x0F)
+    }
+
+    #[test]
+    fn eeprom() {
+        let mut cart = Tama5::new(vec![0; 0x8000], 2, Peripherals::default());
+        exec(&mut cart, 0x0, 0x13, 0xA5);
+        assert_eq!(exec(&mut cart, 0x1, 0x13, 0), 0xA5);
+        assert_eq!(cart.ram().unwrap()[0x13], 0xA5);
+    }
+
+    #[test]
+    fn calendar() {
+        let clock = TestClock::default();
+        let mut cart = Tama5::new(vec![0; 0x8000], 2, clock.peripherals());
+        // Day 59 of year 0 (leap), then of year 1
+        cart.rtc_set = 59 * SECONDS_PER_DAY;
+        assert_eq!(date_digits(&mut cart), [9, 2, 2, 0, 0, 0]);
+        clock.advance(366 * SECONDS_PER_DAY);
+        assert_eq!(date_digits(&mut cart), [1, 0, 3, 0, 1, 0]);
+        // 31 December of year 99, then back to year 0
+        cart.rtc_set = (25 * (4 * 365 + 1) - 1) * SECONDS_PER_DAY;
+        cart.rtc_base = clock.0.get();
+        assert_eq!(date_digits(&mut cart), [1, 3, 2, 1, 9, 9]);
+        clock.advance(SECONDS_PER_DAY);
+        assert_eq!(date_digits(&mut cart), [1, 0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn clock_survives_a_save() {
+        let clock = TestClock::default();
+        clock.advance(5000);
+        let mut cart = Tama5::new(vec![0; 0x8000], 2, clock.peripherals());
+        // 10 minutes past midnight
+        exec(&mut cart, 0x2, 0x03, 1);
+        let rtc = cart.rtc().unwrap();
+
+        clock.advance(3600);
+        let mut cart = Tama5::new(vec![0; 0x8000], 2, clock.peripherals());
+        cart.set_rtc(&rtc);
+        let time = [0x2, 0x3, 0x4, 0x5].map(|idx| exec(&mut cart, 0x3, idx, 0) & 0x0F);
+        assert_eq!(time, [0, 1, 1, 0]);
+    }
+}
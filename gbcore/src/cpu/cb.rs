@@ -1,336 +1,345 @@
 use crate::Memory;
 
 use super::{
-    Cpu,
+    Cpu, exec_match,
     r8::{self, A, B, C, D, E, H, L, MemHL},
     regs::{CARRY, HCARRY, SUB, ZERO},
 };
 
+// CB prefixed opcode table, see opcodes!
+macro_rules! cb_opcodes {
+    ($callback:ident($($args:tt)*)) => {
+        $callback! {
+            ($($args)*)
+            // RLC
+            0x00 => rlc::<B>: "rlc b", 2, 8,
+            0x01 => rlc::<C>: "rlc c", 2, 8,
+            0x02 => rlc::<D>: "rlc d", 2, 8,
+            0x03 => rlc::<E>: "rlc e", 2, 8,
+            0x04 => rlc::<H>: "rlc h", 2, 8,
+            0x05 => rlc::<L>: "rlc l", 2, 8,
+            0x06 => rlc::<MemHL>: "rlc [hl]", 2, 16,
+            0x07 => rlc::<A>: "rlc a", 2, 8,
+
+            // RRC
+            0x08 => rrc::<B>: "rrc b", 2, 8,
+            0x09 => rrc::<C>: "rrc c", 2, 8,
+            0x0A => rrc::<D>: "rrc d", 2, 8,
+            0x0B => rrc::<E>: "rrc e", 2, 8,
+            0x0C => rrc::<H>: "rrc h", 2, 8,
+            0x0D => rrc::<L>: "rrc l", 2, 8,
+            0x0E => rrc::<MemHL>: "rrc [hl]", 2, 16,
+            0x0F => rrc::<A>: "rrc a", 2, 8,
+
+            // RL
+            0x10 => rl::<B>: "rl b", 2, 8,
+            0x11 => rl::<C>: "rl c", 2, 8,
+            0x12 => rl::<D>: "rl d", 2, 8,
+            0x13 => rl::<E>: "rl e", 2, 8,
+            0x14 => rl::<H>: "rl h", 2, 8,
+            0x15 => rl::<L>: "rl l", 2, 8,
+            0x16 => rl::<MemHL>: "rl [hl]", 2, 16,
+            0x17 => rl::<A>: "rl a", 2, 8,
+
+            // RR
+            0x18 => rr::<B>: "rr b", 2, 8,
+            0x19 => rr::<C>: "rr c", 2, 8,
+            0x1A => rr::<D>: "rr d", 2, 8,
+            0x1B => rr::<E>: "rr e", 2, 8,
+            0x1C => rr::<H>: "rr h", 2, 8,
+            0x1D => rr::<L>: "rr l", 2, 8,
+            0x1E => rr::<MemHL>: "rr [hl]", 2, 16,
+            0x1F => rr::<A>: "rr a", 2, 8,
+
+            // SLA
+            0x20 => sla::<B>: "sla b", 2, 8,
+            0x21 => sla::<C>: "sla c", 2, 8,
+            0x22 => sla::<D>: "sla d", 2, 8,
+            0x23 => sla::<E>: "sla e", 2, 8,
+            0x24 => sla::<H>: "sla h", 2, 8,
+            0x25 => sla::<L>: "sla l", 2, 8,
+            0x26 => sla::<MemHL>: "sla [hl]", 2, 16,
+            0x27 => sla::<A>: "sla a", 2, 8,
+
+            // SRA
+            0x28 => sra::<B>: "sra b", 2, 8,
+            0x29 => sra::<C>: "sra c", 2, 8,
+            0x2A => sra::<D>: "sra d", 2, 8,
+            0x2B => sra::<E>: "sra e", 2, 8,
+            0x2C => sra::<H>: "sra h", 2, 8,
+            0x2D => sra::<L>: "sra l", 2, 8,
+            0x2E => sra::<MemHL>: "sra [hl]", 2, 16,
+            0x2F => sra::<A>: "sra a", 2, 8,
+
+            // SWAP
+            0x30 => swap::<B>: "swap b", 2, 8,
+            0x31 => swap::<C>: "swap c", 2, 8,
+            0x32 => swap::<D>: "swap d", 2, 8,
+            0x33 => swap::<E>: "swap e", 2, 8,
+            0x34 => swap::<H>: "swap h", 2, 8,
+            0x35 => swap::<L>: "swap l", 2, 8,
+            0x36 => swap::<MemHL>: "swap [hl]", 2, 16,
+            0x37 => swap::<A>: "swap a", 2, 8,
+
+            // SRL
+            0x38 => srl::<B>: "srl b", 2, 8,
+            0x39 => srl::<C>: "srl c", 2, 8,
+            0x3A => srl::<D>: "srl d", 2, 8,
+            0x3B => srl::<E>: "srl e", 2, 8,
+            0x3C => srl::<H>: "srl h", 2, 8,
+            0x3D => srl::<L>: "srl l", 2, 8,
+            0x3E => srl::<MemHL>: "srl [hl]", 2, 16,
+            0x3F => srl::<A>: "srl a", 2, 8,
+
+            // BIT 0
+            0x40 => bit::<0, B>: "bit 0, b", 2, 8,
+            0x41 => bit::<0, C>: "bit 0, c", 2, 8,
+            0x42 => bit::<0, D>: "bit 0, d", 2, 8,
+            0x43 => bit::<0, E>: "bit 0, e", 2, 8,
+            0x44 => bit::<0, H>: "bit 0, h", 2, 8,
+            0x45 => bit::<0, L>: "bit 0, l", 2, 8,
+            0x46 => bit::<0, MemHL>: "bit 0, [hl]", 2, 12,
+            0x47 => bit::<0, A>: "bit 0, a", 2, 8,
+
+            // BIT 1
+            0x48 => bit::<1, B>: "bit 1, b", 2, 8,
+            0x49 => bit::<1, C>: "bit 1, c", 2, 8,
+            0x4A => bit::<1, D>: "bit 1, d", 2, 8,
+            0x4B => bit::<1, E>: "bit 1, e", 2, 8,
+            0x4C => bit::<1, H>: "bit 1, h", 2, 8,
+            0x4D => bit::<1, L>: "bit 1, l", 2, 8,
+            0x4E => bit::<1, MemHL>: "bit 1, [hl]", 2, 12,
+            0x4F => bit::<1, A>: "bit 1, a", 2, 8,
+
+            // BIT 2
+            0x50 => bit::<2, B>: "bit 2, b", 2, 8,
+            0x51 => bit::<2, C>: "bit 2, c", 2, 8,
+            0x52 => bit::<2, D>: "bit 2, d", 2, 8,
+            0x53 => bit::<2, E>: "bit 2, e", 2, 8,
+            0x54 => bit::<2, H>: "bit 2, h", 2, 8,
+            0x55 => bit::<2, L>: "bit 2, l", 2, 8,
+            0x56 => bit::<2, MemHL>: "bit 2, [hl]", 2, 12,
+            0x57 => bit::<2, A>: "bit 2, a", 2, 8,
+
+            // BIT 3
+            0x58 => bit::<3, B>: "bit 3, b", 2, 8,
+            0x59 => bit::<3, C>: "bit 3, c", 2, 8,
+            0x5A => bit::<3, D>: "bit 3, d", 2, 8,
+            0x5B => bit::<3, E>: "bit 3, e", 2, 8,
+            0x5C => bit::<3, H>: "bit 3, h", 2, 8,
+            0x5D => bit::<3, L>: "bit 3, l", 2, 8,
+            0x5E => bit::<3, MemHL>: "bit 3, [hl]", 2, 12,
+            0x5F => bit::<3, A>: "bit 3, a", 2, 8,
+
+            // BIT 4
+            0x60 => bit::<4, B>: "bit 4, b", 2, 8,
+            0x61 => bit::<4, C>: "bit 4, c", 2, 8,
+            0x62 => bit::<4, D>: "bit 4, d", 2, 8,
+            0x63 => bit::<4, E>: "bit 4, e", 2, 8,
+            0x64 => bit::<4, H>: "bit 4, h", 2, 8,
+            0x65 => bit::<4, L>: "bit 4, l", 2, 8,
+            0x66 => bit::<4, MemHL>: "bit 4, [hl]", 2, 12,
+            0x67 => bit::<4, A>: "bit 4, a", 2, 8,
+
+            // BIT 5
+            0x68 => bit::<5, B>: "bit 5, b", 2, 8,
+            0x69 => bit::<5, C>: "bit 5, c", 2, 8,
+            0x6A => bit::<5, D>: "bit 5, d", 2, 8,
+            0x6B => bit::<5, E>: "bit 5, e", 2, 8,
+            0x6C => bit::<5, H>: "bit 5, h", 2, 8,
+            0x6D => bit::<5, L>: "bit 5, l", 2, 8,
+            0x6E => bit::<5, MemHL>: "bit 5, [hl]", 2, 12,
+            0x6F => bit::<5, A>: "bit 5, a", 2, 8,
+
+            // BIT 6
+            0x70 => bit::<6, B>: "bit 6, b", 2, 8,
+            0x71 => bit::<6, C>: "bit 6, c", 2, 8,
+            0x72 => bit::<6, D>: "bit 6, d", 2, 8,
+            0x73 => bit::<6, E>: "bit 6, e", 2, 8,
+            0x74 => bit::<6, H>: "bit 6, h", 2, 8,
+            0x75 => bit::<6, L>: "bit 6, l", 2, 8,
+            0x76 => bit::<6, MemHL>: "bit 6, [hl]", 2, 12,
+            0x77 => bit::<6, A>: "bit 6, a", 2, 8,
+
+            // BIT 7
+            0x78 => bit::<7, B>: "bit 7, b", 2, 8,
+            0x79 => bit::<7, C>: "bit 7, c", 2, 8,
+            0x7A => bit::<7, D>: "bit 7, d", 2, 8,
+            0x7B => bit::<7, E>: "bit 7, e", 2, 8,
+            0x7C => bit::<7, H>: "bit 7, h", 2, 8,
+            0x7D => bit::<7, L>: "bit 7, l", 2, 8,
+            0x7E => bit::<7, MemHL>: "bit 7, [hl]", 2, 12,
+            0x7F => bit::<7, A>: "bit 7, a", 2, 8,
+
+            // RES 0
+            0x80 => res::<0, B>: "res 0, b", 2, 8,
+            0x81 => res::<0, C>: "res 0, c", 2, 8,
+            0x82 => res::<0, D>: "res 0, d", 2, 8,
+            0x83 => res::<0, E>: "res 0, e", 2, 8,
+            0x84 => res::<0, H>: "res 0, h", 2, 8,
+            0x85 => res::<0, L>: "res 0, l", 2, 8,
+            0x86 => res::<0, MemHL>: "res 0, [hl]", 2, 16,
+            0x87 => res::<0, A>: "res 0, a", 2, 8,
+
+            // RES 1
+            0x88 => res::<1, B>: "res 1, b", 2, 8,
+            0x89 => res::<1, C>: "res 1, c", 2, 8,
+            0x8A => res::<1, D>: "res 1, d", 2, 8,
+            0x8B => res::<1, E>: "res 1, e", 2, 8,
+            0x8C => res::<1, H>: "res 1, h", 2, 8,
+            0x8D => res::<1, L>: "res 1, l", 2, 8,
+            0x8E => res::<1, MemHL>: "res 1, [hl]", 2, 16,
+            0x8F => res::<1, A>: "res 1, a", 2, 8,
+
+            // RES 2
+            0x90 => res::<2, B>: "res 2, b", 2, 8,
+            0x91 => res::<2, C>: "res 2, c", 2, 8,
+            0x92 => res::<2, D>: "res 2, d", 2, 8,
+            0x93 => res::<2, E>: "res 2, e", 2, 8,
+            0x94 => res::<2, H>: "res 2, h", 2, 8,
+            0x95 => res::<2, L>: "res 2, l", 2, 8,
+            0x96 => res::<2, MemHL>: "res 2, [hl]", 2, 16,
+            0x97 => res::<2, A>: "res 2, a", 2, 8,
+
+            // RES 3
+            0x98 => res::<3, B>: "res 3, b", 2, 8,
+            0x99 => res::<3, C>: "res 3, c", 2, 8,
+            0x9A => res::<3, D>: "res 3, d", 2, 8,
+            0x9B => res::<3, E>: "res 3, e", 2, 8,
+            0x9C => res::<3, H>: "res 3, h", 2, 8,
+            0x9D => res::<3, L>: "res 3, l", 2, 8,
+            0x9E => res::<3, MemHL>: "res 3, [hl]", 2, 16,
+            0x9F => res::<3, A>: "res 3, a", 2, 8,
+
+            // RES 4
+            0xA0 => res::<4, B>: "res 4, b", 2, 8,
+            0xA1 => res::<4, C>: "res 4, c", 2, 8,
+            0xA2 => res::<4, D>: "res 4, d", 2, 8,
+            0xA3 => res::<4, E>: "res 4, e", 2, 8,
+            0xA4 => res::<4, H>: "res 4, h", 2, 8,
+            0xA5 => res::<4, L>: "res 4, l", 2, 8,
+            0xA6 => res::<4, MemHL>: "res 4, [hl]", 2, 16,
+            0xA7 => res::<4, A>: "res 4, a", 2, 8,
+
+            // RES 5
+            0xA8 => res::<5, B>: "res 5, b", 2, 8,
+            0xA9 => res::<5, C>: "res 5, c", 2, 8,
+            0xAA => res::<5, D>: "res 5, d", 2, 8,
+            0xAB => res::<5, E>: "res 5, e", 2, 8,
+            0xAC => res::<5, H>: "res 5, h", 2, 8,
+            0xAD => res::<5, L>: "res 5, l", 2, 8,
+            0xAE => res::<5, MemHL>: "res 5, [hl]", 2, 16,
+            0xAF => res::<5, A>: "res 5, a", 2, 8,
+
+            // RES 6
+            0xB0 => res::<6, B>: "res 6, b", 2, 8,
+            0xB1 => res::<6, C>: "res 6, c", 2, 8,
+            0xB2 => res::<6, D>: "res 6, d", 2, 8,
+            0xB3 => res::<6, E>: "res 6, e", 2, 8,
+            0xB4 => res::<6, H>: "res 6, h", 2, 8,
+            0xB5 => res::<6, L>: "res 6, l", 2, 8,
+            0xB6 => res::<6, MemHL>: "res 6, [hl]", 2, 16,
+            0xB7 => res::<6, A>: "res 6, a", 2, 8,
+
+            // RES 7
+            0xB8 => res::<7, B>: "res 7, b", 2, 8,
+            0xB9 => res::<7, C>: "res 7, c", 2, 8,
+            0xBA => res::<7, D>: "res 7, d", 2, 8,
+            0xBB => res::<7, E>: "res 7, e", 2, 8,
+            0xBC => res::<7, H>: "res 7, h", 2, 8,
+            0xBD => res::<7, L>: "res 7, l", 2, 8,
+            0xBE => res::<7, MemHL>: "res 7, [hl]", 2, 16,
+            0xBF => res::<7, A>: "res 7, a", 2, 8,
+
+            // SET 0
+            0xC0 => set::<0, B>: "set 0, b", 2, 8,
+            0xC1 => set::<0, C>: "set 0, c", 2, 8,
+            0xC2 => set::<0, D>: "set 0, d", 2, 8,
+            0xC3 => set::<0, E>: "set 0, e", 2, 8,
+            0xC4 => set::<0, H>: "set 0, h", 2, 8,
+            0xC5 => set::<0, L>: "set 0, l", 2, 8,
+            0xC6 => set::<0, MemHL>: "set 0, [hl]", 2, 16,
+            0xC7 => set::<0, A>: "set 0, a", 2, 8,
+
+            // SET 1
+            0xC8 => set::<1, B>: "set 1, b", 2, 8,
+            0xC9 => set::<1, C>: "set 1, c", 2, 8,
+            0xCA => set::<1, D>: "set 1, d", 2, 8,
+            0xCB => set::<1, E>: "set 1, e", 2, 8,
+            0xCC => set::<1, H>: "set 1, h", 2, 8,
+            0xCD => set::<1, L>: "set 1, l", 2, 8,
+            0xCE => set::<1, MemHL>: "set 1, [hl]", 2, 16,
+            0xCF => set::<1, A>: "set 1, a", 2, 8,
+
+            // SET 2
+            0xD0 => set::<2, B>: "set 2, b", 2, 8,
+            0xD1 => set::<2, C>: "set 2, c", 2, 8,
+            0xD2 => set::<2, D>: "set 2, d", 2, 8,
+            0xD3 => set::<2, E>: "set 2, e", 2, 8,
+            0xD4 => set::<2, H>: "set 2, h", 2, 8,
+            0xD5 => set::<2, L>: "set 2, l", 2, 8,
+            0xD6 => set::<2, MemHL>: "set 2, [hl]", 2, 16,
+            0xD7 => set::<2, A>: "set 2, a", 2, 8,
+
+            // SET 3
+            0xD8 => set::<3, B>: "set 3, b", 2, 8,
+            0xD9 => set::<3, C>: "set 3, c", 2, 8,
+            0xDA => set::<3, D>: "set 3, d", 2, 8,
+            0xDB => set::<3, E>: "set 3, e", 2, 8,
+            0xDC => set::<3, H>: "set 3, h", 2, 8,
+            0xDD => set::<3, L>: "set 3, l", 2, 8,
+            0xDE => set::<3, MemHL>: "set 3, [hl]", 2, 16,
+            0xDF => set::<3, A>: "set 3, a", 2, 8,
+
+            // SET 4
+            0xE0 => set::<4, B>: "set 4, b", 2, 8,
+            0xE1 => set::<4, C>: "set 4, c", 2, 8,
+            0xE2 => set::<4, D>: "set 4, d", 2, 8,
+            0xE3 => set::<4, E>: "set 4, e", 2, 8,
+            0xE4 => set::<4, H>: "set 4, h", 2, 8,
+            0xE5 => set::<4, L>: "set 4, l", 2, 8,
+            0xE6 => set::<4, MemHL>: "set 4, [hl]", 2, 16,
+            0xE7 => set::<4, A>: "set 4, a", 2, 8,
+
+            // SET 5
+            0xE8 => set::<5, B>: "set 5, b", 2, 8,
+            0xE9 => set::<5, C>: "set 5, c", 2, 8,
+            0xEA => set::<5, D>: "set 5, d", 2, 8,
+            0xEB => set::<5, E>: "set 5, e", 2, 8,
+            0xEC => set::<5, H>: "set 5, h", 2, 8,
+            0xED => set::<5, L>: "set 5, l", 2, 8,
+            0xEE => set::<5, MemHL>: "set 5, [hl]", 2, 16,
+            0xEF => set::<5, A>: "set 5, a", 2, 8,
+
+            // SET 6
+            0xF0 => set::<6, B>: "set 6, b", 2, 8,
+            0xF1 => set::<6, C>: "set 6, c", 2, 8,
+            0xF2 => set::<6, D>: "set 6, d", 2, 8,
+            0xF3 => set::<6, E>: "set 6, e", 2, 8,
+            0xF4 => set::<6, H>: "set 6, h", 2, 8,
+            0xF5 => set::<6, L>: "set 6, l", 2, 8,
+            0xF6 => set::<6, MemHL>: "set 6, [hl]", 2, 16,
+            0xF7 => set::<6, A>: "set 6, a", 2, 8,
+
+            // SET 7
+            0xF8 => set::<7, B>: "set 7, b", 2, 8,
+            0xF9 => set::<7, C>: "set 7, c", 2, 8,
+            0xFA => set::<7, D>: "set 7, d", 2, 8,
+            0xFB => set::<7, E>: "set 7, e", 2, 8,
+            0xFC => set::<7, H>: "set 7, h", 2, 8,
+            0xFD => set::<7, L>: "set 7, l", 2, 8,
+            0xFE => set::<7, MemHL>: "set 7, [hl]", 2, 16,
+            0xFF => set::<7, A>: "set 7, a", 2, 8,
+        }
+    };
+}
+pub(crate) use cb_opcodes;
+
 pub fn exec_next(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let pc = cpu.regs.pc();
     let op_code = mmu.read(pc);
     cpu.regs.set_pc(pc.wrapping_add(1));
-    (match op_code {
-        // RLC
-        0x00 => rlc::<B>,
-        0x01 => rlc::<C>,
-        0x02 => rlc::<D>,
-        0x03 => rlc::<E>,
-        0x04 => rlc::<H>,
-        0x05 => rlc::<L>,
-        0x06 => rlc::<MemHL>,
-        0x07 => rlc::<A>,
-
-        // RRC
-        0x08 => rrc::<B>,
-        0x09 => rrc::<C>,
-        0x0A => rrc::<D>,
-        0x0B => rrc::<E>,
-        0x0C => rrc::<H>,
-        0x0D => rrc::<L>,
-        0x0E => rrc::<MemHL>,
-        0x0F => rrc::<A>,
-
-        // RL
-        0x10 => rl::<B>,
-        0x11 => rl::<C>,
-        0x12 => rl::<D>,
-        0x13 => rl::<E>,
-        0x14 => rl::<H>,
-        0x15 => rl::<L>,
-        0x16 => rl::<MemHL>,
-        0x17 => rl::<A>,
-
-        // RR
-        0x18 => rr::<B>,
-        0x19 => rr::<C>,
-        0x1A => rr::<D>,
-        0x1B => rr::<E>,
-        0x1C => rr::<H>,
-        0x1D => rr::<L>,
-        0x1E => rr::<MemHL>,
-        0x1F => rr::<A>,
-
-        // SLA
-        0x20 => sla::<B>,
-        0x21 => sla::<C>,
-        0x22 => sla::<D>,
-        0x23 => sla::<E>,
-        0x24 => sla::<H>,
-        0x25 => sla::<L>,
-        0x26 => sla::<MemHL>,
-        0x27 => sla::<A>,
-
-        // SRA
-        0x28 => sra::<B>,
-        0x29 => sra::<C>,
-        0x2A => sra::<D>,
-        0x2B => sra::<E>,
-        0x2C => sra::<H>,
-        0x2D => sra::<L>,
-        0x2E => sra::<MemHL>,
-        0x2F => sra::<A>,
-
-        // SWAP
-        0x30 => swap::<B>,
-        0x31 => swap::<C>,
-        0x32 => swap::<D>,
-        0x33 => swap::<E>,
-        0x34 => swap::<H>,
-        0x35 => swap::<L>,
-        0x36 => swap::<MemHL>,
-        0x37 => swap::<A>,
-
-        // SRL
-        0x38 => srl::<B>,
-        0x39 => srl::<C>,
-        0x3A => srl::<D>,
-        0x3B => srl::<E>,
-        0x3C => srl::<H>,
-        0x3D => srl::<L>,
-        0x3E => srl::<MemHL>,
-        0x3F => srl::<A>,
-
-        // BIT 0
-        0x40 => bit::<0, B>,
-        0x41 => bit::<0, C>,
-        0x42 => bit::<0, D>,
-        0x43 => bit::<0, E>,
-        0x44 => bit::<0, H>,
-        0x45 => bit::<0, L>,
-        0x46 => bit::<0, MemHL>,
-        0x47 => bit::<0, A>,
-
-        // BIT 1
-        0x48 => bit::<1, B>,
-        0x49 => bit::<1, C>,
-        0x4A => bit::<1, D>,
-        0x4B => bit::<1, E>,
-        0x4C => bit::<1, H>,
-        0x4D => bit::<1, L>,
-        0x4E => bit::<1, MemHL>,
-        0x4F => bit::<1, A>,
-
-        // BIT 2
-        0x50 => bit::<2, B>,
-        0x51 => bit::<2, C>,
-        0x52 => bit::<2, D>,
-        0x53 => bit::<2, E>,
-        0x54 => bit::<2, H>,
-        0x55 => bit::<2, L>,
-        0x56 => bit::<2, MemHL>,
-        0x57 => bit::<2, A>,
-
-        // BIT 3
-        0x58 => bit::<3, B>,
-        0x59 => bit::<3, C>,
-        0x5A => bit::<3, D>,
-        0x5B => bit::<3, E>,
-        0x5C => bit::<3, H>,
-        0x5D => bit::<3, L>,
-        0x5E => bit::<3, MemHL>,
-        0x5F => bit::<3, A>,
-
-        // BIT 4
-        0x60 => bit::<4, B>,
-        0x61 => bit::<4, C>,
-        0x62 => bit::<4, D>,
-        0x63 => bit::<4, E>,
-        0x64 => bit::<4, H>,
-        0x65 => bit::<4, L>,
-        0x66 => bit::<4, MemHL>,
-        0x67 => bit::<4, A>,
-
-        // BIT 5
-        0x68 => bit::<5, B>,
-        0x69 => bit::<5, C>,
-        0x6A => bit::<5, D>,
-        0x6B => bit::<5, E>,
-        0x6C => bit::<5, H>,
-        0x6D => bit::<5, L>,
-        0x6E => bit::<5, MemHL>,
-        0x6F => bit::<5, A>,
-
-        // BIT 6
-        0x70 => bit::<6, B>,
-        0x71 => bit::<6, C>,
-        0x72 => bit::<6, D>,
-        0x73 => bit::<6, E>,
-        0x74 => bit::<6, H>,
-        0x75 => bit::<6, L>,
-        0x76 => bit::<6, MemHL>,
-        0x77 => bit::<6, A>,
-
-        // BIT 7
-        0x78 => bit::<7, B>,
-        0x79 => bit::<7, C>,
-        0x7A => bit::<7, D>,
-        0x7B => bit::<7, E>,
-        0x7C => bit::<7, H>,
-        0x7D => bit::<7, L>,
-        0x7E => bit::<7, MemHL>,
-        0x7F => bit::<7, A>,
-
-        // RES 0
-        0x80 => res::<0, B>,
-        0x81 => res::<0, C>,
-        0x82 => res::<0, D>,
-        0x83 => res::<0, E>,
-        0x84 => res::<0, H>,
-        0x85 => res::<0, L>,
-        0x86 => res::<0, MemHL>,
-        0x87 => res::<0, A>,
-
-        // RES 1
-        0x88 => res::<1, B>,
-        0x89 => res::<1, C>,
-        0x8A => res::<1, D>,
-        0x8B => res::<1, E>,
-        0x8C => res::<1, H>,
-        0x8D => res::<1, L>,
-        0x8E => res::<1, MemHL>,
-        0x8F => res::<1, A>,
-
-        // RES 2
-        0x90 => res::<2, B>,
-        0x91 => res::<2, C>,
-        0x92 => res::<2, D>,
-        0x93 => res::<2, E>,
-        0x94 => res::<2, H>,
-        0x95 => res::<2, L>,
-        0x96 => res::<2, MemHL>,
-        0x97 => res::<2, A>,
-
-        // RES 3
-        0x98 => res::<3, B>,
-        0x99 => res::<3, C>,
-        0x9A => res::<3, D>,
-        0x9B => res::<3, E>,
-        0x9C => res::<3, H>,
-        0x9D => res::<3, L>,
-        0x9E => res::<3, MemHL>,
-        0x9F => res::<3, A>,
-
-        // RES 4
-        0xA0 => res::<4, B>,
-        0xA1 => res::<4, C>,
-        0xA2 => res::<4, D>,
-        0xA3 => res::<4, E>,
-        0xA4 => res::<4, H>,
-        0xA5 => res::<4, L>,
-        0xA6 => res::<4, MemHL>,
-        0xA7 => res::<4, A>,
-
-        // RES 5
-        0xA8 => res::<5, B>,
-        0xA9 => res::<5, C>,
-        0xAA => res::<5, D>,
-        0xAB => res::<5, E>,
-        0xAC => res::<5, H>,
-        0xAD => res::<5, L>,
-        0xAE => res::<5, MemHL>,
-        0xAF => res::<5, A>,
-
-        // RES 6
-        0xB0 => res::<6, B>,
-        0xB1 => res::<6, C>,
-        0xB2 => res::<6, D>,
-        0xB3 => res::<6, E>,
-        0xB4 => res::<6, H>,
-        0xB5 => res::<6, L>,
-        0xB6 => res::<6, MemHL>,
-        0xB7 => res::<6, A>,
-
-        // RES 7
-        0xB8 => res::<7, B>,
-        0xB9 => res::<7, C>,
-        0xBA => res::<7, D>,
-        0xBB => res::<7, E>,
-        0xBC => res::<7, H>,
-        0xBD => res::<7, L>,
-        0xBE => res::<7, MemHL>,
-        0xBF => res::<7, A>,
-
-        // SET 0
-        0xC0 => set::<0, B>,
-        0xC1 => set::<0, C>,
-        0xC2 => set::<0, D>,
-        0xC3 => set::<0, E>,
-        0xC4 => set::<0, H>,
-        0xC5 => set::<0, L>,
-        0xC6 => set::<0, MemHL>,
-        0xC7 => set::<0, A>,
-
-        // SET 1
-        0xC8 => set::<1, B>,
-        0xC9 => set::<1, C>,
-        0xCA => set::<1, D>,
-        0xCB => set::<1, E>,
-        0xCC => set::<1, H>,
-        0xCD => set::<1, L>,
-        0xCE => set::<1, MemHL>,
-        0xCF => set::<1, A>,
-
-        // SET 2
-        0xD0 => set::<2, B>,
-        0xD1 => set::<2, C>,
-        0xD2 => set::<2, D>,
-        0xD3 => set::<2, E>,
-        0xD4 => set::<2, H>,
-        0xD5 => set::<2, L>,
-        0xD6 => set::<2, MemHL>,
-        0xD7 => set::<2, A>,
-
-        // SET 3
-        0xD8 => set::<3, B>,
-        0xD9 => set::<3, C>,
-        0xDA => set::<3, D>,
-        0xDB => set::<3, E>,
-        0xDC => set::<3, H>,
-        0xDD => set::<3, L>,
-        0xDE => set::<3, MemHL>,
-        0xDF => set::<3, A>,
-
-        // SET 4
-        0xE0 => set::<4, B>,
-        0xE1 => set::<4, C>,
-        0xE2 => set::<4, D>,
-        0xE3 => set::<4, E>,
-        0xE4 => set::<4, H>,
-        0xE5 => set::<4, L>,
-        0xE6 => set::<4, MemHL>,
-        0xE7 => set::<4, A>,
-
-        // SET 5
-        0xE8 => set::<5, B>,
-        0xE9 => set::<5, C>,
-        0xEA => set::<5, D>,
-        0xEB => set::<5, E>,
-        0xEC => set::<5, H>,
-        0xED => set::<5, L>,
-        0xEE => set::<5, MemHL>,
-        0xEF => set::<5, A>,
-
-        // SET 6
-        0xF0 => set::<6, B>,
-        0xF1 => set::<6, C>,
-        0xF2 => set::<6, D>,
-        0xF3 => set::<6, E>,
-        0xF4 => set::<6, H>,
-        0xF5 => set::<6, L>,
-        0xF6 => set::<6, MemHL>,
-        0xF7 => set::<6, A>,
-
-        // SET 7
-        0xF8 => set::<7, B>,
-        0xF9 => set::<7, C>,
-        0xFA => set::<7, D>,
-        0xFB => set::<7, E>,
-        0xFC => set::<7, H>,
-        0xFD => set::<7, L>,
-        0xFE => set::<7, MemHL>,
-        0xFF => set::<7, A>,
-    })(cpu, mmu)
+    (cb_opcodes!(exec_match(op_code)))(cpu, mmu)
 }
 
 fn bit<const B: u8, R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -348,7 +357,7 @@ fn rl<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val >> 7 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn rr<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -358,7 +367,7 @@ fn rr<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val & 0x01 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn srl<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -368,7 +377,7 @@ fn srl<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val & 0x01 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn swap<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -377,19 +386,19 @@ fn swap<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<ZERO>(res == 0);
     cpu.regs.set_flag::<{ SUB | HCARRY | CARRY }>(false);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn res<const B: u8, RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let res = RW::read(cpu, mmu) & !(1 << B);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn set<const B: u8, RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let res = RW::read(cpu, mmu) | (1 << B);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn rlc<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -399,7 +408,7 @@ fn rlc<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val >> 7 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn rrc<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -409,7 +418,7 @@ fn rrc<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val & 0x01 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn sla<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -419,7 +428,7 @@ fn sla<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val >> 7 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
 
 fn sra<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
@@ -429,5 +438,5 @@ fn sra<RW: r8::Read + r8::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<{ SUB | HCARRY }>(false);
     cpu.regs.set_flag::<CARRY>(val & 0x01 == 1);
     RW::write(cpu, mmu, res);
-    8 + <RW as r8::Read>::CYCLES_OVERHEAD
+    8 + <RW as r8::Read>::CYCLES_OVERHEAD + <RW as r8::Write>::CYCLES_OVERHEAD
 }
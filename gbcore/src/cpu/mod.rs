@@ -3,28 +3,136 @@ mod r16;
 mod r8;
 mod regs;
 
-use crate::{Memory, cartridge::Cartridge, mmu::MMU};
+use crate::{
+    Memory, Model, bus::Bus, cartridge::Cartridge, gpu::colors::compat_title_checksum, mmu::MMU,
+    trace::Trace,
+};
 use r8::{
     A, B, C, D, D8, E, H, L, MemBC, MemC, MemD8, MemD16, MemDE, MemHL, MemHLDec, MemHLInc, Read,
 };
 use r16::{AF, BC, D16, DE, HL, PC, Read as Read16, SP};
-use regs::{CARRY, HCARRY, Registers, SUB, ZERO};
+use regs::{CARRY, HCARRY, SUB, ZERO};
+
+pub(crate) use cb::cb_opcodes;
+use cb::exec_next as prefix_cb;
+pub use regs::Registers;
+
+// CPU hung by an illegal opcode, until the console is reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockUp {
+    pub opcode: u8,
+    // Address of the opcode
+    pub pc: u16,
+}
 
 #[derive(Default)]
 pub struct Cpu {
     regs: Registers,
     halt: bool,
+    // STOP: low-power mode until a key is pressed
+    stop: bool,
+    // M-cycles left before the CPU runs again after a speed switch
+    switch_pause: u16,
+    // PC is not incremented by the next opcode fetch
+    halt_bug: bool,
     i_master: bool,
+    lock_up: Option<LockUp>,
+    // Log of the executed instructions, see SystemBuilder::trace
+    pub(crate) trace: Option<Trace>,
+    // EI takes effect after the next instruction
+    ime_pending: bool,
+    // Interrupt (IF bit) dispatched by the last tick
+    dispatched: Option<u8>,
 }
 
 impl Cpu {
-    pub fn reset(&mut self, mmu: &mut impl Memory) {
+    pub fn regs(&self) -> &Registers {
+        &self.regs
+    }
+
+    pub fn regs_mut(&mut self) -> &mut Registers {
+        &mut self.regs
+    }
+
+    // IME: Interrupt master enable flag
+    pub fn ime(&self) -> bool {
+        self.i_master
+    }
+
+    pub fn set_ime(&mut self, value: bool) {
+        self.i_master = value;
+        self.ime_pending = false;
+    }
+
+    pub fn lock_up(&self) -> Option<LockUp> {
+        self.lock_up
+    }
+
+    // Execute the next instruction without checking interrupts
+    pub fn step(&mut self, mem: &mut impl Memory) -> u8 {
+        if self.lock_up.is_some() {
+            return 4;
+        }
+        self.apply_ime_pending();
+        exec_next(self, mem)
+    }
+
+    fn apply_ime_pending(&mut self) {
+        if self.ime_pending {
+            self.ime_pending = false;
+            self.i_master = true;
+        }
+    }
+
+    pub fn reset(&mut self, mmu: &mut impl Memory, model: Model, cgb_mode: bool) {
         // SHORTCUT TO INIT CPU & MEMORY WITHOUT BOOT SEQUENCE
+        // Registers left by each boot ROM (Pan Docs "Power Up Sequence")
+        let header_checksum = mmu.read(0x014D);
+        let (af, bc, de, hl) = match model {
+            Model::Dmg | Model::Mgb => {
+                // H and C are set unless the header checksum is 0x00
+                let f = match header_checksum {
+                    0x00 => ZERO,
+                    _ => ZERO | HCARRY | CARRY,
+                };
+                let a: u16 = if model == Model::Dmg { 0x01 } else { 0xFF };
+                (a << 8 | f as u16, 0x0013, 0x00D8, 0x014D)
+            }
+            Model::Sgb => (0x0100, 0x0014, 0x0000, 0xC060),
+            Model::Cgb | Model::Agb => {
+                let (b, de, hl) = match cgb_mode {
+                    true => (0x00, 0xFF56, 0x000D),
+                    false => {
+                        let b = compat_title_checksum(mmu);
+                        let hl = match b {
+                            0x43 | 0x58 => 0x991A,
+                            _ => 0x007C,
+                        };
+                        (b, 0x0008, hl)
+                    }
+                };
+                match model {
+                    // The AGB boot ROM ends with an extra INC B
+                    Model::Agb => {
+                        let b = b.wrapping_add(1);
+                        let mut f = 0;
+                        if b == 0 {
+                            f |= ZERO;
+                        }
+                        if b & 0x0F == 0 {
+                            f |= HCARRY;
+                        }
+                        (0x1100 | f as u16, (b as u16) << 8, de, hl)
+                    }
+                    _ => (0x1180, (b as u16) << 8, de, hl),
+                }
+            }
+        };
         self.regs.set_pc(0x0100);
-        self.regs.set_af(0x11B0); // A = 0x11 -> CGB / A = 0x01 -> GB
-        self.regs.set_bc(0x0012);
-        self.regs.set_de(0x00D8);
-        self.regs.set_hl(0x014D);
+        self.regs.set_af(af);
+        self.regs.set_bc(bc);
+        self.regs.set_de(de);
+        self.regs.set_hl(hl);
         self.regs.set_sp(0xFFFE);
 
         mmu.write(0xFF50, 0x01);
@@ -48,7 +156,7 @@ impl Cpu {
         mmu.write(0xFF23, 0xBF); // NR30
         mmu.write(0xFF24, 0x77); // NR50
         mmu.write(0xFF25, 0xF3); // NR51
-        mmu.write(0xFF26, 0xF1); // NR52
+        mmu.write(0xFF26, if model == Model::Sgb { 0xF0 } else { 0xF1 }); // NR52
         mmu.write(0xFF40, 0x91); // LCDC
         mmu.write(0xFF42, 0x00); // SCY
         mmu.write(0xFF43, 0x00); // SCX
@@ -61,391 +169,497 @@ impl Cpu {
         mmu.write(0xFFFF, 0x00); // IE
     }
 
-    pub fn tick<C: Cartridge>(&mut self, mmu: &mut MMU<C>) -> u8 {
-        let addr_interrupt = self.next_interrupt(mmu);
-        if addr_interrupt != 0x0000 {
-            self.halt = false;
-            push::<PC>(self, mmu);
-            self.regs.set_pc(addr_interrupt);
-            return 8;
+    pub(crate) fn tick<C: Cartridge>(&mut self, bus: &mut Bus<C>) -> u8 {
+        self.dispatched = None;
+        // Nothing but a reset gets out of a lock-up, interrupts included
+        if self.lock_up.is_some() {
+            return 4;
         }
-        if self.halt {
+        if self.switch_pause > 0 {
+            self.switch_pause -= 1;
             return 4;
         }
+        // Interrupts are not serviced until a pressed key selected in P1
+        // wakes the CPU up
+        if self.stop {
+            if bus.mmu.read(0xFF00) & 0x0F == 0x0F {
+                return 4;
+            }
+            self.stop = false;
+        }
+        let pending = pending_interrupts(bus.mmu);
+        if self.i_master && pending != 0 {
+            return self.dispatch_interrupt(bus);
+        }
+        if self.halt {
+            // Leave HALT without servicing the interrupt when IME=0
+            if pending == 0 {
+                return 4;
+            }
+            self.halt = false;
+        }
 
-        exec_next(self, mmu)
+        self.apply_ime_pending();
+        if let Some(trace) = &mut self.trace {
+            trace.log(&self.regs, bus.mmu);
+        }
+        let cycles = exec_next(self, bus);
+        // HALT bug: with IME=0 and an interrupt pending, HALT is skipped and
+        // the next byte is read twice
+        if self.halt && !self.i_master && pending_interrupts(bus.mmu) != 0 {
+            self.halt = false;
+            self.halt_bug = true;
+        }
+        if self.stop {
+            self.enter_stop(bus.mmu);
+        }
+        cycles
     }
 
-    fn next_interrupt<C: Cartridge>(&mut self, mmu: &mut MMU<C>) -> u16 {
-        let i_flag = mmu.interrupt.ff0f_if;
-        if self.halt && i_flag & 0b1111 != 0 {
-            self.halt = false;
+    pub(crate) fn dispatched(&self) -> Option<u8> {
+        self.dispatched
+    }
+
+    // What the next tick does, see tick
+    pub(crate) fn next_tick<C: Cartridge>(&self, mmu: &mut MMU<C>) -> NextTick {
+        let pending = pending_interrupts(mmu);
+        if self.lock_up.is_some() || self.switch_pause > 0 {
+            return NextTick::Idle;
         }
-        if !self.i_master {
-            return 0x0000;
+        if self.stop && mmu.read(0xFF00) & 0x0F == 0x0F {
+            return NextTick::Idle;
         }
-        let i_enable = mmu.interrupt.ffff_ie;
-        let valid_interrupts = i_enable & i_flag;
-        match valid_interrupts.trailing_zeros() {
-            // V-Blank
-            0 => {
-                mmu.interrupt.ff0f_if &= !(1 << 0);
-                self.i_master = false;
-                0x0040
-            }
-            // LCD STAT
-            1 => {
-                mmu.interrupt.ff0f_if &= !(1 << 1);
-                self.i_master = false;
-                0x0048
-            }
-            // Timer
-            2 => {
-                mmu.interrupt.ff0f_if &= !(1 << 2);
-                self.i_master = false;
-                0x0050
-            }
-            // Serial
-            3 => {
-                mmu.interrupt.ff0f_if &= !(1 << 3);
-                self.i_master = false;
-                0x0058
-            }
-            // Joypad
-            4 => {
-                mmu.interrupt.ff0f_if &= !(1 << 4);
-                self.i_master = false;
-                0x0060
+        if self.i_master && pending != 0 {
+            return NextTick::Interrupt;
+        }
+        match self.halt && pending == 0 {
+            true => NextTick::Idle,
+            false => NextTick::Instruction,
+        }
+    }
+
+    pub(crate) fn stopped(&self) -> bool {
+        self.stop || self.switch_pause > 0
+    }
+
+    // DIV is reset. On CGB, an armed KEY1 switches speed instead of stopping,
+    // the CPU and the LCD pause for a while.
+    fn enter_stop<C: Cartridge>(&mut self, mmu: &mut MMU<C>) {
+        mmu.write(0xFF04, 0x00);
+        if mmu.speed.switch_armed {
+            mmu.speed.switch();
+            self.stop = false;
+            self.switch_pause = SPEED_SWITCH_PAUSE;
+        }
+    }
+
+    // 5 M-cycles: 2 wait states, push PC, jump. One more when halted.
+    fn dispatch_interrupt<C: Cartridge>(&mut self, bus: &mut Bus<C>) -> u8 {
+        let halted = self.halt;
+        self.halt = false;
+        self.i_master = false;
+        if halted {
+            bus.internal();
+        }
+        bus.internal();
+        bus.internal();
+
+        let pc = self.regs.pc();
+        let sp = self.regs.sp().wrapping_sub(1);
+        bus.write(sp, (pc >> 8) as u8);
+        // The interrupt is selected after the high byte push: writing it to
+        // IE (SP=0000) can change or cancel it, PC is then set to 0000
+        let pending = pending_interrupts(bus.mmu);
+        let sp = sp.wrapping_sub(1);
+        bus.write(sp, (pc & 0x00FF) as u8);
+        self.regs.set_sp(sp);
+
+        let addr = match pending.trailing_zeros() {
+            // V-Blank, LCD STAT, Timer, Serial, Joypad
+            n @ 0..=4 => {
+                bus.mmu.interrupt.ff0f_if &= !(1 << n);
+                self.dispatched = Some(n as u8);
+                0x0040 + n as u16 * 8
             }
             _ => 0x0000,
+        };
+        self.regs.set_pc(addr);
+        bus.internal();
+        match halted {
+            true => 24,
+            false => 20,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NextTick {
+    // Runs the instruction at PC
+    Instruction,
+    // Dispatches an interrupt
+    Interrupt,
+    // M-cycle spent halted, stopped or locked up
+    Idle,
+}
+
+// M-cycles
+const SPEED_SWITCH_PAUSE: u16 = 2050;
+
+fn pending_interrupts<C: Cartridge>(mmu: &MMU<C>) -> u8 {
+    mmu.interrupt.ffff_ie & mmu.interrupt.ff0f_if & 0x1F
+}
+
+// Opcode table, shared by the CPU and the disassembler. Calls
+// `$callback!((args) opcode => function: "text", length, cycles, ...)`.
+// Conditional instructions give the cycles without / with the branch taken.
+// In the text, n8/n16 are immediates, a8 is an address in FF00-FFFF, a16 an
+// address, r8 a relative jump and e8 a signed offset.
+macro_rules! opcodes {
+    ($callback:ident($($args:tt)*)) => {
+        $callback! {
+            ($($args)*)
+            0x00 => nop: "nop", 1, 4,
+            0x10 => stop: "stop", 2, 4,
+            0xF3 => disable_interrupts: "di", 1, 4,
+            0xFB => enable_interrupts: "ei", 1, 4,
+            0x76 => halt: "halt", 1, 4,
+            0xCB => prefix_cb: "prefix", 1, 4,
+
+            // Do not exist
+            0xD3 => illegal::<0xD3>: "db $D3", 1, 4,
+            0xDB => illegal::<0xDB>: "db $DB", 1, 4,
+            0xDD => illegal::<0xDD>: "db $DD", 1, 4,
+            0xE3 => illegal::<0xE3>: "db $E3", 1, 4,
+            0xE4 => illegal::<0xE4>: "db $E4", 1, 4,
+            0xEB => illegal::<0xEB>: "db $EB", 1, 4,
+            0xEC => illegal::<0xEC>: "db $EC", 1, 4,
+            0xED => illegal::<0xED>: "db $ED", 1, 4,
+            0xF4 => illegal::<0xF4>: "db $F4", 1, 4,
+            0xFC => illegal::<0xFC>: "db $FC", 1, 4,
+            0xFD => illegal::<0xFD>: "db $FD", 1, 4,
+
+            // INC
+            0x04 => inc::<B>: "inc b", 1, 4,
+            0x0C => inc::<C>: "inc c", 1, 4,
+            0x14 => inc::<D>: "inc d", 1, 4,
+            0x1C => inc::<E>: "inc e", 1, 4,
+            0x24 => inc::<H>: "inc h", 1, 4,
+            0x2C => inc::<L>: "inc l", 1, 4,
+            0x34 => inc::<MemHL>: "inc [hl]", 1, 12,
+            0x3C => inc::<A>: "inc a", 1, 4,
+
+            // INC 16-bit
+            0x03 => inc16::<BC>: "inc bc", 1, 8,
+            0x13 => inc16::<DE>: "inc de", 1, 8,
+            0x23 => inc16::<HL>: "inc hl", 1, 8,
+            0x33 => inc16::<SP>: "inc sp", 1, 8,
+
+            // DEC
+            0x05 => dec::<B>: "dec b", 1, 4,
+            0x0D => dec::<C>: "dec c", 1, 4,
+            0x15 => dec::<D>: "dec d", 1, 4,
+            0x1D => dec::<E>: "dec e", 1, 4,
+            0x25 => dec::<H>: "dec h", 1, 4,
+            0x2D => dec::<L>: "dec l", 1, 4,
+            0x35 => dec::<MemHL>: "dec [hl]", 1, 12,
+            0x3D => dec::<A>: "dec a", 1, 4,
+
+            // DEC 16-bit
+            0x0B => dec16::<BC>: "dec bc", 1, 8,
+            0x1B => dec16::<DE>: "dec de", 1, 8,
+            0x2B => dec16::<HL>: "dec hl", 1, 8,
+            0x3B => dec16::<SP>: "dec sp", 1, 8,
+
+            // ADD 16-bit
+            0x09 => add16::<HL, BC>: "add hl, bc", 1, 8,
+            0x19 => add16::<HL, DE>: "add hl, de", 1, 8,
+            0x29 => add16::<HL, HL>: "add hl, hl", 1, 8,
+            0x39 => add16::<HL, SP>: "add hl, sp", 1, 8,
+
+            0x07 => rlca: "rlca", 1, 4,
+            0x17 => rla: "rla", 1, 4,
+            0x0F => rrca: "rrca", 1, 4,
+            0x1F => rra: "rra", 1, 4,
+            0x2f => cpl: "cpl", 1, 4,
+            0x37 => scf: "scf", 1, 4,
+            0x3F => ccf: "ccf", 1, 4,
+            0x27 => daa: "daa", 1, 4,
+
+            // JR
+            0x18 => jr::<D8>: "jr r8", 2, 12,
+            0x28 => jr_flag::<ZERO, D8>: "jr z, r8", 2, 8 / 12,
+            0x38 => jr_flag::<CARRY, D8>: "jr c, r8", 2, 8 / 12,
+            0x20 => jr_n_flag::<ZERO, D8>: "jr nz, r8", 2, 8 / 12,
+            0x30 => jr_n_flag::<CARRY, D8>: "jr nc, r8", 2, 8 / 12,
+
+            // JP
+            0xC3 => jp::<D16>: "jp a16", 3, 16,
+            0xCA => jp_flag::<ZERO, D16>: "jp z, a16", 3, 12 / 16,
+            0xDA => jp_flag::<CARRY, D16>: "jp c, a16", 3, 12 / 16,
+            0xC2 => jp_n_flag::<ZERO, D16>: "jp nz, a16", 3, 12 / 16,
+            0xD2 => jp_n_flag::<CARRY, D16>: "jp nc, a16", 3, 12 / 16,
+            0xE9 => jp_hl: "jp hl", 1, 4,
+
+            // CALL
+            0xCD => call::<D16>: "call a16", 3, 24,
+            0xCC => call_flag::<ZERO, D16>: "call z, a16", 3, 12 / 24,
+            0xDC => call_flag::<CARRY, D16>: "call c, a16", 3, 12 / 24,
+            0xC4 => call_n_flag::<ZERO, D16>: "call nz, a16", 3, 12 / 24,
+            0xD4 => call_n_flag::<CARRY, D16>: "call nc, a16", 3, 12 / 24,
+
+            // RST
+            0xC7 => rst::<0x0000>: "rst $0000", 1, 16,
+            0xD7 => rst::<0x0010>: "rst $0010", 1, 16,
+            0xE7 => rst::<0x0020>: "rst $0020", 1, 16,
+            0xF7 => rst::<0x0030>: "rst $0030", 1, 16,
+            0xCF => rst::<0x0008>: "rst $0008", 1, 16,
+            0xDF => rst::<0x0018>: "rst $0018", 1, 16,
+            0xEF => rst::<0x0028>: "rst $0028", 1, 16,
+            0xFF => rst::<0x0038>: "rst $0038", 1, 16,
+
+            // RET
+            0xC9 => ret: "ret", 1, 16,
+            0xD9 => reti: "reti", 1, 16,
+            0xC8 => ret_flag::<ZERO>: "ret z", 1, 8 / 20,
+            0xD8 => ret_flag::<CARRY>: "ret c", 1, 8 / 20,
+            0xC0 => ret_n_flag::<ZERO>: "ret nz", 1, 8 / 20,
+            0xD0 => ret_n_flag::<CARRY>: "ret nc", 1, 8 / 20,
+
+            // PUSH
+            0xC5 => push::<BC>: "push bc", 1, 16,
+            0xD5 => push::<DE>: "push de", 1, 16,
+            0xE5 => push::<HL>: "push hl", 1, 16,
+            0xF5 => push::<AF>: "push af", 1, 16,
+
+            // POP
+            0xC1 => pop::<BC>: "pop bc", 1, 12,
+            0xD1 => pop::<DE>: "pop de", 1, 12,
+            0xE1 => pop::<HL>: "pop hl", 1, 12,
+            0xF1 => pop_af: "pop af", 1, 12,
+
+            // ADD
+            0x80 => add::<B>: "add a, b", 1, 4,
+            0x81 => add::<C>: "add a, c", 1, 4,
+            0x82 => add::<D>: "add a, d", 1, 4,
+            0x83 => add::<E>: "add a, e", 1, 4,
+            0x84 => add::<H>: "add a, h", 1, 4,
+            0x85 => add::<L>: "add a, l", 1, 4,
+            0x86 => add::<MemHL>: "add a, [hl]", 1, 8,
+            0x87 => add::<A>: "add a, a", 1, 4,
+            0xC6 => add::<D8>: "add a, n8", 2, 8,
+
+            // ADC
+            0x88 => adc::<B>: "adc a, b", 1, 4,
+            0x89 => adc::<C>: "adc a, c", 1, 4,
+            0x8A => adc::<D>: "adc a, d", 1, 4,
+            0x8B => adc::<E>: "adc a, e", 1, 4,
+            0x8C => adc::<H>: "adc a, h", 1, 4,
+            0x8D => adc::<L>: "adc a, l", 1, 4,
+            0x8E => adc::<MemHL>: "adc a, [hl]", 1, 8,
+            0x8F => adc::<A>: "adc a, a", 1, 4,
+            0xCE => adc::<D8>: "adc a, n8", 2, 8,
+
+            // SUB
+            0x90 => sub::<B>: "sub a, b", 1, 4,
+            0x91 => sub::<C>: "sub a, c", 1, 4,
+            0x92 => sub::<D>: "sub a, d", 1, 4,
+            0x93 => sub::<E>: "sub a, e", 1, 4,
+            0x94 => sub::<H>: "sub a, h", 1, 4,
+            0x95 => sub::<L>: "sub a, l", 1, 4,
+            0x96 => sub::<MemHL>: "sub a, [hl]", 1, 8,
+            0x97 => sub::<A>: "sub a, a", 1, 4,
+            0xD6 => sub::<D8>: "sub a, n8", 2, 8,
+
+            // SBC
+            0x98 => sbc::<B>: "sbc a, b", 1, 4,
+            0x99 => sbc::<C>: "sbc a, c", 1, 4,
+            0x9A => sbc::<D>: "sbc a, d", 1, 4,
+            0x9B => sbc::<E>: "sbc a, e", 1, 4,
+            0x9C => sbc::<H>: "sbc a, h", 1, 4,
+            0x9D => sbc::<L>: "sbc a, l", 1, 4,
+            0x9E => sbc::<MemHL>: "sbc a, [hl]", 1, 8,
+            0x9F => sbc::<A>: "sbc a, a", 1, 4,
+            0xDE => sbc::<D8>: "sbc a, n8", 2, 8,
+
+            // AND
+            0xA0 => and::<B>: "and a, b", 1, 4,
+            0xA1 => and::<C>: "and a, c", 1, 4,
+            0xA2 => and::<D>: "and a, d", 1, 4,
+            0xA3 => and::<E>: "and a, e", 1, 4,
+            0xA4 => and::<H>: "and a, h", 1, 4,
+            0xA5 => and::<L>: "and a, l", 1, 4,
+            0xA6 => and::<MemHL>: "and a, [hl]", 1, 8,
+            0xA7 => and::<A>: "and a, a", 1, 4,
+            0xE6 => and::<D8>: "and a, n8", 2, 8,
+
+            // XOR
+            0xA8 => xor::<B>: "xor a, b", 1, 4,
+            0xA9 => xor::<C>: "xor a, c", 1, 4,
+            0xAA => xor::<D>: "xor a, d", 1, 4,
+            0xAB => xor::<E>: "xor a, e", 1, 4,
+            0xAC => xor::<H>: "xor a, h", 1, 4,
+            0xAD => xor::<L>: "xor a, l", 1, 4,
+            0xAE => xor::<MemHL>: "xor a, [hl]", 1, 8,
+            0xAF => xor::<A>: "xor a, a", 1, 4,
+            0xEE => xor::<D8>: "xor a, n8", 2, 8,
+
+            // OR
+            0xB0 => or::<B>: "or a, b", 1, 4,
+            0xB1 => or::<C>: "or a, c", 1, 4,
+            0xB2 => or::<D>: "or a, d", 1, 4,
+            0xB3 => or::<E>: "or a, e", 1, 4,
+            0xB4 => or::<H>: "or a, h", 1, 4,
+            0xB5 => or::<L>: "or a, l", 1, 4,
+            0xB6 => or::<MemHL>: "or a, [hl]", 1, 8,
+            0xB7 => or::<A>: "or a, a", 1, 4,
+            0xF6 => or::<D8>: "or a, n8", 2, 8,
+
+            // CP
+            0xB8 => cp::<B>: "cp a, b", 1, 4,
+            0xB9 => cp::<C>: "cp a, c", 1, 4,
+            0xBA => cp::<D>: "cp a, d", 1, 4,
+            0xBB => cp::<E>: "cp a, e", 1, 4,
+            0xBC => cp::<H>: "cp a, h", 1, 4,
+            0xBD => cp::<L>: "cp a, l", 1, 4,
+            0xBE => cp::<MemHL>: "cp a, [hl]", 1, 8,
+            0xBF => cp::<A>: "cp a, a", 1, 4,
+            0xFE => cp::<D8>: "cp a, n8", 2, 8,
+
+            // LD 16-bit
+            0x01 => ld_16::<BC, D16>: "ld bc, n16", 3, 12,
+            0x11 => ld_16::<DE, D16>: "ld de, n16", 3, 12,
+            0x21 => ld_16::<HL, D16>: "ld hl, n16", 3, 12,
+            0x31 => ld_16::<SP, D16>: "ld sp, n16", 3, 12,
+            0xF8 => ld_hl_spr8: "ld hl, sp+e8", 2, 12,
+            0xF9 => ld_sp_hl: "ld sp, hl", 1, 8,
+            0x08 => ld_a16_sp: "ld [a16], sp", 3, 20,
+            0xE8 => add_sp_r8: "add sp, e8", 2, 16,
+
+            // LD 8-bit
+            0x40 => ld::<B, B>: "ld b, b", 1, 4,
+            0x41 => ld::<B, C>: "ld b, c", 1, 4,
+            0x42 => ld::<B, D>: "ld b, d", 1, 4,
+            0x43 => ld::<B, E>: "ld b, e", 1, 4,
+            0x44 => ld::<B, H>: "ld b, h", 1, 4,
+            0x45 => ld::<B, L>: "ld b, l", 1, 4,
+            0x46 => ld::<B, MemHL>: "ld b, [hl]", 1, 8,
+            0x47 => ld::<B, A>: "ld b, a", 1, 4,
+            0x06 => ld::<B, D8>: "ld b, n8", 2, 8,
+
+            0x48 => ld::<C, B>: "ld c, b", 1, 4,
+            0x49 => ld::<C, C>: "ld c, c", 1, 4,
+            0x4A => ld::<C, D>: "ld c, d", 1, 4,
+            0x4B => ld::<C, E>: "ld c, e", 1, 4,
+            0x4C => ld::<C, H>: "ld c, h", 1, 4,
+            0x4D => ld::<C, L>: "ld c, l", 1, 4,
+            0x4E => ld::<C, MemHL>: "ld c, [hl]", 1, 8,
+            0x4F => ld::<C, A>: "ld c, a", 1, 4,
+            0x0E => ld::<C, D8>: "ld c, n8", 2, 8,
+
+            0x50 => ld::<D, B>: "ld d, b", 1, 4,
+            0x51 => ld::<D, C>: "ld d, c", 1, 4,
+            0x52 => ld::<D, D>: "ld d, d", 1, 4,
+            0x53 => ld::<D, E>: "ld d, e", 1, 4,
+            0x54 => ld::<D, H>: "ld d, h", 1, 4,
+            0x55 => ld::<D, L>: "ld d, l", 1, 4,
+            0x56 => ld::<D, MemHL>: "ld d, [hl]", 1, 8,
+            0x57 => ld::<D, A>: "ld d, a", 1, 4,
+            0x16 => ld::<D, D8>: "ld d, n8", 2, 8,
+
+            0x58 => ld::<E, B>: "ld e, b", 1, 4,
+            0x59 => ld::<E, C>: "ld e, c", 1, 4,
+            0x5A => ld::<E, D>: "ld e, d", 1, 4,
+            0x5B => ld::<E, E>: "ld e, e", 1, 4,
+            0x5C => ld::<E, H>: "ld e, h", 1, 4,
+            0x5D => ld::<E, L>: "ld e, l", 1, 4,
+            0x5E => ld::<E, MemHL>: "ld e, [hl]", 1, 8,
+            0x5F => ld::<E, A>: "ld e, a", 1, 4,
+            0x1E => ld::<E, D8>: "ld e, n8", 2, 8,
+
+            0x60 => ld::<H, B>: "ld h, b", 1, 4,
+            0x61 => ld::<H, C>: "ld h, c", 1, 4,
+            0x62 => ld::<H, D>: "ld h, d", 1, 4,
+            0x63 => ld::<H, E>: "ld h, e", 1, 4,
+            0x64 => ld::<H, H>: "ld h, h", 1, 4,
+            0x65 => ld::<H, L>: "ld h, l", 1, 4,
+            0x66 => ld::<H, MemHL>: "ld h, [hl]", 1, 8,
+            0x67 => ld::<H, A>: "ld h, a", 1, 4,
+            0x26 => ld::<H, D8>: "ld h, n8", 2, 8,
+
+            0x68 => ld::<L, B>: "ld l, b", 1, 4,
+            0x69 => ld::<L, C>: "ld l, c", 1, 4,
+            0x6A => ld::<L, D>: "ld l, d", 1, 4,
+            0x6B => ld::<L, E>: "ld l, e", 1, 4,
+            0x6C => ld::<L, H>: "ld l, h", 1, 4,
+            0x6D => ld::<L, L>: "ld l, l", 1, 4,
+            0x6E => ld::<L, MemHL>: "ld l, [hl]", 1, 8,
+            0x6F => ld::<L, A>: "ld l, a", 1, 4,
+            0x2E => ld::<L, D8>: "ld l, n8", 2, 8,
+
+            0x70 => ld::<MemHL, B>: "ld [hl], b", 1, 8,
+            0x71 => ld::<MemHL, C>: "ld [hl], c", 1, 8,
+            0x72 => ld::<MemHL, D>: "ld [hl], d", 1, 8,
+            0x73 => ld::<MemHL, E>: "ld [hl], e", 1, 8,
+            0x74 => ld::<MemHL, H>: "ld [hl], h", 1, 8,
+            0x75 => ld::<MemHL, L>: "ld [hl], l", 1, 8,
+            // LD (HL),(HL) => N/A
+            0x77 => ld::<MemHL, A>: "ld [hl], a", 1, 8,
+            0x36 => ld::<MemHL, D8>: "ld [hl], n8", 2, 12,
+
+            0x78 => ld::<A, B>: "ld a, b", 1, 4,
+            0x79 => ld::<A, C>: "ld a, c", 1, 4,
+            0x7A => ld::<A, D>: "ld a, d", 1, 4,
+            0x7B => ld::<A, E>: "ld a, e", 1, 4,
+            0x7C => ld::<A, H>: "ld a, h", 1, 4,
+            0x7D => ld::<A, L>: "ld a, l", 1, 4,
+            0x7E => ld::<A, MemHL>: "ld a, [hl]", 1, 8,
+            0x7F => ld::<A, A>: "ld a, a", 1, 4,
+            0x3E => ld::<A, D8>: "ld a, n8", 2, 8,
+
+            // LD (xx), A
+            0x02 => ld::<MemBC, A>: "ld [bc], a", 1, 8,
+            0x12 => ld::<MemDE, A>: "ld [de], a", 1, 8,
+            0x22 => ld::<MemHLInc, A>: "ld [hl+], a", 1, 8,
+            0x32 => ld::<MemHLDec, A>: "ld [hl-], a", 1, 8,
+            0xE0 => ld::<MemD8, A>: "ldh [a8], a", 2, 12,
+            0xE2 => ld::<MemC, A>: "ldh [c], a", 1, 8,
+            0xEA => ld::<MemD16, A>: "ld [a16], a", 3, 16,
+
+            // LD A, (xx)
+            0x0A => ld::<A, MemBC>: "ld a, [bc]", 1, 8,
+            0x1A => ld::<A, MemDE>: "ld a, [de]", 1, 8,
+            0x2A => ld::<A, MemHLInc>: "ld a, [hl+]", 1, 8,
+            0x3A => ld::<A, MemHLDec>: "ld a, [hl-]", 1, 8,
+            0xF0 => ld::<A, MemD8>: "ldh a, [a8]", 2, 12,
+            0xF2 => ld::<A, MemC>: "ldh a, [c]", 1, 8,
+            0xFA => ld::<A, MemD16>: "ld a, [a16]", 3, 16,
+        }
+    };
+}
+pub(crate) use opcodes;
+
+// Function running each opcode of a table
+macro_rules! exec_match {
+    (($op_code:expr) $($op:literal => $f:ident $(::<$($g:tt),*>)?:
+        $text:literal, $len:literal, $cycles:literal $(/ $taken:literal)?,)*) => {
+        match $op_code {
+            $($op => $f $(::<$($g),*>)?,)*
+        }
+    };
+}
+use exec_match;
+
 fn exec_next<M: Memory>(cpu: &mut Cpu, mmu: &mut M) -> u8 {
     let pc = cpu.regs.pc();
     let op_code = mmu.read(pc);
 
     // println!("decode: {:#04x} addr:{:#06x}", op_code, pc);
 
-    cpu.regs.set_pc(pc.wrapping_add(1));
-    (match op_code {
-        0x00 => nop,
-        0x10 => nop,                // TODO: STOP
-        0xF3 => disable_interrupts, // TODO: DI
-        0xFB => enable_interrupts,  // TODO: EI
-        0x76 => halt,               // TODO: HALT
-        0xCB => cb::exec_next,
-
-        // Do not exist
-        0xD3 => nop,
-        0xDB => nop,
-        0xDD => nop,
-        0xE3 => nop,
-        0xE4 => nop,
-        0xEB => nop,
-        0xEC => nop,
-        0xED => nop,
-        0xF4 => nop,
-        0xFC => nop,
-        0xFD => nop,
-
-        // INC
-        0x04 => inc::<B>,
-        0x0C => inc::<C>,
-        0x14 => inc::<D>,
-        0x1C => inc::<E>,
-        0x24 => inc::<H>,
-        0x2C => inc::<L>,
-        0x34 => inc::<MemHL>,
-        0x3C => inc::<A>,
-
-        // INC 16-bit
-        0x03 => inc16::<BC>,
-        0x13 => inc16::<DE>,
-        0x23 => inc16::<HL>,
-        0x33 => inc16::<SP>,
-
-        // DEC
-        0x05 => dec::<B>,
-        0x0D => dec::<C>,
-        0x15 => dec::<D>,
-        0x1D => dec::<E>,
-        0x25 => dec::<H>,
-        0x2D => dec::<L>,
-        0x35 => dec::<MemHL>,
-        0x3D => dec::<A>,
-
-        // DEC 16-bit
-        0x0B => dec16::<BC>,
-        0x1B => dec16::<DE>,
-        0x2B => dec16::<HL>,
-        0x3B => dec16::<SP>,
-
-        // ADD 16-bit
-        0x09 => add16::<HL, BC>,
-        0x19 => add16::<HL, DE>,
-        0x29 => add16::<HL, HL>,
-        0x39 => add16::<HL, SP>,
-
-        0x07 => rlca,
-        0x17 => rla,
-        0x0F => rrca,
-        0x1F => rra,
-        0x2f => cpl,
-        0x37 => scf,
-        0x3F => ccf,
-        0x27 => daa,
-
-        // JR
-        0x18 => jr::<D8>,
-        0x28 => jr_flag::<ZERO, D8>,
-        0x38 => jr_flag::<CARRY, D8>,
-        0x20 => jr_n_flag::<ZERO, D8>,
-        0x30 => jr_n_flag::<CARRY, D8>,
-
-        // JP
-        0xC3 => jp::<D16>,
-        0xCA => jp_flag::<ZERO, D16>,
-        0xDA => jp_flag::<CARRY, D16>,
-        0xC2 => jp_n_flag::<ZERO, D16>,
-        0xD2 => jp_n_flag::<CARRY, D16>,
-        0xE9 => jp::<HL>,
-
-        // CALL
-        0xCD => call::<D16>,
-        0xCC => call_flag::<ZERO, D16>,
-        0xDC => call_flag::<CARRY, D16>,
-        0xC4 => call_n_flag::<ZERO, D16>,
-        0xD4 => call_n_flag::<CARRY, D16>,
-
-        // RST
-        0xC7 => rst::<0x0000>,
-        0xD7 => rst::<0x0010>,
-        0xE7 => rst::<0x0020>,
-        0xF7 => rst::<0x0030>,
-        0xCF => rst::<0x0008>,
-        0xDF => rst::<0x0018>,
-        0xEF => rst::<0x0028>,
-        0xFF => rst::<0x0038>,
-
-        // RET
-        0xC9 => ret,
-        0xD9 => reti,
-        0xC8 => ret_flag::<ZERO>,
-        0xD8 => ret_flag::<CARRY>,
-        0xC0 => ret_n_flag::<ZERO>,
-        0xD0 => ret_n_flag::<CARRY>,
-
-        // PUSH
-        0xC5 => push::<BC>,
-        0xD5 => push::<DE>,
-        0xE5 => push::<HL>,
-        0xF5 => push::<AF>,
-
-        // POP
-        0xC1 => pop::<BC>,
-        0xD1 => pop::<DE>,
-        0xE1 => pop::<HL>,
-        0xF1 => pop_af,
-
-        // ADD
-        0x80 => add::<B>,
-        0x81 => add::<C>,
-        0x82 => add::<D>,
-        0x83 => add::<E>,
-        0x84 => add::<H>,
-        0x85 => add::<L>,
-        0x86 => add::<MemHL>,
-        0x87 => add::<A>,
-        0xC6 => add::<D8>,
-
-        // ADC
-        0x88 => adc::<B>,
-        0x89 => adc::<C>,
-        0x8A => adc::<D>,
-        0x8B => adc::<E>,
-        0x8C => adc::<H>,
-        0x8D => adc::<L>,
-        0x8E => adc::<MemHL>,
-        0x8F => adc::<A>,
-        0xCE => adc::<D8>,
-
-        // SUB
-        0x90 => sub::<B>,
-        0x91 => sub::<C>,
-        0x92 => sub::<D>,
-        0x93 => sub::<E>,
-        0x94 => sub::<H>,
-        0x95 => sub::<L>,
-        0x96 => sub::<MemHL>,
-        0x97 => sub::<A>,
-        0xD6 => sub::<D8>,
-
-        // SBC
-        0x98 => sbc::<B>,
-        0x99 => sbc::<C>,
-        0x9A => sbc::<D>,
-        0x9B => sbc::<E>,
-        0x9C => sbc::<H>,
-        0x9D => sbc::<L>,
-        0x9E => sbc::<MemHL>,
-        0x9F => sbc::<A>,
-        0xDE => sbc::<D8>,
-
-        // AND
-        0xA0 => and::<B>,
-        0xA1 => and::<C>,
-        0xA2 => and::<D>,
-        0xA3 => and::<E>,
-        0xA4 => and::<H>,
-        0xA5 => and::<L>,
-        0xA6 => and::<MemHL>,
-        0xA7 => and::<A>,
-        0xE6 => and::<D8>,
-
-        // XOR
-        0xA8 => xor::<B>,
-        0xA9 => xor::<C>,
-        0xAA => xor::<D>,
-        0xAB => xor::<E>,
-        0xAC => xor::<H>,
-        0xAD => xor::<L>,
-        0xAE => xor::<MemHL>,
-        0xAF => xor::<A>,
-        0xEE => xor::<D8>,
-
-        // OR
-        0xB0 => or::<B>,
-        0xB1 => or::<C>,
-        0xB2 => or::<D>,
-        0xB3 => or::<E>,
-        0xB4 => or::<H>,
-        0xB5 => or::<L>,
-        0xB6 => or::<MemHL>,
-        0xB7 => or::<A>,
-        0xF6 => or::<D8>,
-
-        // CP
-        0xB8 => cp::<B>,
-        0xB9 => cp::<C>,
-        0xBA => cp::<D>,
-        0xBB => cp::<E>,
-        0xBC => cp::<H>,
-        0xBD => cp::<L>,
-        0xBE => cp::<MemHL>,
-        0xBF => cp::<A>,
-        0xFE => cp::<D8>,
-
-        // LD 16-bit
-        0x01 => ld_16::<BC, D16>,
-        0x11 => ld_16::<DE, D16>,
-        0x21 => ld_16::<HL, D16>,
-        0x31 => ld_16::<SP, D16>,
-        0xF8 => ld_hl_spr8,
-        0xF9 => ld_16::<SP, HL>,
-        0x08 => ld_a16_sp,
-        0xE8 => add_sp_r8,
-
-        // LD 8-bit
-        0x40 => ld::<B, B>,
-        0x41 => ld::<B, C>,
-        0x42 => ld::<B, D>,
-        0x43 => ld::<B, E>,
-        0x44 => ld::<B, H>,
-        0x45 => ld::<B, L>,
-        0x46 => ld::<B, MemHL>,
-        0x47 => ld::<B, A>,
-        0x06 => ld::<B, D8>,
-
-        0x48 => ld::<C, B>,
-        0x49 => ld::<C, C>,
-        0x4A => ld::<C, D>,
-        0x4B => ld::<C, E>,
-        0x4C => ld::<C, H>,
-        0x4D => ld::<C, L>,
-        0x4E => ld::<C, MemHL>,
-        0x4F => ld::<C, A>,
-        0x0E => ld::<C, D8>,
-
-        0x50 => ld::<D, B>,
-        0x51 => ld::<D, C>,
-        0x52 => ld::<D, D>,
-        0x53 => ld::<D, E>,
-        0x54 => ld::<D, H>,
-        0x55 => ld::<D, L>,
-        0x56 => ld::<D, MemHL>,
-        0x57 => ld::<D, A>,
-        0x16 => ld::<D, D8>,
-
-        0x58 => ld::<E, B>,
-        0x59 => ld::<E, C>,
-        0x5A => ld::<E, D>,
-        0x5B => ld::<E, E>,
-        0x5C => ld::<E, H>,
-        0x5D => ld::<E, L>,
-        0x5E => ld::<E, MemHL>,
-        0x5F => ld::<E, A>,
-        0x1E => ld::<E, D8>,
-
-        0x60 => ld::<H, B>,
-        0x61 => ld::<H, C>,
-        0x62 => ld::<H, D>,
-        0x63 => ld::<H, E>,
-        0x64 => ld::<H, H>,
-        0x65 => ld::<H, L>,
-        0x66 => ld::<H, MemHL>,
-        0x67 => ld::<H, A>,
-        0x26 => ld::<H, D8>,
-
-        0x68 => ld::<L, B>,
-        0x69 => ld::<L, C>,
-        0x6A => ld::<L, D>,
-        0x6B => ld::<L, E>,
-        0x6C => ld::<L, H>,
-        0x6D => ld::<L, L>,
-        0x6E => ld::<L, MemHL>,
-        0x6F => ld::<L, A>,
-        0x2E => ld::<L, D8>,
-
-        0x70 => ld::<MemHL, B>,
-        0x71 => ld::<MemHL, C>,
-        0x72 => ld::<MemHL, D>,
-        0x73 => ld::<MemHL, E>,
-        0x74 => ld::<MemHL, H>,
-        0x75 => ld::<MemHL, L>,
-        // LD (HL),(HL) => N/A
-        0x77 => ld::<MemHL, A>,
-        0x36 => ld::<MemHL, D8>,
-
-        0x78 => ld::<A, B>,
-        0x79 => ld::<A, C>,
-        0x7A => ld::<A, D>,
-        0x7B => ld::<A, E>,
-        0x7C => ld::<A, H>,
-        0x7D => ld::<A, L>,
-        0x7E => ld::<A, MemHL>,
-        0x7F => ld::<A, A>,
-        0x3E => ld::<A, D8>,
-
-        // LD (xx), A
-        0x02 => ld::<MemBC, A>,
-        0x12 => ld::<MemDE, A>,
-        0x22 => ld::<MemHLInc, A>,
-        0x32 => ld::<MemHLDec, A>,
-        0xE0 => ld::<MemD8, A>,
-        0xE2 => ld::<MemC, A>,
-        0xEA => ld::<MemD16, A>,
-
-        // LD A, (xx)
-        0x0A => ld::<A, MemBC>,
-        0x1A => ld::<A, MemDE>,
-        0x2A => ld::<A, MemHLInc>,
-        0x3A => ld::<A, MemHLDec>,
-        0xF0 => ld::<A, MemD8>,
-        0xF2 => ld::<A, MemC>,
-        0xFA => ld::<A, MemD16>,
-    })(cpu, mmu)
+    match cpu.halt_bug {
+        true => cpu.halt_bug = false,
+        false => cpu.regs.set_pc(pc.wrapping_add(1)),
+    }
+    (opcodes!(exec_match(op_code)))(cpu, mmu)
 }
 
 fn halt(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
@@ -453,13 +667,31 @@ fn halt(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
     4
 }
 
+// STOP is 2 bytes long, the second one is ignored
+fn stop(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
+    cpu.regs.set_pc(cpu.regs.pc().wrapping_add(1));
+    cpu.stop = true;
+    4
+}
+
 fn enable_interrupts(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
-    cpu.i_master = true;
+    if !cpu.i_master {
+        cpu.ime_pending = true;
+    }
     4
 }
 
 fn disable_interrupts(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
     cpu.i_master = false;
+    cpu.ime_pending = false;
+    4
+}
+
+fn illegal<const OP: u8>(cpu: &mut Cpu, _: &mut impl Memory) -> u8 {
+    cpu.lock_up = Some(LockUp {
+        opcode: OP,
+        pc: cpu.regs.pc().wrapping_sub(1),
+    });
     4
 }
 
@@ -479,6 +711,13 @@ fn ld_16<W: r16::Write, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u
     4 + R::CYCLES_OVERHEAD + W::CYCLES_OVERHEAD
 }
 
+// LD SP,HL takes an extra cycle to copy 16 bits
+fn ld_sp_hl(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
+    cpu.regs.set_sp(cpu.regs.hl());
+    mmu.internal();
+    8
+}
+
 fn and<R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let res = cpu.regs.a() & R::read(cpu, mmu);
     cpu.regs.set_a(res);
@@ -583,6 +822,7 @@ fn jr<R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let r8 = R::read(cpu, mmu) as i8;
     cpu.regs
         .set_pc(cpu.regs.pc().wrapping_add_signed(r8 as i16));
+    mmu.internal();
     12
 }
 
@@ -590,7 +830,7 @@ fn jr_flag<const F: u8, R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8
     match cpu.regs.flag::<F>() {
         true => jr::<R>(cpu, mmu),
         false => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(1));
+            R::read(cpu, mmu);
             8
         }
     }
@@ -599,7 +839,7 @@ fn jr_flag<const F: u8, R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8
 fn jr_n_flag<const F: u8, R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     match cpu.regs.flag::<F>() {
         true => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(1));
+            R::read(cpu, mmu);
             8
         }
         false => jr::<R>(cpu, mmu),
@@ -609,14 +849,21 @@ fn jr_n_flag<const F: u8, R: r8::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) ->
 fn jp<R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let addr = R::read(cpu, mmu);
     cpu.regs.set_pc(addr);
-    4 + R::CYCLES_OVERHEAD
+    mmu.internal();
+    8 + R::CYCLES_OVERHEAD
+}
+
+// JP HL does not wait for the new address
+fn jp_hl(cpu: &mut Cpu, _mmu: &mut impl Memory) -> u8 {
+    cpu.regs.set_pc(cpu.regs.hl());
+    4
 }
 
 fn jp_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     match cpu.regs.flag::<F>() {
         true => jp::<R>(cpu, mmu),
         false => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(2));
+            R::read(cpu, mmu);
             12
         }
     }
@@ -625,7 +872,7 @@ fn jp_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u
 fn jp_n_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     match cpu.regs.flag::<F>() {
         true => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(2));
+            R::read(cpu, mmu);
             12
         }
         false => jp::<R>(cpu, mmu),
@@ -649,7 +896,7 @@ fn call_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) ->
     match cpu.regs.flag::<F>() {
         true => call::<R>(cpu, mmu),
         false => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(2));
+            R::read(cpu, mmu);
             12
         }
     }
@@ -658,7 +905,7 @@ fn call_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) ->
 fn call_n_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     match cpu.regs.flag::<F>() {
         true => {
-            cpu.regs.set_pc(cpu.regs.pc().wrapping_add(2));
+            R::read(cpu, mmu);
             12
         }
         false => call::<R>(cpu, mmu),
@@ -667,10 +914,13 @@ fn call_n_flag<const F: u8, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory)
 
 fn ret(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     pop::<PC>(cpu, mmu);
+    mmu.internal();
     16
 }
 
+// The condition is checked during an internal cycle
 fn ret_flag<const F: u8>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
+    mmu.internal();
     match cpu.regs.flag::<F>() {
         true => ret(cpu, mmu) + 4,
         false => 8,
@@ -678,6 +928,7 @@ fn ret_flag<const F: u8>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
 }
 
 fn ret_n_flag<const F: u8>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
+    mmu.internal();
     match cpu.regs.flag::<F>() {
         true => 8,
         false => ret(cpu, mmu) + 4,
@@ -687,12 +938,15 @@ fn ret_n_flag<const F: u8>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
 fn reti(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     pop::<PC>(cpu, mmu);
     cpu.i_master = true;
+    mmu.internal();
     16
 }
 
+// SP is decremented during an internal cycle before the writes
 fn push<R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let val = R::read(cpu, mmu);
     let sp: u16 = cpu.regs.sp();
+    mmu.internal();
     mmu.write(sp.wrapping_sub(1), (val >> 8) as u8);
     mmu.write(sp.wrapping_sub(2), (val & 0x00FF) as u8);
     cpu.regs.set_sp(sp.wrapping_sub(2));
@@ -721,14 +975,14 @@ fn pop_af(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
 fn inc16<RW: r16::Read + r16::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let val = RW::read(cpu, mmu);
     RW::write(cpu, mmu, val.wrapping_add(1));
-
+    mmu.internal();
     8
 }
 
 fn dec16<RW: r16::Read + r16::Write>(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     let val = RW::read(cpu, mmu);
     RW::write(cpu, mmu, val.wrapping_sub(1));
-
+    mmu.internal();
     8
 }
 
@@ -741,7 +995,7 @@ fn add16<RW: r16::Read + r16::Write, R: r16::Read>(cpu: &mut Cpu, mmu: &mut impl
         .set_flag::<HCARRY>((reg_1 & 0x0FFF) + (reg_2 & 0x0FFF) > 0x0FFF);
     cpu.regs.set_flag::<CARRY>(carry);
     RW::write(cpu, mmu, res);
-
+    mmu.internal();
     8
 }
 
@@ -837,7 +1091,7 @@ fn ld_hl_spr8(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<HCARRY>(hcarry);
     cpu.regs.set_flag::<CARRY>(carry);
     cpu.regs.set_hl(res);
-
+    mmu.internal();
     12
 }
 
@@ -852,7 +1106,8 @@ fn add_sp_r8(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
     cpu.regs.set_flag::<HCARRY>(hcarry);
     cpu.regs.set_flag::<CARRY>(carry);
     cpu.regs.set_sp(res);
-
+    mmu.internal();
+    mmu.internal();
     16
 }
 
@@ -57,14 +57,14 @@ pub struct MemReg16<R16: r16::Read> {
 }
 
 impl<R16: r16::Read> Read for MemReg16<R16> {
-    const CYCLES_OVERHEAD: u8 = R16::CYCLES_OVERHEAD + 8;
+    const CYCLES_OVERHEAD: u8 = R16::CYCLES_OVERHEAD + 4;
     fn read(cpu: &mut Cpu, mmu: &mut impl Memory) -> u8 {
         let addr = R16::read(cpu, mmu);
         mmu.read(addr)
     }
 }
 impl<R16: r16::Read> Write for MemReg16<R16> {
-    const CYCLES_OVERHEAD: u8 = R16::CYCLES_OVERHEAD + 8;
+    const CYCLES_OVERHEAD: u8 = R16::CYCLES_OVERHEAD + 4;
     fn write(cpu: &mut Cpu, mmu: &mut impl Memory, val: u8) {
         let addr = R16::read(cpu, mmu);
         mmu.write(addr, val);
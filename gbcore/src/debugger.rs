@@ -0,0 +1,311 @@
+use crate::{Registers, cartridge::Cartridge};
+use core::fmt;
+
+extern crate alloc;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+}
+
+impl Register {
+    pub fn get(&self, regs: &Registers) -> u16 {
+        match self {
+            Register::A => regs.a() as u16,
+            Register::F => regs.af() & 0x00FF,
+            Register::B => regs.b() as u16,
+            Register::C => regs.c() as u16,
+            Register::D => regs.d() as u16,
+            Register::E => regs.e() as u16,
+            Register::H => regs.h() as u16,
+            Register::L => regs.l() as u16,
+            Register::AF => regs.af(),
+            Register::BC => regs.bc(),
+            Register::DE => regs.de(),
+            Register::HL => regs.hl(),
+            Register::SP => regs.sp(),
+            Register::PC => regs.pc(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// Register value test of a conditional breakpoint, e.g. A == 0x12
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub compare: Compare,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn holds(&self, regs: &Registers) -> bool {
+        let reg = self.register.get(regs);
+        match self.compare {
+            Compare::Eq => reg == self.value,
+            Compare::Ne => reg != self.value,
+            Compare::Lt => reg < self.value,
+            Compare::Le => reg <= self.value,
+            Compare::Gt => reg > self.value,
+            Compare::Ge => reg >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let compare = match self.compare {
+            Compare::Eq => "==",
+            Compare::Ne => "!=",
+            Compare::Lt => "<",
+            Compare::Le => "<=",
+            Compare::Gt => ">",
+            Compare::Ge => ">=",
+        };
+        write!(f, "{:?} {} ${:X}", self.register, compare, self.value)
+    }
+}
+
+// Stop before the instruction at `addr` runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub addr: u16,
+    // ROM bank mapped at `addr`, see Cartridge::rom_bank. Any bank if None.
+    pub bank: Option<usize>,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    pub fn new(addr: u16) -> Self {
+        Self {
+            addr,
+            bank: None,
+            condition: None,
+        }
+    }
+
+    pub fn bank(mut self, bank: usize) -> Self {
+        self.bank = Some(bank);
+        self
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    fn hit(&self, regs: &Registers, cartridge: &impl Cartridge) -> bool {
+        let pc = regs.pc();
+        pc == self.addr
+            && self
+                .bank
+                .is_none_or(|bank| pc >= 0x8000 || cartridge.rom_bank(pc) == bank)
+            && self.condition.is_none_or(|c| c.holds(regs))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bank {
+            Some(bank) => write!(f, "{:02X}:{:04X}", bank, self.addr)?,
+            None => write!(f, "{:04X}", self.addr)?,
+        }
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+// Stop after an instruction accessing `start..=end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub access: Access,
+}
+
+impl Watchpoint {
+    pub fn new(start: u16, end: u16, access: Access) -> Self {
+        Self { start, end, access }
+    }
+
+    fn matches(&self, addr: u16, write: bool) -> bool {
+        let access = match self.access {
+            Access::Read => !write,
+            Access::Write => write,
+            Access::ReadWrite => true,
+        };
+        access && (self.start..=self.end).contains(&addr)
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            Access::Read => "r",
+            Access::Write => "w",
+            Access::ReadWrite => "rw",
+        };
+        match self.start == self.end {
+            true => write!(f, "{:04X} {}", self.start, access),
+            false => write!(f, "{:04X}-{:04X} {}", self.start, self.end, access),
+        }
+    }
+}
+
+// First watched access of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub addr: u16,
+    // Value read or written
+    pub value: u8,
+    pub write: bool,
+}
+
+// Checked on every CPU memory access (see Bus), the accesses made by the
+// peripherals, the debugger or the disassembler are not watched
+#[derive(Default)]
+pub(crate) struct Watchpoints {
+    pub list: Vec<Watchpoint>,
+    pub hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn check(&mut self, addr: u16, value: u8, write: bool) {
+        if self.list.is_empty() || self.hit.is_some() {
+            return;
+        }
+        if self.list.iter().any(|w| w.matches(addr, write)) {
+            self.hit = Some(WatchHit { addr, value, write });
+        }
+    }
+}
+
+// Breakpoints checked by the System between instructions, watchpoints
+// checked by the Bus during them
+#[derive(Default)]
+pub(crate) struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Watchpoints,
+    // IF bits of the interrupts to stop on, once dispatched
+    pub interrupts: u8,
+    // Opcodes to stop on, before they run
+    pub opcodes: BTreeSet<u8>,
+    // PC of the last breakpoint or opcode stop, not checked again by the
+    // next run so that it can resume from there
+    pub resume: Option<u16>,
+}
+
+impl Debugger {
+    pub fn breakpoint(&self, regs: &Registers, cartridge: &impl Cartridge) -> bool {
+        self.breakpoints.iter().any(|b| b.hit(regs, cartridge))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::DynCartridge;
+    use crate::system::{RunOutcome, System};
+    use alloc::vec;
+
+    // 32 KiB ROM only cartridge running `code` from 0x0100, NOPs after it
+    fn system(code: &[u8]) -> System<DynCartridge> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x100 + code.len()].copy_from_slice(code);
+        System::new(DynCartridge::new(rom).unwrap())
+    }
+
+    #[test]
+    fn peripheral_accesses_are_not_watched() {
+        let mut sys = system(&[]);
+        // TAC is read by the timers, P1 written by the joypad
+        sys.add_watchpoint(Watchpoint::new(0xFF07, 0xFF07, Access::Read));
+        sys.add_watchpoint(Watchpoint::new(0xFF00, 0xFF00, Access::Write));
+        sys.set_keys(0xFF);
+        for _ in 0..3 {
+            assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+        }
+    }
+
+    #[test]
+    fn cpu_accesses_are_watched() {
+        // LDH A,(07) ; LD A,$20 ; LDH (00),A
+        let mut sys = system(&[0xF0, 0x07, 0x3E, 0x20, 0xE0, 0x00]);
+        sys.add_watchpoint(Watchpoint::new(0xFF07, 0xFF07, Access::Read));
+        sys.add_watchpoint(Watchpoint::new(0xFF00, 0xFF00, Access::Write));
+        let tac = sys.peek(0xFF07);
+        assert_eq!(
+            sys.run_until_vblank().unwrap(),
+            RunOutcome::Watchpoint(WatchHit {
+                addr: 0xFF07,
+                value: tac,
+                write: false,
+            })
+        );
+        assert_eq!(sys.registers().pc(), 0x0102);
+        assert_eq!(
+            sys.run_until_vblank().unwrap(),
+            RunOutcome::Watchpoint(WatchHit {
+                addr: 0xFF00,
+                value: 0x20,
+                write: true,
+            })
+        );
+    }
+
+    #[test]
+    fn breakpoint_after_a_frame_is_hit() {
+        let mut sys = system(&[]);
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+        let pc = sys.registers().pc();
+        sys.set_breakpoint(pc);
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Breakpoint(pc));
+        // Resumes from the breakpoint
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+    }
+
+    #[test]
+    fn halted_cycles_are_not_instructions() {
+        // HALT with no interrupt enabled, never left
+        let mut sys = system(&[0x76]);
+        sys.set_breakpoint(0x0101);
+        assert_eq!(sys.step_instruction().unwrap(), RunOutcome::Done);
+        assert_eq!(sys.registers().pc(), 0x0101);
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+        assert_eq!(sys.step_instruction().unwrap(), RunOutcome::Frame);
+        assert_eq!(sys.registers().pc(), 0x0101);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    Memory,
+    cpu::{cb_opcodes, opcodes},
+};
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    // a, b, hl, sp...
+    Reg(&'static str),
+    // z, nz, c, nc
+    Cond(&'static str),
+    // [hl], [hl+], [c]...
+    RegMem(&'static str),
+    Imm8(u8),
+    Imm16(u16),
+    // [n16]
+    Mem(u16),
+    // Jump, call and RST destinations
+    Addr(u16),
+    // sp+e8
+    SpOffset(i8),
+    // e8 added to SP
+    Offset(i8),
+    Bit(u8),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Reg(r) | Operand::Cond(r) => write!(f, "{}", r),
+            Operand::RegMem(r) => write!(f, "[{}]", r),
+            Operand::Imm8(v) => write!(f, "${:02X}", v),
+            Operand::Imm16(v) | Operand::Addr(v) => write!(f, "${:04X}", v),
+            Operand::Mem(addr) => write!(f, "[${:04X}]", addr),
+            Operand::SpOffset(e) => write!(f, "sp{:+}", e),
+            Operand::Offset(e) => write!(f, "{}", e),
+            Operand::Bit(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: u16,
+    pub mnemonic: &'static str,
+    operands: [Option<Operand>; 2],
+    bytes: [u8; 3],
+    len: u8,
+    // T-cycles, with the condition not met for conditional instructions
+    pub cycles: u8,
+    // T-cycles with the condition met (branch taken)
+    pub cycles_taken: Option<u8>,
+    // Destination of jumps, calls and RSTs
+    pub target: Option<u16>,
+}
+
+impl Instruction {
+    pub fn operands(&self) -> impl Iterator<Item = &Operand> {
+        self.operands.iter().flatten()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    pub fn len(&self) -> u16 {
+        self.len as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Address of the next instruction in memory
+    pub fn next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.len as u16)
+    }
+}
+
+// RGBDS syntax
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic)?;
+        for (i, operand) in self.operands().enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            write!(f, "{}{}", sep, operand)?;
+        }
+        Ok(())
+    }
+}
+
+// Text, length and cycles of each opcode, as written in the CPU opcode tables
+macro_rules! decode_match {
+    (($op_code:expr) $($op:literal => $f:ident $(::<$($g:tt),*>)?:
+        $text:literal, $len:literal, $cycles:literal $(/ $taken:literal)?,)*) => {
+        match $op_code {
+            $($op => Opcode {
+                text: $text,
+                len: $len,
+                cycles: $cycles,
+                cycles_taken: None $(.or(Some($taken)))?,
+            },)*
+        }
+    };
+}
+
+struct Opcode {
+    text: &'static str,
+    len: u8,
+    cycles: u8,
+    cycles_taken: Option<u8>,
+}
+
+// Decode the instruction at `addr`
+pub fn decode(mem: &mut impl Memory, addr: u16) -> Instruction {
+    let mut bytes = [
+        mem.read(addr),
+        mem.read(addr.wrapping_add(1)),
+        mem.read(addr.wrapping_add(2)),
+    ];
+    let opcode = match bytes[0] {
+        0xCB => cb_opcodes!(decode_match(bytes[1])),
+        op => opcodes!(decode_match(op)),
+    };
+
+    let (mnemonic, args) = opcode.text.split_once(' ').unwrap_or((opcode.text, ""));
+    let branch = matches!(mnemonic, "jr" | "jp" | "call" | "ret");
+    let mut operands = [None, None];
+    let mut target = None;
+    for (slot, arg) in operands
+        .iter_mut()
+        .zip(args.split(", ").filter(|arg| !arg.is_empty()))
+    {
+        let operand = operand(arg, branch, addr, bytes);
+        if let Operand::Addr(addr) = operand {
+            target = Some(addr);
+        }
+        *slot = Some(operand);
+    }
+
+    bytes[opcode.len as usize..].fill(0);
+    Instruction {
+        addr,
+        mnemonic,
+        operands,
+        bytes,
+        len: opcode.len,
+        cycles: opcode.cycles,
+        cycles_taken: opcode.cycles_taken,
+        target,
+    }
+}
+
+// Operand from its text in the opcode tables
+fn operand(text: &'static str, branch: bool, addr: u16, bytes: [u8; 3]) -> Operand {
+    let d8 = bytes[1];
+    let d16 = u16::from_le_bytes([bytes[1], bytes[2]]);
+    match text {
+        "n8" => Operand::Imm8(d8),
+        "n16" => Operand::Imm16(d16),
+        "a16" => Operand::Addr(d16),
+        "r8" => Operand::Addr(addr.wrapping_add(2).wrapping_add(d8 as i8 as u16)),
+        "e8" => Operand::Offset(d8 as i8),
+        "sp+e8" => Operand::SpOffset(d8 as i8),
+        "[a8]" => Operand::Mem(0xFF00 | d8 as u16),
+        "[a16]" => Operand::Mem(d16),
+        "z" | "nz" | "nc" => Operand::Cond(text),
+        // C is also a register
+        "c" if branch => Operand::Cond(text),
+        _ if text.starts_with('[') => Operand::RegMem(&text[1..text.len() - 1]),
+        // RST vectors and illegal opcodes
+        _ if text.starts_with('$') => match u16::from_str_radix(&text[1..], 16) {
+            Ok(value) if text.len() == 3 => Operand::Imm8(value as u8),
+            Ok(value) => Operand::Addr(value),
+            Err(_) => unreachable!("invalid operand {}", text),
+        },
+        _ => match text.parse() {
+            Ok(bit) => Operand::Bit(bit),
+            Err(_) => Operand::Reg(text),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::Cpu;
+    extern crate alloc;
+    use alloc::{format, string::String, string::ToString, vec, vec::Vec};
+
+    const REGS: [&str; 8] = ["b", "c", "d", "e", "h", "l", "[hl]", "a"];
+
+    // 00-3F and C0-FF, with the operand bytes $34 $12 at 0x0100
+    const OTHERS: [[&str; 4]; 32] = [
+        ["nop", "ld bc, $1234", "ld [bc], a", "inc bc"],
+        ["inc b", "dec b", "ld b, $34", "rlca"],
+        ["ld [$1234], sp", "add hl, bc", "ld a, [bc]", "dec bc"],
+        ["inc c", "dec c", "ld c, $34", "rrca"],
+        ["stop", "ld de, $1234", "ld [de], a", "inc de"],
+        ["inc d", "dec d", "ld d, $34", "rla"],
+        ["jr $0136", "add hl, de", "ld a, [de]", "dec de"],
+        ["inc e", "dec e", "ld e, $34", "rra"],
+        ["jr nz, $0136", "ld hl, $1234", "ld [hl+], a", "inc hl"],
+        ["inc h", "dec h", "ld h, $34", "daa"],
+        ["jr z, $0136", "add hl, hl", "ld a, [hl+]", "dec hl"],
+        ["inc l", "dec l", "ld l, $34", "cpl"],
+        ["jr nc, $0136", "ld sp, $1234", "ld [hl-], a", "inc sp"],
+        ["inc [hl]", "dec [hl]", "ld [hl], $34", "scf"],
+        ["jr c, $0136", "add hl, sp", "ld a, [hl-]", "dec sp"],
+        ["inc a", "dec a", "ld a, $34", "ccf"],
+        ["ret nz", "pop bc", "jp nz, $1234", "jp $1234"],
+        ["call nz, $1234", "push bc", "add a, $34", "rst $0000"],
+        ["ret z", "ret", "jp z, $1234", ""],
+        ["call z, $1234", "call $1234", "adc a, $34", "rst $0008"],
+        ["ret nc", "pop de", "jp nc, $1234", "db $D3"],
+        ["call nc, $1234", "push de", "sub a, $34", "rst $0010"],
+        ["ret c", "reti", "jp c, $1234", "db $DB"],
+        ["call c, $1234", "db $DD", "sbc a, $34", "rst $0018"],
+        ["ldh [$FF34], a", "pop hl", "ldh [c], a", "db $E3"],
+        ["db $E4", "push hl", "and a, $34", "rst $0020"],
+        ["add sp, 52", "jp hl", "ld [$1234], a", "db $EB"],
+        ["db $EC", "db $ED", "xor a, $34", "rst $0028"],
+        ["ldh a, [$FF34]", "pop af", "ldh a, [c]", "di"],
+        ["db $F4", "push af", "or a, $34", "rst $0030"],
+        ["ld hl, sp+52", "ld sp, hl", "ld a, [$1234]", "ei"],
+        ["db $FC", "db $FD", "cp a, $34", "rst $0038"],
+    ];
+
+    fn expected_text(cb: bool, op: u8) -> String {
+        const ALU: [&str; 8] = ["add", "adc", "sub", "sbc", "and", "xor", "or", "cp"];
+        const SHIFTS: [&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+        let (x, y, z) = ((op >> 6) as usize, (op >> 3) as usize & 7, op as usize & 7);
+        let other = |idx: usize| OTHERS[idx / 4][idx % 4].to_string();
+        match (cb, x) {
+            (true, 0) => format!("{} {}", SHIFTS[y], REGS[z]),
+            (true, _) => format!("{} {}, {}", ["bit", "res", "set"][x - 1], y, REGS[z]),
+            (false, 1) if op == 0x76 => "halt".to_string(),
+            (false, 1) => format!("ld {}, {}", REGS[y], REGS[z]),
+            (false, 2) => format!("{} a, {}", ALU[y], REGS[z]),
+            (false, 0) => other(op as usize),
+            (false, _) => other(op as usize - 0x80),
+        }
+    }
+
+    struct FlatMemory(Vec<u8>);
+
+    impl Memory for FlatMemory {
+        fn read(&mut self, addr: u16) -> u8 {
+            self.0[addr as usize]
+        }
+
+        fn write(&mut self, addr: u16, value: u8) {
+            self.0[addr as usize] = value;
+        }
+    }
+
+    fn memory(bytes: &[u8]) -> FlatMemory {
+        let mut mem = FlatMemory(vec![0; 0x10000]);
+        mem.0[0x0100..0x0100 + bytes.len()].copy_from_slice(bytes);
+        mem
+    }
+
+    // Run the instruction at 0x0100, returns the cycles and the new PC
+    fn run(bytes: &[u8], flags: u8) -> (u8, u16) {
+        let mut cpu = Cpu::default();
+        let regs = cpu.regs_mut();
+        regs.set_pc(0x0100);
+        regs.set_sp(0xD000);
+        regs.set_hl(0xC000);
+        regs.set_af(flags as u16);
+        let cycles = cpu.step(&mut memory(bytes));
+        (cycles, cpu.regs().pc())
+    }
+
+    #[test]
+    fn opcode_tables() {
+        for cb in [false, true] {
+            for op in 0..=0xFFu8 {
+                if !cb && op == 0xCB {
+                    continue;
+                }
+                let bytes = match cb {
+                    true => [0xCB, op, 0x12],
+                    false => [op, 0x34, 0x12],
+                };
+                let instr = decode(&mut memory(&bytes), 0x0100);
+                let name = format!("{:02X} {:02X}", bytes[0], bytes[1]);
+                assert_eq!(instr.to_string(), expected_text(cb, op), "{}", name);
+
+                let (not_taken, pc_not_taken) = run(&bytes, 0x00);
+                let (taken, pc_taken) = run(&bytes, 0xF0);
+                let cycles = (not_taken.min(taken), not_taken.max(taken));
+                let expected = (instr.cycles, instr.cycles_taken.unwrap_or(instr.cycles));
+                assert_eq!(cycles, expected, "{} cycles", name);
+
+                // Unless it jumps (RET pops 0x0000, JP HL goes to 0xC000), the
+                // CPU moves to the next instruction
+                for pc in [pc_not_taken, pc_taken] {
+                    let jumped = Some(pc) == instr.target || pc == 0x0000 || pc == 0xC000;
+                    assert!(pc == instr.next_addr() || jumped, "{} length", name);
+                }
+            }
+        }
+    }
+}
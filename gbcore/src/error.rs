@@ -1,3 +1,9 @@
+use crate::{
+    LockUp,
+    cartridge::{CartridgeType, RamType},
+};
+use core::fmt;
+
 #[derive(Debug)]
 pub enum CoreError {
     UnknownRamType(u8),
@@ -6,5 +12,55 @@ pub enum CoreError {
     UnknownOpCode(u8),
     UnknownOpCodeCB(u8),
     UnknownCPUState(u16, u16),
+    // Illegal opcode run, the CPU only gets out of it by a reset
+    CpuLockUp(LockUp),
     UnknownGPULY(u8),
+    InvalidBootRomSize(usize),
+    TruncatedHeader(usize),
+    UnsupportedCartridge(CartridgeType),
+    UnsupportedRamType(RamType),
+    // Size from the header, size of the file
+    RomSizeMismatch(usize, usize),
 }
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreError::UnknownRamType(v) => write!(f, "unknown RAM size {:#04x} in header", v),
+            CoreError::UnknownRomType(v) => write!(f, "unknown ROM size {:#04x} in header", v),
+            CoreError::UnknownCartridgeType(v) => {
+                write!(f, "unknown cartridge type {:#04x} in header", v)
+            }
+            CoreError::UnknownOpCode(op) => write!(f, "unknown opcode {:#04x}", op),
+            CoreError::UnknownOpCodeCB(op) => write!(f, "unknown opcode 0xCB {:#04x}", op),
+            CoreError::UnknownCPUState(pc, sp) => {
+                write!(f, "unknown CPU state (PC={:#06x}, SP={:#06x})", pc, sp)
+            }
+            CoreError::CpuLockUp(lock_up) => write!(
+                f,
+                "CPU locked up by illegal opcode {:#04x} at {:#06x}",
+                lock_up.opcode, lock_up.pc
+            ),
+            CoreError::UnknownGPULY(ly) => write!(f, "unknown LY value {}", ly),
+            CoreError::InvalidBootRomSize(len) => write!(
+                f,
+                "invalid boot ROM size {} bytes (expected 256 or 2304)",
+                len
+            ),
+            CoreError::TruncatedHeader(len) => write!(
+                f,
+                "ROM too small to contain a header ({} bytes, expected at least 336)",
+                len
+            ),
+            CoreError::UnsupportedCartridge(t) => write!(f, "unsupported cartridge type {:?}", t),
+            CoreError::UnsupportedRamType(t) => write!(f, "unsupported RAM size {:?}", t),
+            CoreError::RomSizeMismatch(expected, len) => write!(
+                f,
+                "ROM size mismatch: header says {} bytes, file has {} bytes",
+                expected, len
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CoreError {}
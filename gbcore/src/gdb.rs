@@ -0,0 +1,572 @@
+// GDB remote serial protocol, without the transport: the frontend feeds the
+// bytes received from GDB and sends back the ones written to `out`.
+//
+// Registers follow GDB's Z80 description (gdb-multiarch, `set architecture
+// z80`): AF BC DE HL SP PC, then the Z80 only ones which read as 0.
+use crate::{
+    CoreError, RunOutcome, System,
+    cartridge::Cartridge,
+    debugger::{Access, Watchpoint},
+};
+use core::fmt::Write;
+
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>z80</architecture>
+  <feature name="org.gnu.gdb.z80.cpu">
+    <reg name="af" bitsize="16" type="int"/>
+    <reg name="bc" bitsize="16" type="int"/>
+    <reg name="de" bitsize="16" type="data_ptr"/>
+    <reg name="hl" bitsize="16" type="data_ptr"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="ix" bitsize="16" type="data_ptr"/>
+    <reg name="iy" bitsize="16" type="data_ptr"/>
+    <reg name="af'" bitsize="16" type="int"/>
+    <reg name="bc'" bitsize="16" type="int"/>
+    <reg name="de'" bitsize="16" type="data_ptr"/>
+    <reg name="hl'" bitsize="16" type="data_ptr"/>
+    <reg name="ir" bitsize="16" type="int"/>
+  </feature>
+</target>"#;
+
+const REGISTERS: usize = 13;
+
+// Signals reported in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+const SIGABRT: u8 = 6;
+
+// Packet that can not be parsed, answered with an error
+struct Malformed;
+
+enum Input {
+    Idle,
+    Packet(Vec<u8>),
+    // Packet and first checksum digit
+    Checksum(Vec<u8>, Option<u8>),
+}
+
+pub struct GdbStub {
+    input: Input,
+    // Last packet sent, resent when GDB asks for it
+    last: Vec<u8>,
+    last_stop: String,
+    running: bool,
+    detached: bool,
+}
+
+impl Default for GdbStub {
+    fn default() -> Self {
+        Self {
+            input: Input::Idle,
+            last: Vec::new(),
+            last_stop: stop_signal(SIGTRAP),
+            running: false,
+            detached: false,
+        }
+    }
+}
+
+impl GdbStub {
+    // GDB asked to continue: run the system (see System::run_until_vblank)
+    // and report the outcome with `stopped`
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    // GDB detached or killed the session, the system can run freely
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    // Bytes received from GDB
+    pub fn receive<C: Cartridge>(&mut self, sys: &mut System<C>, data: &[u8], out: &mut Vec<u8>) {
+        for &byte in data {
+            self.input = match core::mem::replace(&mut self.input, Input::Idle) {
+                Input::Idle => match byte {
+                    b'$' => Input::Packet(Vec::new()),
+                    b'-' => {
+                        out.extend_from_slice(&self.last);
+                        Input::Idle
+                    }
+                    // Ctrl-C
+                    0x03 if self.running => {
+                        self.stop(stop_signal(SIGINT), out);
+                        Input::Idle
+                    }
+                    // Acks
+                    _ => Input::Idle,
+                },
+                Input::Packet(mut packet) => match byte {
+                    b'#' => Input::Checksum(packet, None),
+                    _ => {
+                        packet.push(byte);
+                        Input::Packet(packet)
+                    }
+                },
+                Input::Checksum(packet, None) => Input::Checksum(packet, Some(byte)),
+                Input::Checksum(packet, Some(high)) => {
+                    let sum = packet.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                    match hex_digit(high).zip(hex_digit(byte)) {
+                        Some((high, low)) if high << 4 | low == sum => {
+                            out.push(b'+');
+                            let reply = match self.handle(sys, &unescape(&packet)) {
+                                Ok(reply) => reply,
+                                Err(Malformed) => Some("E01".into()),
+                            };
+                            if let Some(reply) = reply {
+                                self.send(&reply, out);
+                            }
+                        }
+                        _ => out.push(b'-'),
+                    }
+                    Input::Idle
+                }
+            };
+        }
+    }
+
+    // Outcome of a run started by a continue, frames and completed runs do
+    // not stop it. Errors are reported as an abort.
+    pub fn stopped(&mut self, outcome: Result<RunOutcome, CoreError>, out: &mut Vec<u8>) {
+        let reply = match outcome {
+            _ if !self.running => return,
+            Ok(RunOutcome::Done | RunOutcome::Frame) => return,
+            Ok(outcome) => stop_reply(outcome),
+            Err(_) => stop_signal(SIGABRT),
+        };
+        self.stop(reply, out);
+    }
+
+    fn stop(&mut self, reply: String, out: &mut Vec<u8>) {
+        self.running = false;
+        self.send(&reply, out);
+        self.last_stop = reply;
+    }
+
+    fn send(&mut self, reply: &str, out: &mut Vec<u8>) {
+        let mut packet = Vec::with_capacity(reply.len() + 4);
+        packet.push(b'$');
+        for &byte in reply.as_bytes() {
+            match byte {
+                b'$' | b'#' | b'}' | b'*' => packet.extend_from_slice(&[b'}', byte ^ 0x20]),
+                _ => packet.push(byte),
+            }
+        }
+        let sum = packet[1..].iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        let _ = write!(Bytes(&mut packet), "#{:02x}", sum);
+        out.extend_from_slice(&packet);
+        self.last = packet;
+    }
+
+    // Reply to a packet, None when there is nothing to send (yet)
+    fn handle<C: Cartridge>(
+        &mut self,
+        sys: &mut System<C>,
+        packet: &[u8],
+    ) -> Result<Option<String>, Malformed> {
+        let packet = core::str::from_utf8(packet).map_err(|_| Malformed)?;
+        let (command, args) = packet.split_at(packet.len().min(1));
+        let reply = match command {
+            "?" => self.last_stop.clone(),
+            "g" => {
+                let mut reply = String::new();
+                for n in 0..REGISTERS {
+                    push_u16(&mut reply, register(sys, n));
+                }
+                reply
+            }
+            "G" => {
+                let values = parse_hex_bytes(args)?;
+                for (n, value) in values.chunks_exact(2).enumerate() {
+                    set_register(sys, n, u16::from_le_bytes([value[0], value[1]]));
+                }
+                "OK".into()
+            }
+            "p" => {
+                let n = parse_hex(args)? as usize;
+                let mut reply = String::new();
+                push_u16(&mut reply, register(sys, n));
+                reply
+            }
+            "P" => {
+                let (n, value) = args.split_once('=').ok_or(Malformed)?;
+                let value = parse_hex_bytes(value)?;
+                let low = *value.first().ok_or(Malformed)?;
+                let value = u16::from_le_bytes([low, *value.get(1).unwrap_or(&0)]);
+                set_register(sys, parse_hex(n)? as usize, value);
+                "OK".into()
+            }
+            "m" => {
+                let (addr, len) = parse_range(args)?;
+                let mut reply = String::new();
+                for i in 0..len {
+                    let _ = write!(reply, "{:02x}", sys.peek(addr.wrapping_add(i)));
+                }
+                reply
+            }
+            "M" => {
+                let (range, data) = args.split_once(':').ok_or(Malformed)?;
+                let (addr, _) = parse_range(range)?;
+                for (i, value) in parse_hex_bytes(data)?.into_iter().enumerate() {
+                    sys.poke(addr.wrapping_add(i as u16), value);
+                }
+                "OK".into()
+            }
+            "c" => {
+                if !args.is_empty() {
+                    sys.registers_mut().set_pc(parse_hex(args)? as u16);
+                }
+                self.running = true;
+                return Ok(None);
+            }
+            "s" => {
+                if !args.is_empty() {
+                    sys.registers_mut().set_pc(parse_hex(args)? as u16);
+                }
+                let reply = match sys.step_instruction() {
+                    Ok(outcome) => stop_reply(outcome),
+                    Err(_) => stop_signal(SIGABRT),
+                };
+                self.last_stop = reply.clone();
+                reply
+            }
+            "Z" | "z" => self.breakpoint(sys, command == "Z", args)?,
+            "H" => "OK".into(),
+            "D" => {
+                self.detached = true;
+                "OK".into()
+            }
+            "k" => {
+                self.detached = true;
+                return Ok(None);
+            }
+            "q" => query(args)?,
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    // Z/z<type>,<addr>,<kind>: 0-1 breakpoint, 2 write, 3 read, 4 access
+    fn breakpoint<C: Cartridge>(
+        &mut self,
+        sys: &mut System<C>,
+        insert: bool,
+        args: &str,
+    ) -> Result<String, Malformed> {
+        let (kind, range) = args.split_once(',').ok_or(Malformed)?;
+        let (addr, len) = parse_range(range)?;
+        let access = match kind {
+            "0" | "1" => {
+                match insert {
+                    true => sys.set_breakpoint(addr),
+                    false => sys.clear_breakpoint(addr),
+                }
+                return Ok("OK".into());
+            }
+            "2" => Access::Write,
+            "3" => Access::Read,
+            "4" => Access::ReadWrite,
+            _ => return Ok(String::new()),
+        };
+        let end = addr.wrapping_add(len.max(1) - 1);
+        let watchpoint = Watchpoint::new(addr, end, access);
+        match insert {
+            true => sys.add_watchpoint(watchpoint),
+            false => {
+                let index = sys.watchpoints().iter().position(|w| *w == watchpoint);
+                sys.remove_watchpoint(index.ok_or(Malformed)?);
+            }
+        }
+        Ok("OK".into())
+    }
+}
+
+fn query(args: &str) -> Result<String, Malformed> {
+    if args.starts_with("Supported") {
+        return Ok("PacketSize=1000;qXfer:features:read+".into());
+    }
+    if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        let (offset, len) = range.split_once(',').ok_or(Malformed)?;
+        let offset = (parse_hex(offset)? as usize).min(TARGET_XML.len());
+        let end = (offset + parse_hex(len)? as usize).min(TARGET_XML.len());
+        let more = if end < TARGET_XML.len() { "m" } else { "l" };
+        return Ok([more, &TARGET_XML[offset..end]].concat());
+    }
+    Ok(match args {
+        "Attached" => "1",
+        "C" => "QC1",
+        "fThreadInfo" => "m1",
+        "sThreadInfo" => "l",
+        _ => "",
+    }
+    .into())
+}
+
+fn stop_reply(outcome: RunOutcome) -> String {
+    match outcome {
+        RunOutcome::Watchpoint(hit) => {
+            let kind = if hit.write { "watch" } else { "rwatch" };
+            let mut reply = String::new();
+            let _ = write!(reply, "T{:02x}{}:{:x};", SIGTRAP, kind, hit.addr);
+            reply
+        }
+        RunOutcome::LockUp(_) => stop_signal(SIGILL),
+        _ => stop_signal(SIGTRAP),
+    }
+}
+
+fn stop_signal(signal: u8) -> String {
+    let mut reply = String::new();
+    let _ = write!(reply, "S{:02x}", signal);
+    reply
+}
+
+fn register<C: Cartridge>(sys: &System<C>, n: usize) -> u16 {
+    let regs = sys.registers();
+    match n {
+        0 => regs.af(),
+        1 => regs.bc(),
+        2 => regs.de(),
+        3 => regs.hl(),
+        4 => regs.sp(),
+        5 => regs.pc(),
+        _ => 0,
+    }
+}
+
+fn set_register<C: Cartridge>(sys: &mut System<C>, n: usize, value: u16) {
+    let regs = sys.registers_mut();
+    match n {
+        0 => regs.set_af(value),
+        1 => regs.set_bc(value),
+        2 => regs.set_de(value),
+        3 => regs.set_hl(value),
+        4 => regs.set_sp(value),
+        5 => regs.set_pc(value),
+        _ => {}
+    }
+}
+
+// Little endian, as GDB expects for the target
+fn push_u16(reply: &mut String, value: u16) {
+    let _ = write!(reply, "{:02x}{:02x}", value & 0xFF, value >> 8);
+}
+
+// <addr>,<len>
+fn parse_range(args: &str) -> Result<(u16, u16), Malformed> {
+    let (addr, len) = args.split_once(',').ok_or(Malformed)?;
+    Ok((parse_hex(addr)? as u16, parse_hex(len)? as u16))
+}
+
+fn parse_hex(value: &str) -> Result<u32, Malformed> {
+    u32::from_str_radix(value, 16).map_err(|_| Malformed)
+}
+
+fn parse_hex_bytes(value: &str) -> Result<Vec<u8>, Malformed> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => hex_digit(*high)
+                .zip(hex_digit(*low))
+                .map(|(high, low)| high << 4 | low)
+                .ok_or(Malformed),
+            _ => Err(Malformed),
+        })
+        .collect()
+}
+
+// `}` escapes the next byte, xored with 0x20
+fn unescape(packet: &[u8]) -> Vec<u8> {
+    let mut bytes = packet.iter();
+    let mut data = Vec::with_capacity(packet.len());
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'}' => data.extend(bytes.next().map(|b| b ^ 0x20)),
+            _ => data.push(byte),
+        }
+    }
+    data
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|d| d as u8)
+}
+
+// fmt::Write into a byte buffer
+struct Bytes<'a>(&'a mut Vec<u8>);
+
+impl Write for Bytes<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::DynCartridge;
+    use alloc::vec;
+
+    fn system() -> System<DynCartridge> {
+        System::new(DynCartridge::new(vec![0; 0x8000]).unwrap())
+    }
+
+    // $<data>#<checksum>
+    fn packet(data: &str) -> String {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        let mut packet = String::new();
+        let _ = write!(packet, "${}#{:02x}", data, sum);
+        packet
+    }
+
+    fn receive(stub: &mut GdbStub, sys: &mut System<DynCartridge>, data: &str) -> String {
+        let mut out = Vec::new();
+        stub.receive(sys, data.as_bytes(), &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    // Reply to a packet, acked
+    fn reply(stub: &mut GdbStub, sys: &mut System<DynCartridge>, data: &str) -> String {
+        let out = receive(stub, sys, &packet(data));
+        let reply = out.strip_prefix('+').expect("packet not acked");
+        let (body, sum) = reply[1..].rsplit_once('#').unwrap();
+        assert_eq!(&packet(body)[body.len() + 1..], ["#", sum].concat());
+        body.into()
+    }
+
+    #[test]
+    fn framing_and_checksum() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        assert_eq!(receive(&mut stub, &mut sys, "$?#3f"), "+$S05#b8");
+        // Acks are ignored, bad checksums asked again
+        assert_eq!(receive(&mut stub, &mut sys, "+$?#00"), "-");
+        assert_eq!(receive(&mut stub, &mut sys, "$?#zz"), "-");
+        // Packets split between reads
+        assert_eq!(receive(&mut stub, &mut sys, "$?"), "");
+        assert_eq!(receive(&mut stub, &mut sys, "#3"), "");
+        assert_eq!(receive(&mut stub, &mut sys, "f"), "+$S05#b8");
+        // Resend
+        assert_eq!(receive(&mut stub, &mut sys, "-"), "$S05#b8");
+        assert_eq!(reply(&mut stub, &mut sys, "vMustReplyEmpty"), "");
+        assert_eq!(reply(&mut stub, &mut sys, "m0100"), "E01");
+    }
+
+    #[test]
+    fn escaping() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        let mut out = Vec::new();
+        stub.send("a$b#c}d*", &mut out);
+        assert_eq!(out, packet("a}\x04b}\x03c}]d}\x0a").as_bytes());
+
+        sys.poke(0xC000, 0x12);
+        // }M is an escaped m
+        assert_eq!(reply(&mut stub, &mut sys, "}Mc000,1"), "12");
+    }
+
+    #[test]
+    fn memory() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        assert_eq!(reply(&mut stub, &mut sys, "Mc000,3:123456"), "OK");
+        assert_eq!(reply(&mut stub, &mut sys, "mc000,3"), "123456");
+        assert_eq!(reply(&mut stub, &mut sys, "mc000,0"), "");
+        assert_eq!(reply(&mut stub, &mut sys, "Mc000,1:1"), "E01");
+    }
+
+    #[test]
+    fn registers() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        let regs = reply(&mut stub, &mut sys, "g");
+        assert_eq!(regs.len(), REGISTERS * 4);
+        assert_eq!(&regs[20..24], "0001");
+
+        let regs = "b0011300d8004d01feff3412";
+        assert_eq!(reply(&mut stub, &mut sys, &["G", regs].concat()), "OK");
+        assert_eq!(sys.registers().af(), 0x01B0);
+        assert_eq!(sys.registers().hl(), 0x014D);
+        assert_eq!(sys.registers().sp(), 0xFFFE);
+        assert_eq!(sys.registers().pc(), 0x1234);
+        assert_eq!(reply(&mut stub, &mut sys, "P5=0001"), "OK");
+        assert_eq!(reply(&mut stub, &mut sys, "p5"), "0001");
+        assert_eq!(reply(&mut stub, &mut sys, "p6"), "0000");
+    }
+
+    #[test]
+    fn breakpoints_and_watchpoints() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        assert_eq!(reply(&mut stub, &mut sys, "Z0,110,1"), "OK");
+        assert_eq!(sys.breakpoints().len(), 1);
+        assert_eq!(reply(&mut stub, &mut sys, "Z2,c000,2"), "OK");
+        assert_eq!(
+            sys.watchpoints(),
+            [Watchpoint::new(0xC000, 0xC001, Access::Write)]
+        );
+
+        assert_eq!(receive(&mut stub, &mut sys, &packet("c")), "+");
+        assert!(stub.running());
+        let mut out = Vec::new();
+        stub.stopped(sys.run_until_vblank(), &mut out);
+        assert_eq!(out, packet("S05").as_bytes());
+        assert_eq!(sys.registers().pc(), 0x0110);
+        assert_eq!(reply(&mut stub, &mut sys, "?"), "S05");
+
+        assert_eq!(reply(&mut stub, &mut sys, "z0,110,1"), "OK");
+        assert_eq!(reply(&mut stub, &mut sys, "z2,c000,2"), "OK");
+        assert_eq!(reply(&mut stub, &mut sys, "z2,c000,2"), "E01");
+        assert!(sys.breakpoints().is_empty() && sys.watchpoints().is_empty());
+        assert_eq!(reply(&mut stub, &mut sys, "Z5,c000,2"), "");
+    }
+
+    #[test]
+    fn stop_replies() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        let mut out = Vec::new();
+        // Not running
+        stub.stopped(Ok(RunOutcome::Breakpoint(0x100)), &mut out);
+        assert!(out.is_empty());
+
+        receive(&mut stub, &mut sys, &packet("c"));
+        stub.stopped(Ok(RunOutcome::Frame), &mut out);
+        assert!(out.is_empty() && stub.running());
+        stub.stopped(Err(CoreError::UnknownCPUState(0x100, 0xFFFE)), &mut out);
+        assert_eq!(out, packet("S06").as_bytes());
+        assert!(!stub.running());
+
+        receive(&mut stub, &mut sys, &packet("c"));
+        assert_eq!(receive(&mut stub, &mut sys, "\x03"), packet("S02"));
+
+        assert_eq!(reply(&mut stub, &mut sys, "s"), "S05");
+        assert_eq!(sys.registers().pc(), 0x0101);
+    }
+
+    #[test]
+    fn target_xml_chunks() {
+        let (mut stub, mut sys) = (GdbStub::default(), system());
+        let mut xml = String::new();
+        let mut offset = 0;
+        loop {
+            let query = alloc::format!("qXfer:features:read:target.xml:{:x},100", offset);
+            let chunk = reply(&mut stub, &mut sys, &query);
+            let (more, data) = chunk.split_at(1);
+            assert!(data.len() <= 0x100);
+            xml.push_str(data);
+            offset += data.len();
+            if more == "l" {
+                break;
+            }
+            assert_eq!(more, "m");
+        }
+        assert_eq!(xml, TARGET_XML);
+        let end = alloc::format!("qXfer:features:read:target.xml:{:x},10", offset);
+        assert_eq!(reply(&mut stub, &mut sys, &end), "l");
+    }
+}
@@ -1,5 +1,279 @@
+use crate::{KEY_A, KEY_B, KEY_DOWN, KEY_LEFT, KEY_RIGHT, KEY_UP, Memory};
+
 pub const COLOR_ZERO: (u8, u8, u8) = (0xED, 0xED, 0xED);
 
+// Colorization applied by the CGB boot ROM to DMG games (RGB555)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompatPalette {
+    pub bg: [u16; 4],
+    pub obj0: [u16; 4],
+    pub obj1: [u16; 4],
+}
+
+impl CompatPalette {
+    const fn new(bg: [u16; 4], obj0: [u16; 4], obj1: [u16; 4]) -> Self {
+        Self { bg, obj0, obj1 }
+    }
+
+    // Palettes selectable with a button combo during the boot logo
+    pub const RIGHT: Self = Self::combination(1);
+    pub const UP: Self = Self::combination(5);
+    pub const UP_A: Self = Self::combination(43);
+    pub const UP_B: Self = Self::combination(28);
+    pub const LEFT: Self = Self::combination(48);
+    pub const LEFT_A: Self = Self::combination(40);
+    pub const LEFT_B: Self = Self::combination(7);
+    pub const DOWN: Self = Self::combination(8);
+    pub const DOWN_A: Self = Self::combination(3);
+    pub const DOWN_B: Self = Self::combination(49);
+    pub const RIGHT_A: Self = Self::combination(0);
+    pub const RIGHT_B: Self = Self::combination(6);
+
+    // Used for games without a specific colorization
+    pub const DEFAULT: Self = Self::RIGHT_A;
+
+    const fn combination(idx: usize) -> Self {
+        let [obj0, obj1, bg] = COMBINATIONS[idx];
+        Self::new(colors(bg), colors(obj0), colors(obj1))
+    }
+
+    // Palette picked by holding a direction (and A or B) during the boot logo
+    pub fn from_keys(keys: u8) -> Option<Self> {
+        let button = keys & (KEY_A | KEY_B);
+        let palette = match (keys & (KEY_UP | KEY_DOWN | KEY_LEFT | KEY_RIGHT), button) {
+            (KEY_UP, 0) => Self::UP,
+            (KEY_UP, KEY_A) => Self::UP_A,
+            (KEY_UP, KEY_B) => Self::UP_B,
+            (KEY_LEFT, 0) => Self::LEFT,
+            (KEY_LEFT, KEY_A) => Self::LEFT_A,
+            (KEY_LEFT, KEY_B) => Self::LEFT_B,
+            (KEY_DOWN, 0) => Self::DOWN,
+            (KEY_DOWN, KEY_A) => Self::DOWN_A,
+            (KEY_DOWN, KEY_B) => Self::DOWN_B,
+            (KEY_RIGHT, 0) => Self::RIGHT,
+            (KEY_RIGHT, KEY_A) => Self::RIGHT_A,
+            (KEY_RIGHT, KEY_B) => Self::RIGHT_B,
+            _ => return None,
+        };
+        Some(palette)
+    }
+
+    // Palette the boot ROM assigns to a Nintendo game from its title checksum.
+    // Some checksums are shared by several games, the 4th letter of the title
+    // tells them apart.
+    pub fn from_title_checksum(checksum: u8, fourth_letter: u8) -> Self {
+        let idx = TITLE_CHECKSUMS.iter().enumerate().position(|(idx, &sum)| {
+            sum == checksum
+                && (idx < FIRST_DUPLICATE || FOURTH_LETTERS[idx - FIRST_DUPLICATE] == fourth_letter)
+        });
+        match idx {
+            // Bit 7 only tells the boot ROM to use the DMG logo tilemap
+            Some(idx) => Self::combination((COMBINATION_PER_CHECKSUM[idx] & 0x7F) as usize),
+            None => Self::DEFAULT,
+        }
+    }
+
+    // Boot keys first, then the title
+    pub(crate) fn at_boot(mem: &mut impl Memory, keys: u8) -> Self {
+        Self::from_keys(keys).unwrap_or_else(|| Self::for_cartridge(mem))
+    }
+
+    pub(crate) fn for_cartridge(mem: &mut impl Memory) -> Self {
+        Self::from_title_checksum(compat_title_checksum(mem), mem.read(0x0137))
+    }
+}
+
+// Sum of the title bytes, used by the CGB boot ROM to colorize Nintendo games
+pub(crate) fn compat_title_checksum(mmu: &mut impl Memory) -> u8 {
+    let nintendo = match mmu.read(0x014B) {
+        0x01 => true,
+        0x33 => mmu.read(0x0144) == b'0' && mmu.read(0x0145) == b'1',
+        _ => false,
+    };
+    if !nintendo {
+        return 0x00;
+    }
+    (0x0134..=0x0143).fold(0u8, |sum, addr| sum.wrapping_add(mmu.read(addr)))
+}
+
+const fn colors(start: u8) -> [u16; 4] {
+    let start = start as usize;
+    [
+        COLORS[start],
+        COLORS[start + 1],
+        COLORS[start + 2],
+        COLORS[start + 3],
+    ]
+}
+
+// Title checksums known by the CGB boot ROM. From FIRST_DUPLICATE on, the
+// 4th letter of the title must match too.
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, // Default
+    0x88, // ALLEY WAY
+    0x16, // YAKUMAN
+    0x36, // BASEBALL, (Game and Watch 2)
+    0xD1, // TENNIS
+    0xDB, // TETRIS
+    0xF2, // QIX
+    0x3C, // DR.MARIO
+    0x8C, // RADARMISSION
+    0x92, // F1RACE
+    0x3D, // YOSSY NO TAMAGO
+    0x5C, 0x58, // X
+    0xC9, // MARIOLAND2
+    0x3E, // YOSSY NO COOKIE
+    0x70, // ZELDA
+    0x1D, 0x59, 0x69, // TETRIS FLASH
+    0x19, // DONKEY KONG
+    0x35, // MARIO'S PICROSS
+    0xA8, 0x14, // POKEMON RED, (GAMEBOYCAMERA G)
+    0xAA, // POKEMON GREEN
+    0x75, // PICROSS 2
+    0x95, // YOSSY NO PANEPON
+    0x99, // KIRAKIRA KIDS
+    0x34, // GAMEBOY GALLERY
+    0x6F, // POCKETCAMERA
+    0x15, 0xFF, // BALLOON KID
+    0x97, // KINGOFTHEZOO
+    0x4B, // DMG FOOTBALL
+    0x90, // WORLD CUP
+    0x17, // OTHELLO
+    0x10, // SUPER RC PRO-AM
+    0x39, // DYNABLASTER
+    0xF7, // BOY AND BLOB GB2
+    0xF6, // MEGAMAN
+    0xA2, // STAR WARS-NOA
+    0x49, 0x4E, // WAVERACE
+    0x43, 0x68, // LOLO2
+    0xE0, // YOSHI'S COOKIE
+    0x8B, // MYSTIC QUEST
+    0xF0, 0xCE, // TOPRANKINGTENNIS
+    0x0C, // MANSELL
+    0x29, // MEGAMAN3
+    0xE8, // SPACE INVADERS
+    0xB7, // GAME&WATCH
+    0x86, // DONKEYKONGLAND95
+    0x9A, // ASTEROIDS/MISCMD
+    0x52, // STREET FIGHTER 2
+    0x01, // DEFENDER/JOUST
+    0x9D, // KILLERINSTINCT95
+    0x71, // TETRIS BLAST
+    0x9C, // PINOCCHIO
+    0xBD, 0x5D, // BA.TOSHINDEN
+    0x6D, // NETTOU KOF 95
+    0x67, 0x3F, // TETRIS PLUS
+    0x6B, // DONKEYKONGLAND 3
+    0xB3, // ???[B]????????
+    0x46, // SUP[E]R MARIOLAND
+    0x28, // GOL[F]
+    0xA5, // SOL[A]RSTRIKER
+    0xC6, // GBW[A]RS
+    0xD3, // KAE[R]UNOTAMENI
+    0x27, // ???[B]????????
+    0x61, // POK[E]MON BLUE
+    0x18, // DON[K]EYKONGLAND
+    0x66, // GAM[E]BOY GALLERY2
+    0x6A, // DON[K]EYKONGLAND 2
+    0xBF, // KID[ ]ICARUS
+    0x0D, // TET[R]IS2
+    0xF4, // ???[-]????????
+    0xB3, // MOG[U]RANYA
+    0x46, // ???[R]????????
+    0x28, // GAL[A]GA&GALAXIAN
+    0xA5, // BT2[R]AGNAROKWORLD
+    0xC6, // KEN[ ]GRIFFEY JR
+    0xD3, // ???[I]????????
+    0x27, // MAG[N]ETIC SOCCER
+    0x61, // VEG[A]S STAKES
+    0x18, // ???[I]????????
+    0x66, // MIL[L]I/CENTI/PEDE
+    0x6A, // MAR[I]O & YOSHI
+    0xBF, // SOC[C]ER
+    0x0D, // POK[E]BOM
+    0xF4, // G&W[ ]GALLERY
+    0xB3, // TET[R]IS ATTACK
+];
+const FIRST_DUPLICATE: usize = 65;
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+// Entry of COMBINATIONS for each title checksum
+const COMBINATION_PER_CHECKSUM: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44, 21, 32, 31, 20, 5, 33, 13, 14, 5, 29,
+    5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6,
+    5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0, 39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39,
+    24, 31, 50, 17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+
+// First color in COLORS of the OBJ0, OBJ1 and BG palettes. A few start in
+// the middle of a palette, as in the boot ROM.
+const COMBINATIONS: [[u8; 3]; 51] = [
+    [16, 16, 116],
+    [72, 72, 72],
+    [80, 80, 80],
+    [96, 96, 96],
+    [36, 36, 36],
+    [0, 0, 0],
+    [108, 108, 108],
+    [20, 20, 20],
+    [48, 48, 48],
+    [104, 104, 104],
+    [64, 32, 32],
+    [16, 112, 112],
+    [16, 8, 8],
+    [12, 16, 16],
+    [16, 116, 116],
+    [112, 16, 112],
+    [8, 68, 8],
+    [64, 64, 32],
+    [16, 16, 28],
+    [16, 16, 72],
+    [16, 16, 80],
+    [76, 76, 36],
+    [15, 15, 44],
+    [68, 68, 8],
+    [16, 16, 8],
+    [16, 16, 12],
+    [112, 112, 0],
+    [12, 12, 0],
+    [0, 0, 4],
+    [72, 88, 72],
+    [80, 88, 80],
+    [96, 88, 96],
+    [64, 88, 32],
+    [68, 16, 52],
+    [111, 0, 56],
+    [111, 16, 60],
+    [76, 91, 36],
+    [64, 112, 40],
+    [16, 92, 112],
+    [68, 88, 8],
+    [16, 0, 8],
+    [16, 112, 12],
+    [112, 12, 0],
+    [12, 112, 16],
+    [84, 112, 16],
+    [12, 112, 0],
+    [100, 12, 112],
+    [0, 112, 32],
+    [16, 12, 112],
+    [112, 12, 24],
+    [16, 112, 116],
+];
+
+const COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000, 0x639F, 0x4279, 0x15B0, 0x04CB, 0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000, 0x7FFF, 0x421F, 0x1CF2, 0x0000, 0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000, 0x7FFF, 0x03EF, 0x01D6, 0x0000, 0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000, 0x67FF, 0x77AC, 0x1A13, 0x2D6B, 0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000, 0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0, 0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF, 0x7FFF, 0x01DF, 0x0112, 0x0000, 0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000, 0x299F, 0x001A, 0x000C, 0x0000, 0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120, 0x7FFF, 0x7EEB, 0x001F, 0x7C00, 0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000, 0x03FF, 0x001F, 0x000C, 0x0000, 0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF, 0x7FFF, 0x7E8C, 0x7C00, 0x0000, 0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
 pub struct Colors {
     // FF47 - BGP - BG Palette Data (R/W) - Non CGB Mode Only
     pub ff47_bgp: u8,
@@ -12,6 +286,11 @@ pub struct Colors {
     pub ff68_bcps_bgpi: u8,
     // FF69 - BCPD/BGPD - CGB Mode Only - Background Palette Data
     ff69_bcpd_bgpd: [u8; 64],
+
+    // FF6A - OCPS/OBPI - CGB Mode Only - Sprite Palette Index
+    pub ff6a_ocps_obpi: u8,
+    // FF6B - OCPD/OBPD - CGB Mode Only - Sprite Palette Data
+    ff6b_ocpd_obpd: [u8; 64],
 }
 
 impl Default for Colors {
@@ -22,6 +301,8 @@ impl Default for Colors {
             ff49_obp1: 0,
             ff68_bcps_bgpi: 0,
             ff69_bcpd_bgpd: [0; 64],
+            ff6a_ocps_obpi: 0,
+            ff6b_ocpd_obpd: [0; 64],
         }
     }
 }
@@ -42,26 +323,32 @@ impl Colors {
     // Colors
 
     pub fn cgb_bgp_palette(&self, idx: u8) -> Palette {
-        let mut byte_offset = (idx * 8) as usize; // 8 possible palettes
-        let mut palette = Palette::default();
-        for idx in 0..4 {
-            let color_bits = (self.ff69_bcpd_bgpd[byte_offset] as u16)
-                | ((self.ff69_bcpd_bgpd[byte_offset + 1] as u16) << 8);
-            byte_offset += 2;
+        Palette::new_cgb_palette(&self.ff69_bcpd_bgpd, idx)
+    }
+    pub fn cgb_obp_palette(&self, idx: u8) -> Palette {
+        Palette::new_cgb_palette(&self.ff6b_ocpd_obpd, idx)
+    }
 
-            let mut color = (
-                (color_bits as u8 & 0x1F) << 3,
-                ((color_bits >> 5) as u8 & 0x1F) << 3,
-                ((color_bits >> 10) as u8 & 0x1F) << 3,
-            );
+    // DMG compatibility mode
 
-            if color == (0, 0, 0) {
-                color = COLOR_ZERO;
-            }
-            palette.colors[idx] = color
-        }
+    pub fn compat_bgp_palette(&self) -> Palette {
+        Palette::new_compat_palette(&self.ff69_bcpd_bgpd, 0, self.ff47_bgp)
+    }
+    pub fn compat_obp0_palette(&self) -> Palette {
+        Palette::new_compat_palette(&self.ff6b_ocpd_obpd, 0, self.ff48_obp0)
+    }
+    pub fn compat_obp1_palette(&self) -> Palette {
+        Palette::new_compat_palette(&self.ff6b_ocpd_obpd, 1, self.ff49_obp1)
+    }
 
-        palette
+    // What the CGB boot ROM does before starting a DMG game
+    pub fn load_compat_palette(&mut self, palette: &CompatPalette) {
+        for (idx, color) in palette.bg.iter().enumerate() {
+            self.ff69_bcpd_bgpd[idx * 2..idx * 2 + 2].copy_from_slice(&color.to_le_bytes());
+        }
+        for (idx, color) in palette.obj0.iter().chain(&palette.obj1).enumerate() {
+            self.ff6b_ocpd_obpd[idx * 2..idx * 2 + 2].copy_from_slice(&color.to_le_bytes());
+        }
     }
 
     // IO
@@ -80,6 +367,21 @@ impl Colors {
             self.ff68_bcps_bgpi = 0b10000000 | byte_idx;
         }
     }
+
+    pub fn ff6b_ocpd_obpd(&self) -> u8 {
+        let byte_idx = self.ff6a_ocps_obpi & 0b111111;
+        self.ff6b_ocpd_obpd[byte_idx as usize]
+    }
+
+    pub fn set_ff6b_ocpd_obpd(&mut self, value: u8) {
+        let mut byte_idx = self.ff6a_ocps_obpi & 0b111111;
+        let auto_inc = (self.ff6a_ocps_obpi & 0b10000000) != 0;
+        self.ff6b_ocpd_obpd[byte_idx as usize] = value;
+        if auto_inc {
+            byte_idx = (byte_idx + 1) & 0b111111;
+            self.ff6a_ocps_obpi = 0b10000000 | byte_idx;
+        }
+    }
 }
 
 pub struct Palette {
@@ -95,6 +397,37 @@ impl Default for Palette {
 }
 
 impl Palette {
+    fn new_cgb_palette(data: &[u8; 64], idx: u8) -> Self {
+        let mut byte_offset = (idx * 8) as usize; // 8 possible palettes
+        let mut palette = Palette::default();
+        for idx in 0..4 {
+            let color_bits = (data[byte_offset] as u16) | ((data[byte_offset + 1] as u16) << 8);
+            byte_offset += 2;
+
+            let mut color = (
+                (color_bits as u8 & 0x1F) << 3,
+                ((color_bits >> 5) as u8 & 0x1F) << 3,
+                ((color_bits >> 10) as u8 & 0x1F) << 3,
+            );
+
+            if color == (0, 0, 0) {
+                color = COLOR_ZERO;
+            }
+            palette.colors[idx] = color
+        }
+
+        palette
+    }
+
+    fn new_compat_palette(data: &[u8; 64], idx: u8, raw: u8) -> Self {
+        let colors = Self::new_cgb_palette(data, idx);
+        let mut palette = Palette::default();
+        for idx in 0..4 {
+            palette.colors[idx] = colors.colors[((raw >> (idx * 2)) & 0x03) as usize];
+        }
+        palette
+    }
+
     fn new_mono_palette(raw: u8) -> Self {
         let mut palette = Palette::default();
         for idx in 0..4 {
@@ -119,3 +452,63 @@ impl Palette {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::DynCartridge;
+    extern crate alloc;
+    use alloc::vec;
+
+    fn nintendo_cartridge(title: &[u8]) -> DynCartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x0134 + title.len()].copy_from_slice(title);
+        rom[0x014B] = 0x01;
+        DynCartridge::new(rom).unwrap()
+    }
+
+    #[test]
+    fn title_checksum() {
+        let mut cart = nintendo_cartridge(b"POKEMON RED");
+        let palette = CompatPalette::for_cartridge(&mut cart);
+        assert_eq!(palette.bg, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+        assert_eq!(palette.obj0, [0x7FFF, 0x1BEF, 0x0200, 0x0000]);
+        assert_eq!(palette.obj1, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+    }
+
+    #[test]
+    fn duplicate_checksum() {
+        // Both titles sum to 0x46
+        let mut cart = nintendo_cartridge(b"SUPER MARIOLAND");
+        let palette = CompatPalette::for_cartridge(&mut cart);
+        assert_eq!(palette.bg, [0x7ED6, 0x4BFF, 0x2175, 0x0000]);
+        assert_eq!(palette.obj0, [0x0000, 0x7FFF, 0x421F, 0x1CF2]);
+        let palette = CompatPalette::from_title_checksum(0x46, b'R');
+        assert_eq!(palette.bg, [0x7FFF, 0x7E8C, 0x7C00, 0x0000]);
+        let palette = CompatPalette::from_title_checksum(0x46, b'X');
+        assert_eq!(palette, CompatPalette::DEFAULT);
+    }
+
+    #[test]
+    fn other_licensee() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x013F].copy_from_slice(b"POKEMON RED");
+        rom[0x014B] = 0x08;
+        let mut cart = DynCartridge::new(rom).unwrap();
+        assert_eq!(
+            CompatPalette::for_cartridge(&mut cart),
+            CompatPalette::DEFAULT
+        );
+    }
+
+    #[test]
+    fn boot_keys() {
+        let mut cart = nintendo_cartridge(b"POKEMON RED");
+        let palette = CompatPalette::at_boot(&mut cart, crate::KEY_LEFT | crate::KEY_B);
+        assert_eq!(palette, CompatPalette::LEFT_B);
+        assert_eq!(palette.bg, [0x7FFF, 0x5294, 0x294A, 0x0000]);
+        let palette = CompatPalette::at_boot(&mut cart, crate::KEY_START);
+        assert_eq!(palette, CompatPalette::for_cartridge(&mut cart));
+        assert_eq!(CompatPalette::RIGHT_A.bg, [0x7FFF, 0x1BEF, 0x6180, 0x0000]);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
-    MByte, Memory, Screen,
+    CoreError, MByte, Memory, Screen,
     cartridge::Cartridge,
-    gpu::{colors::COLOR_ZERO, lcd::Mode},
+    gpu::{
+        colors::{COLOR_ZERO, Colors, Palette},
+        lcd::Mode,
+    },
     mmu::MMU,
 };
 
@@ -21,6 +24,28 @@ type BgPalette = MByte<0xFF47>; //      BGP  - BG Palette Data
 type SpritePalette0 = MByte<0xFF48>; // OBP0 - Object Palette 0 Data
 type SpritePalette1 = MByte<0xFF49>; // OBP1 - Object Palette 1 Data
 
+// How tiles and sprites pick their colors
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum ColorMode {
+    // DMG shades from BGP/OBP0/OBP1
+    #[default]
+    Mono,
+    // CGB palettes selected by tile and sprite attributes
+    Color,
+    // DMG game on CGB: BGP/OBP0/OBP1 shades index CGB palettes BG0/OBJ0/OBJ1
+    Compat,
+}
+
+impl ColorMode {
+    fn bg_palette(self, colors: &Colors, cgb_palette_number: u8) -> Palette {
+        match self {
+            ColorMode::Color => colors.cgb_bgp_palette(cgb_palette_number),
+            ColorMode::Compat => colors.compat_bgp_palette(),
+            ColorMode::Mono => colors.bgp_palette(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct GPU {
     current_mode: Mode, // Can not rely on FF41
@@ -29,34 +54,38 @@ pub(crate) struct GPU {
     disabled_length: usize,
 
     screen: Screen,
-
-    color_mode: bool,
 }
 
 impl GPU {
-    pub fn new(color: bool) -> Self {
-        let mut gpu = Self::default();
-        gpu.color_mode = color;
-        gpu
-    }
-
     pub fn swap_screen(&mut self, screen: &mut Screen) {
         core::mem::swap(&mut self.screen, screen);
     }
 
-    pub fn tick<C: Cartridge>(&mut self, mmu: &mut MMU<C>, ticks: u8) -> State {
+    // PPU not running (CPU stopped): the LCD is blank, frames are still
+    // handed out on time
+    pub fn idle(&mut self, ticks: u8) -> State {
+        self.disabled_length += ticks as usize;
+        if self.disabled_length >= FULL_FRAME {
+            self.disabled_length -= FULL_FRAME;
+            self.screen = Screen::default();
+            return State::Frame;
+        }
+        State::Default
+    }
+
+    pub fn tick<C: Cartridge>(&mut self, mmu: &mut MMU<C>, ticks: u8) -> Result<State, CoreError> {
         if !mmu.lcd.display_enable() {
             if self.disabled_length >= FULL_FRAME {
                 self.disabled_length = 0;
-                return State::Frame;
+                return Ok(State::Frame);
             }
-            self.disabled_length += 1;
-            return State::Default;
+            self.disabled_length += ticks as usize;
+            return Ok(State::Default);
         }
 
         self.current_mode_length += ticks as usize;
         let state = match self.current_mode {
-            Mode::HBlank => self.do_hblank(mmu),
+            Mode::HBlank => self.do_hblank(mmu)?,
             Mode::VBlank => {
                 self.do_vblank(mmu);
                 State::Default
@@ -81,7 +110,7 @@ impl GPU {
             mmu.lcd.set_coincidence_flag_interrupt(false)
         }
 
-        state
+        Ok(state)
     }
 
     fn switch_mode<C: Cartridge>(&mut self, mem: &mut MMU<C>, mode: Mode) {
@@ -98,10 +127,10 @@ impl GPU {
         self.current_mode = mode;
     }
 
-    fn do_hblank<C: Cartridge>(&mut self, mmu: &mut MMU<C>) -> State {
+    fn do_hblank<C: Cartridge>(&mut self, mmu: &mut MMU<C>) -> Result<State, CoreError> {
         match self.current_mode_length {
             // HBLANK continues
-            0..=HBLANK_LENGTH => State::Default,
+            0..=HBLANK_LENGTH => Ok(State::Default),
             // End of HBLANK
             _ => {
                 mmu.lcd.ff44_ly = mmu.lcd.ff44_ly.wrapping_add(1);
@@ -109,14 +138,14 @@ impl GPU {
                     // End of line
                     0..=143 => {
                         self.switch_mode(mmu, Mode::SearchOAM);
-                        State::Default
+                        Ok(State::Default)
                     }
                     // End of frame, start VBLANK
                     144 => {
                         self.switch_mode(mmu, Mode::VBlank);
-                        State::Frame
+                        Ok(State::Frame)
                     }
-                    _ => panic!("Bad ly value at the end of hblank"),
+                    ly => Err(CoreError::UnknownGPULY(ly)),
                 }
             }
         }
@@ -176,7 +205,6 @@ impl GPU {
     }
 
     fn draw_bg_line<C: Cartridge>(&mut self, mmu: &mut MMU<C>, line: u8) {
-        let mut palette = mmu.colors.bgp_palette();
         let mut pixel_x: u8 = 0;
 
         let scroll_x: u8 = mmu.lcd.ff43_scx;
@@ -203,11 +231,9 @@ impl GPU {
                 tile_map_cell.idx(),
             );
 
-            if self.color_mode {
-                palette = mmu
-                    .colors
-                    .cgb_bgp_palette(tile_map_cell.cgb_palette_number());
-            }
+            let palette = mmu
+                .color_mode
+                .bg_palette(&mmu.colors, tile_map_cell.cgb_palette_number());
 
             let lo = tile_data.0[in_tile_byte_offset];
             let hi = tile_data.0[in_tile_byte_offset + 1];
@@ -242,7 +268,6 @@ impl GPU {
             return;
         }
 
-        let palette = mmu.colors.bgp_palette();
         let mut pixel_x: u8 = 0;
 
         let scroll_x: u8 = mmu.lcd.ff4b_wx.wrapping_sub(7);
@@ -269,6 +294,10 @@ impl GPU {
                 tile_map_cell.idx(),
             );
 
+            let palette = mmu
+                .color_mode
+                .bg_palette(&mmu.colors, tile_map_cell.cgb_palette_number());
+
             let lo = tile_data.0[in_tile_byte_offset];
             let hi = tile_data.0[in_tile_byte_offset + 1];
 
@@ -334,12 +363,21 @@ impl GPU {
 
                 let in_tile_byte_offset: usize = (sprite_line as usize) * 2;
 
-                let palette = match sprite.palette() {
-                    true => mmu.colors.obp1_palette(),
-                    false => mmu.colors.obp0_palette(),
+                let palette = match (mmu.color_mode, sprite.palette()) {
+                    (ColorMode::Color, _) => {
+                        mmu.colors.cgb_obp_palette(sprite.cgb_palette_number())
+                    }
+                    (ColorMode::Compat, true) => mmu.colors.compat_obp1_palette(),
+                    (ColorMode::Compat, false) => mmu.colors.compat_obp0_palette(),
+                    (ColorMode::Mono, true) => mmu.colors.obp1_palette(),
+                    (ColorMode::Mono, false) => mmu.colors.obp0_palette(),
                 };
                 let obj_to_bg_priority = sprite.obj_to_bg_priority();
-                let tile_data = mmu.vram.get_tile_data(true, false, tile_number);
+                let tile_data = mmu.vram.get_tile_data(
+                    true,
+                    mmu.color_mode == ColorMode::Color && sprite.bank(),
+                    tile_number,
+                );
 
                 let mut lo = tile_data.0[in_tile_byte_offset];
                 let mut hi = tile_data.0[in_tile_byte_offset + 1];
@@ -35,27 +35,23 @@ impl Memory for OAM {
                     0 => self.sprites[sprite_idx].y_pos,
                     1 => self.sprites[sprite_idx].x_pos,
                     2 => self.sprites[sprite_idx].tile_number,
-                    3 => self.sprites[sprite_idx].flags,
-                    _ => panic!("Sprite read out {:#04x}", addr),
+                    _ => self.sprites[sprite_idx].flags,
                 }
             }
-            _ => panic!("OAM read out {:#04x}", addr),
+            _ => 0xFF,
         }
     }
 
     fn write(&mut self, addr: u16, value: u8) {
-        match addr {
-            0xFE00..=0xFE9F => {
-                let sprite_idx = ((addr - 0xFE00) as usize) >> 2;
-                match ((addr - 0xFE00) as usize) & 0b11 {
-                    0 => self.sprites[sprite_idx].y_pos = value,
-                    1 => self.sprites[sprite_idx].x_pos = value,
-                    2 => self.sprites[sprite_idx].tile_number = value,
-                    3 => self.sprites[sprite_idx].flags = value,
-                    _ => panic!("Sprite write out {:#04x}", addr),
-                }
-            }
-            _ => panic!("OAM write out {:#04x}", addr),
+        if !(0xFE00..=0xFE9F).contains(&addr) {
+            return;
+        }
+        let sprite_idx = ((addr - 0xFE00) as usize) >> 2;
+        match ((addr - 0xFE00) as usize) & 0b11 {
+            0 => self.sprites[sprite_idx].y_pos = value,
+            1 => self.sprites[sprite_idx].x_pos = value,
+            2 => self.sprites[sprite_idx].tile_number = value,
+            _ => self.sprites[sprite_idx].flags = value,
         }
     }
 }
@@ -77,6 +73,16 @@ pub(crate) struct Sprite {
 }
 
 impl Sprite {
+    // Bit2-0 Palette number  **CGB Mode Only**     (OBP0-7)
+    pub fn cgb_palette_number(&self) -> u8 {
+        self.flags & 0b111
+    }
+
+    // Bit3   Tile VRAM-Bank  **CGB Mode Only**     (0=Bank 0, 1=Bank 1)
+    pub fn bank(&self) -> bool {
+        get_bit::<3>(self.flags)
+    }
+
     // Bit4   Palette number  **Non CGB Mode Only** (0=OBP0, 1=OBP1)
     pub fn palette(&self) -> bool {
         get_bit::<4>(self.flags)
@@ -100,24 +106,32 @@ impl Sprite {
 }
 
 const TRANSFER_DST: u16 = 0xFE00;
+const TRANSFER_LEN: u16 = 160;
 
+// Copies one byte per M-cycle, 160 M-cycles per transfer. Writing FF46
+// during a transfer restarts it.
 #[derive(Default)]
-pub(crate) struct OamDmaManager {}
+pub(crate) struct OamDmaManager {
+    // Next byte to copy of the running transfer
+    next: Option<u16>,
+}
 
 impl OamDmaManager {
-    pub fn tick<C: Cartridge>(&mut self, mmu: &mut MMU<C>) {
-        if !mmu.oam.dma_transfer_requested {
-            return;
-        }
-        let mut src = (mmu.oam.ff46_dma() as u16) << 8;
-        let mut dst = TRANSFER_DST;
-        for _ in 0..160 {
+    pub fn tick<C: Cartridge>(&mut self, mmu: &mut MMU<C>, ticks: u8) {
+        for _ in 0..ticks / 4 {
+            // The transfer starts on the M-cycle after the FF46 write
+            if mmu.oam.dma_transfer_requested {
+                mmu.oam.dma_transfer_requested = false;
+                self.next = Some(0);
+                continue;
+            }
+            let Some(i) = self.next else {
+                return;
+            };
+            let src = ((mmu.oam.ff46_dma() as u16) << 8) + i;
             let byte = mmu.read(src);
-            mmu.write(dst, byte);
-            src += 1;
-            dst += 1;
+            mmu.write(TRANSFER_DST + i, byte);
+            self.next = Some(i + 1).filter(|&i| i < TRANSFER_LEN);
         }
-
-        mmu.oam.dma_transfer_requested = false;
     }
 }
@@ -1,22 +1,38 @@
 #![no_std]
 #![feature(iter_array_chunks)]
+mod boot;
+mod bus;
 pub mod cartridge;
 mod cpu;
+pub mod debugger;
+pub mod disasm;
 mod error;
+pub mod gdb;
 mod gpu;
 mod hram;
 mod mmu;
+mod model;
+mod serial;
 mod system;
 mod timers;
+mod trace;
 mod unusable;
 mod wram;
 
+pub use self::boot::BootRom;
+pub use self::cpu::{Cpu, LockUp, Registers};
 pub use self::error::CoreError;
-pub use self::system::System;
+pub use self::gpu::colors::CompatPalette;
+pub use self::model::Model;
+pub use self::system::{RunOutcome, StepOutcome, System, SystemBuilder};
+pub use self::trace::TraceFormat;
 
 pub trait Memory {
     fn read(&mut self, addr: u16) -> u8;
     fn write(&mut self, addr: u16, value: u8);
+
+    // M-cycle of an instruction without memory access
+    fn internal(&mut self) {}
 }
 
 pub(crate) fn get_bit<const BIT: u32>(n: u8) -> bool {
@@ -1,11 +1,13 @@
 mod interrupt;
+mod speed;
 
 use crate::{
     Memory,
+    boot::BootRom,
     cartridge::Cartridge,
-    gpu::{colors::Colors, lcd::LCD, oam::OAM, vram::VRAM},
+    gpu::{ColorMode, colors::Colors, lcd::LCD, oam::OAM, vram::VRAM},
     hram::HRAM,
-    mmu::interrupt::Interrupt,
+    mmu::{interrupt::Interrupt, speed::Speed},
     unusable::Unusable,
     wram::WRAM,
 };
@@ -14,7 +16,11 @@ pub(crate) struct MMU<C: Cartridge>
 where
     C: Cartridge,
 {
-    cartridge: C,
+    pub cartridge: C,
+    boot_rom: Option<BootRom>,
+    // CGB registers (VBK, SVBK, palettes) are only available in CGB mode.
+    // The CGB boot ROM picks the mode of the game through KEY0.
+    pub color_mode: ColorMode,
     hram: HRAM,
     wram: WRAM,
     unusable: Unusable,
@@ -23,15 +29,20 @@ where
     pub oam: OAM,
     pub interrupt: Interrupt,
     pub lcd: LCD,
+    pub speed: Speed,
     pub vram: VRAM,
 
     io: [u8; 0xFF7F - 0xFF00 + 1], // For other IO
+
+    // Value read from LY instead of the real one, for comparable traces
+    pub ly_stub: Option<u8>,
 }
 
 impl<C> MMU<C>
 where
     C: Cartridge,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         cartridge: C,
         hram: HRAM,
@@ -39,9 +50,13 @@ where
         unusable: Unusable,
         vram: VRAM,
         oam: OAM,
+        boot_rom: Option<BootRom>,
+        color_mode: ColorMode,
     ) -> Self {
         MMU {
             cartridge,
+            boot_rom,
+            color_mode,
             interrupt: Interrupt::default(),
             hram,
             wram,
@@ -49,10 +64,33 @@ where
             vram,
             oam,
             lcd: LCD::default(),
+            speed: Speed::default(),
             colors: Colors::default(),
             io: [0; 0xFF7F - 0xFF00 + 1],
+            ly_stub: None,
         }
     }
+
+    // Power cycle, the cartridge keeps its state
+    pub fn reset(&mut self, boot_rom: Option<BootRom>, color_mode: ColorMode) {
+        self.boot_rom = boot_rom;
+        self.color_mode = color_mode;
+        self.hram = HRAM::default();
+        self.wram = WRAM::default();
+        self.unusable = Unusable::default();
+        self.vram = VRAM::default();
+        self.oam = OAM::default();
+        self.interrupt = Interrupt::default();
+        self.lcd = LCD::default();
+        self.speed = Speed::default();
+        self.colors = Colors::default();
+        self.io = [0; 0xFF7F - 0xFF00 + 1];
+    }
+
+    // The CGB boot ROM also sets up the palettes of DMG games
+    fn cgb_registers(&self) -> bool {
+        self.color_mode == ColorMode::Color || self.boot_rom.as_ref().is_some_and(|b| b.is_cgb())
+    }
 }
 
 impl<'a, C> Memory for MMU<C>
@@ -61,6 +99,12 @@ where
 {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
+            // CGB registers are open bus in DMG mode
+            0xFF4D | 0xFF4F | 0xFF68..=0xFF6B | 0xFF70 if !self.cgb_registers() => 0xFF,
+            // Boot ROM, until FF50 is written
+            0x0000..=0x08FF if self.boot_rom.as_ref().is_some_and(|b| b.contains(addr)) => {
+                self.boot_rom.as_ref().unwrap().read(addr)
+            }
             // Boot Room
             0x0000..=0x00FF => self.cartridge.read(addr),
             // 16 KiB ROM bank 00
@@ -92,7 +136,7 @@ where
             // SCX: Background viewport X position
             0xFF43 => self.lcd.ff43_scx,
             // LY - LCDC Y-Coordinate
-            0xFF44 => self.lcd.ff44_ly,
+            0xFF44 => self.ly_stub.unwrap_or(self.lcd.ff44_ly),
             // LYC - LY Compare
             0xFF45 => self.lcd.ff45_lyc,
             // DMA: OAM DMA source address & start
@@ -107,12 +151,20 @@ where
             0xFF4A => self.lcd.ff4a_wy,
             // WX - Window X Position minus 7
             0xFF4B => self.lcd.ff4b_wx,
+            // KEY1 - CGB Mode Only - Prepare Speed Switch
+            0xFF4D => self.speed.ff4d_key1(),
             // VBK (CGB Mode only): VRAM bank
             0xFF4F => self.vram.ff4f_vbk,
             // BCPS/BGPI - CGB Mode Only - Background Palette Index
             0xFF68 => self.colors.ff68_bcps_bgpi,
             // BCPD/BGPD - CGB Mode Only - Background Palette Data
             0xFF69 => self.colors.ff69_bcpd_bgpd(),
+            // OCPS/OBPI - CGB Mode Only - Sprite Palette Index
+            0xFF6A => self.colors.ff6a_ocps_obpi,
+            // OCPD/OBPD - CGB Mode Only - Sprite Palette Data
+            0xFF6B => self.colors.ff6b_ocpd_obpd(),
+            // SVBK - CGB Mode Only - WRAM Bank
+            0xFF70 => self.wram.ff70_svbk,
             // I/O
             0xFF00..=0xFF7F => self.io[(addr - 0xFF00) as usize],
             // HRAM
@@ -124,6 +176,7 @@ where
 
     fn write(&mut self, addr: u16, value: u8) {
         match addr {
+            0xFF4D | 0xFF4F | 0xFF68..=0xFF6B | 0xFF70 if !self.cgb_registers() => {}
             // Boot Room
             0x0000..=0x00FF => self.cartridge.write(addr, value),
             // 16 KiB ROM bank 00
@@ -170,12 +223,39 @@ where
             0xFF4A => self.lcd.ff4a_wy = value,
             // WX - Window X Position minus 7
             0xFF4B => self.lcd.ff4b_wx = value,
+            // KEY1 - CGB Mode Only - Prepare Speed Switch
+            0xFF4D => self.speed.set_ff4d_key1(value),
             // VBK (CGB Mode only): VRAM bank
             0xFF4F => self.vram.ff4f_vbk = value,
+            // KEY0 - CPU mode, locked once the boot ROM is disabled
+            0xFF4C => {
+                if self.boot_rom.is_some() {
+                    self.io[(addr - 0xFF00) as usize] = value;
+                }
+            }
+            // BANK: Boot ROM disable
+            0xFF50 => {
+                if value & 0x01 != 0
+                    && let Some(boot_rom) = self.boot_rom.take()
+                    && boot_rom.is_cgb()
+                    && self.color_mode == ColorMode::Color
+                    && self.io[0x4C] & 0x0C == 0x04
+                {
+                    // DMG game: the boot ROM has set the compatibility palettes
+                    self.color_mode = ColorMode::Compat;
+                }
+                self.io[(addr - 0xFF00) as usize] = value;
+            }
             // BCPS/BGPI - CGB Mode Only - Background Palette Index
             0xFF68 => self.colors.ff68_bcps_bgpi = value,
             // BCPD/BGPD - CGB Mode Only - Background Palette Data
             0xFF69 => self.colors.set_ff69_bcpd_bgpd(value),
+            // OCPS/OBPI - CGB Mode Only - Sprite Palette Index
+            0xFF6A => self.colors.ff6a_ocps_obpi = value,
+            // OCPD/OBPD - CGB Mode Only - Sprite Palette Data
+            0xFF6B => self.colors.set_ff6b_ocpd_obpd(value),
+            // SVBK - CGB Mode Only - WRAM Bank
+            0xFF70 => self.wram.ff70_svbk = value,
             // I/O
             0xFF00..=0xFF7F => self.io[(addr - 0xFF00) as usize] = value,
             // HRAM
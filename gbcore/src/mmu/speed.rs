@@ -0,0 +1,25 @@
+#[derive(Default)]
+pub(crate) struct Speed {
+    // CPU running at 8 MHz
+    pub double_speed: bool,
+    // Speed switch done by the next STOP
+    pub switch_armed: bool,
+}
+
+impl Speed {
+    // FF4D - KEY1 - CGB Mode Only - Prepare Speed Switch
+    //   Bit 7: Current Speed     (0=Normal, 1=Double) (Read Only)
+    //   Bit 0: Prepare Speed Switch (0=No, 1=Prepare) (Read/Write)
+    pub fn ff4d_key1(&self) -> u8 {
+        0x7E | ((self.double_speed as u8) << 7) | self.switch_armed as u8
+    }
+
+    pub fn set_ff4d_key1(&mut self, value: u8) {
+        self.switch_armed = value & 0x01 != 0;
+    }
+
+    pub fn switch(&mut self) {
+        self.double_speed = !self.double_speed;
+        self.switch_armed = false;
+    }
+}
@@ -0,0 +1,21 @@
+// Console variant being emulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    // Original Game Boy
+    Dmg,
+    // Game Boy Pocket / Light
+    Mgb,
+    // Super Game Boy
+    Sgb,
+    // Game Boy Color
+    Cgb,
+    // Game Boy Advance running GB/GBC games
+    Agb,
+}
+
+impl Model {
+    // CGB hardware: color palettes, VRAM/WRAM banks, DMG compatibility mode
+    pub fn is_cgb(&self) -> bool {
+        matches!(self, Model::Cgb | Model::Agb)
+    }
+}
@@ -0,0 +1,49 @@
+extern crate alloc;
+use alloc::collections::VecDeque;
+
+use crate::{MBit, MByte, Memory};
+
+const CPUCLOCK: usize = 4194304;
+const TRANSFER_LENGTH: usize = CPUCLOCK / 8192 * 8;
+const OUTPUT_CAPACITY: usize = 4096;
+
+type Sb = MByte<0xFF01>;
+type Sc = MByte<0xFF02>;
+type SerialInt = MBit<0xFF0F, 3>;
+
+#[derive(Default)]
+pub struct Serial {
+    // Cycles counter of the current transfer
+    transfer_count: usize,
+    // Bytes shifted out with no cable connected
+    output: VecDeque<u8>,
+}
+
+impl Serial {
+    pub fn tick(&mut self, mmu: &mut impl Memory, ticks: u8) {
+        // FF02 - SC: Bit 7 - Transfer enable, Bit 0 - Clock select (1=Internal)
+        let sc = Sc::read(mmu);
+        if sc & 0b10000001 != 0b10000001 {
+            self.transfer_count = 0;
+            return;
+        }
+
+        self.transfer_count += ticks as usize;
+        if self.transfer_count >= TRANSFER_LENGTH {
+            if self.output.len() == OUTPUT_CAPACITY {
+                self.output.pop_front();
+            }
+            self.output.push_back(Sb::read(mmu));
+
+            // Nothing connected: 1s are shifted in
+            Sb::write(mmu, 0xFF);
+            Sc::write(mmu, sc & 0b01111111);
+            SerialInt::set(mmu, true);
+            self.transfer_count = 0;
+        }
+    }
+
+    pub fn read_output(&mut self) -> Option<u8> {
+        self.output.pop_front()
+    }
+}
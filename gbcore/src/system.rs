@@ -1,74 +1,551 @@
 use crate::{
-    MBit, MByte, Memory, Screen,
-    cartridge::Cartridge,
-    cpu,
+    CoreError, LockUp, MBit, MByte, Memory, Model, Registers, Screen,
+    boot::BootRom,
+    bus::Bus,
+    cartridge::{Cartridge, CgbFlag},
+    cpu::{self, NextTick},
+    debugger::{Breakpoint, Debugger, WatchHit, Watchpoint},
+    disasm::{self, Instruction},
     gpu::{
-        self, State,
+        self, ColorMode,
+        colors::CompatPalette,
         oam::{self, OamDmaManager},
         vram,
     },
     hram,
     mmu::MMU,
+    serial::Serial,
     timers::Timers,
+    trace::{Trace, TraceFormat},
     unusable, wram,
 };
 
+extern crate alloc;
+use alloc::boxed::Box;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepOutcome {
+    // CPU cycles (T-states) run
+    pub cycles: u8,
+    // A frame was completed during the step
+    pub frame: bool,
+}
+
+// Why a run (see System::run_cycles...) returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    // Ran for the requested time
+    Done,
+    // VBlank started, the frame can be read with swap_screen
+    Frame,
+    // PC reached a breakpoint, the instruction there is not run yet
+    Breakpoint(u16),
+    // The last instruction accessed a watched address
+    Watchpoint(WatchHit),
+    // An interrupt (IF bit) was dispatched, PC is at its handler
+    Interrupt(u8),
+    // The opcode at PC is watched and not run yet
+    Opcode(u8),
+    // CPU hung by an illegal opcode
+    LockUp(LockUp),
+}
+
 pub struct System<C: Cartridge> {
     cpu: cpu::Cpu,
     gpu: gpu::GPU,
     mmu: MMU<C>,
     joypad: Joypad,
     timers: Timers,
+    serial: Serial,
     oam_manager: OamDmaManager,
+
+    // Power-on configuration, kept for reset
+    model: Model,
+    color_mode: ColorMode,
+    boot_rom: Option<BootRom>,
+    compat_palette: Option<CompatPalette>,
+    boot_keys: u8,
+
+    lock_up_hook: Option<Box<dyn FnMut(LockUp)>>,
+    // Error that ended the last tick, see take_error
+    tick_error: Option<CoreError>,
+
+    // T-cycles since power on
+    cycles: u64,
+    debugger: Debugger,
 }
 
 impl<C: Cartridge> System<C> {
-    pub fn new(mut cartridge: C) -> Self {
-        let color_mode = cartridge.read(0x0143) == 0x80 || cartridge.read(0x0143) == 0xC0;
+    pub fn new(cartridge: C) -> Self {
+        SystemBuilder::new(cartridge).build()
+    }
+
+    // Run until the next frame. An error ends the frame early and is kept
+    // for take_error, try_tick returns it instead.
+    pub fn tick(&mut self, screen: &mut Screen, keys: &u8) {
+        // use std::time::Instant;
+        // let now = Instant::now();
+        self.joypad.handle_keys(&mut self.mmu, keys);
+
+        // A locked up CPU leaves the screen running, like on hardware
+        loop {
+            match self.run_step() {
+                Ok(step) if step.frame => break,
+                Ok(_) => {}
+                Err(e) => {
+                    self.tick_error = Some(e);
+                    break;
+                }
+            }
+        }
+        self.gpu.swap_screen(screen);
+        // let elapsed = now.elapsed();
+        // println!("Elapsed: {:.2?}", elapsed);
+    }
+
+    // Error that ended a tick early since the last call
+    pub fn take_error(&mut self) -> Option<CoreError> {
+        self.tick_error.take()
+    }
+
+    // Run until the next frame. The screen is not updated on error, the
+    // system can be reset to recover.
+    pub fn try_tick(&mut self, screen: &mut Screen, keys: &u8) -> Result<(), CoreError> {
+        self.joypad.handle_keys(&mut self.mmu, keys);
+        while !self.step()?.frame {}
+        self.gpu.swap_screen(screen);
+        Ok(())
+    }
+
+    // Run a single instruction (or interrupt dispatch, or halted M-cycle)
+    // and the rest of the system for the same time
+    pub fn step(&mut self) -> Result<StepOutcome, CoreError> {
+        if let Some(lock_up) = self.cpu.lock_up() {
+            return Err(CoreError::CpuLockUp(lock_up));
+        }
+        self.run_step()
+    }
+
+    fn run_step(&mut self) -> Result<StepOutcome, CoreError> {
+        self.joypad.tick(&mut self.mmu);
+        let mut bus = Bus::new(
+            &mut self.mmu,
+            &mut self.gpu,
+            &mut self.timers,
+            &mut self.serial,
+            &mut self.oam_manager,
+            &mut self.debugger.watchpoints,
+        );
+        let locked = self.cpu.lock_up().is_some();
+        let cycles = self.cpu.tick(&mut bus);
+        let frame = match self.cpu.stopped() {
+            true => bus.idle(cycles)?,
+            false => bus.finish(cycles)?,
+        };
+        if let (false, Some(lock_up)) = (locked, self.cpu.lock_up())
+            && let Some(hook) = &mut self.lock_up_hook
+        {
+            hook(lock_up);
+        }
+        self.cycles += cycles as u64;
+        Ok(StepOutcome { cycles, frame })
+    }
+
+    // Keys held from now on (KEY_A | KEY_START...), for the run_* methods
+    pub fn set_keys(&mut self, keys: u8) {
+        self.joypad.handle_keys(&mut self.mmu, &keys);
+    }
+
+    // Latest complete frame
+    pub fn swap_screen(&mut self, screen: &mut Screen) {
+        self.gpu.swap_screen(screen);
+    }
+
+    // T-cycles run since power on
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // Instruction at `addr`, as seen by the CPU
+    pub fn disassemble(&mut self, addr: u16) -> Instruction {
+        disasm::decode(&mut self.mmu, addr)
+    }
+
+    // Memory as seen by the CPU, without triggering watchpoints
+    pub fn peek(&mut self, addr: u16) -> u8 {
+        self.mmu.read(addr)
+    }
+
+    pub fn poke(&mut self, addr: u16, value: u8) {
+        self.mmu.write(addr, value);
+    }
+
+    pub fn set_breakpoint(&mut self, addr: u16) {
+        self.add_breakpoint(Breakpoint::new(addr));
+    }
+
+    // Every breakpoint at `addr`, whatever its bank and condition
+    pub fn clear_breakpoint(&mut self, addr: u16) {
+        self.debugger.breakpoints.retain(|b| b.addr != addr);
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.debugger.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        (index < self.debugger.breakpoints.len()).then(|| self.debugger.breakpoints.remove(index))
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.debugger.breakpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.debugger.watchpoints.list.push(watchpoint);
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) -> Option<Watchpoint> {
+        let list = &mut self.debugger.watchpoints.list;
+        (index < list.len()).then(|| list.remove(index))
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.debugger.watchpoints.list
+    }
+
+    // Stop once one of the interrupts in `mask` (IF bits) is dispatched
+    pub fn set_interrupt_breaks(&mut self, mask: u8) {
+        self.debugger.interrupts = mask & 0x1F;
+    }
+
+    pub fn interrupt_breaks(&self) -> u8 {
+        self.debugger.interrupts
+    }
+
+    // Stop before `opcode` runs (CB-prefixed opcodes are seen as 0xCB)
+    pub fn break_on_opcode(&mut self, opcode: u8) {
+        self.debugger.opcodes.insert(opcode);
+    }
+
+    pub fn clear_opcode_break(&mut self, opcode: u8) {
+        self.debugger.opcodes.remove(&opcode);
+    }
+
+    pub fn opcode_breaks(&self) -> impl Iterator<Item = u8> + '_ {
+        self.debugger.opcodes.iter().copied()
+    }
+
+    // Exactly one instruction (or interrupt dispatch), breakpoints are
+    // ignored. The M-cycles spent halted or stopped before it are run too,
+    // up to the end of the frame.
+    pub fn step_instruction(&mut self) -> Result<RunOutcome, CoreError> {
+        self.debugger.resume = None;
+        let mut frame = false;
+        loop {
+            if let Some(lock_up) = self.cpu.lock_up() {
+                return Ok(RunOutcome::LockUp(lock_up));
+            }
+            let idle = self.cpu.next_tick(&mut self.mmu) == NextTick::Idle;
+            self.debugger.watchpoints.hit = None;
+            frame |= self.run_step()?.frame;
+            if let Some(outcome) = self.break_after_step() {
+                return Ok(outcome);
+            }
+            if !idle || frame {
+                break;
+            }
+        }
+        match frame {
+            true => Ok(RunOutcome::Frame),
+            false => Ok(RunOutcome::Done),
+        }
+    }
+
+    // Until LY changes (456 dots, or the same time with the LCD off)
+    pub fn run_scanline(&mut self) -> Result<RunOutcome, CoreError> {
+        let ly = self.mmu.lcd.ff44_ly;
+        let length = 456 << self.mmu.speed.double_speed as u64;
+        let end = self.cycles + length;
+        self.run_until(|system, step| match step.frame {
+            true => Some(RunOutcome::Frame),
+            false if system.mmu.lcd.ff44_ly != ly || system.cycles >= end => Some(RunOutcome::Done),
+            false => None,
+        })
+    }
+
+    // At least `cycles` T-cycles, whole instructions are run. Frames do not
+    // stop the run.
+    pub fn run_cycles(&mut self, cycles: u64) -> Result<RunOutcome, CoreError> {
+        if cycles == 0 {
+            return Ok(RunOutcome::Done);
+        }
+        let end = self.cycles + cycles;
+        self.run_until(|system, _| (system.cycles >= end).then_some(RunOutcome::Done))
+    }
+
+    pub fn run_until_vblank(&mut self) -> Result<RunOutcome, CoreError> {
+        self.run_until(|_, step| step.frame.then_some(RunOutcome::Frame))
+    }
+
+    // Step until `done` returns an outcome. Breakpoints and opcodes are
+    // checked before each step, except the one the previous run stopped at
+    // so that it can resume from there. Watchpoints and interrupts are
+    // checked after each step.
+    fn run_until(
+        &mut self,
+        mut done: impl FnMut(&Self, &StepOutcome) -> Option<RunOutcome>,
+    ) -> Result<RunOutcome, CoreError> {
+        let mut resume = self.debugger.resume.take();
+        loop {
+            if let Some(lock_up) = self.cpu.lock_up() {
+                return Ok(RunOutcome::LockUp(lock_up));
+            }
+            let pc = self.cpu.regs().pc();
+            if resume.take() != Some(pc)
+                && let Some(outcome) = self.break_before_step()
+            {
+                self.debugger.resume = Some(pc);
+                return Ok(outcome);
+            }
+
+            self.debugger.watchpoints.hit = None;
+            let step = self.run_step()?;
+            if let Some(outcome) = self.break_after_step() {
+                return Ok(outcome);
+            }
+            if let Some(outcome) = done(self, &step) {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    fn break_after_step(&mut self) -> Option<RunOutcome> {
+        if let Some(lock_up) = self.cpu.lock_up() {
+            return Some(RunOutcome::LockUp(lock_up));
+        }
+        if let Some(hit) = self.debugger.watchpoints.hit.take() {
+            return Some(RunOutcome::Watchpoint(hit));
+        }
+        self.cpu
+            .dispatched()
+            .filter(|n| self.debugger.interrupts & (1 << n) != 0)
+            .map(RunOutcome::Interrupt)
+    }
+
+    // Only before the instruction at PC runs, not while halted or before
+    // an interrupt dispatch
+    fn break_before_step(&mut self) -> Option<RunOutcome> {
+        if self.debugger.breakpoints.is_empty() && self.debugger.opcodes.is_empty()
+            || self.cpu.next_tick(&mut self.mmu) != NextTick::Instruction
+        {
+            return None;
+        }
+        let regs = self.cpu.regs();
+        if self.debugger.breakpoint(regs, &self.mmu.cartridge) {
+            return Some(RunOutcome::Breakpoint(regs.pc()));
+        }
+        if self.debugger.opcodes.is_empty() {
+            return None;
+        }
+        let opcode = self.peek(self.cpu.regs().pc());
+        self.debugger
+            .opcodes
+            .contains(&opcode)
+            .then_some(RunOutcome::Opcode(opcode))
+    }
+
+    // Power cycle: everything but the cartridge goes back to its initial
+    // state, the boot ROM runs again if there is one
+    pub fn reset(&mut self) {
+        let trace = self.cpu.trace.take();
+        self.cpu = cpu::Cpu::default();
+        self.cpu.trace = trace;
+        self.gpu = gpu::GPU::default();
+        self.mmu.reset(self.boot_rom.clone(), self.color_mode);
+        self.joypad = Joypad::default();
+        self.timers = Timers::default();
+        self.serial = Serial::default();
+        self.oam_manager = OamDmaManager::default();
+        self.debugger.resume = None;
+        self.tick_error = None;
+        self.cycles = 0;
+        self.power_on();
+    }
+
+    fn power_on(&mut self) {
+        if self.boot_rom.is_some() {
+            return;
+        }
+        let cgb_mode = self.color_mode == ColorMode::Color;
+        self.cpu.reset(&mut self.mmu, self.model, cgb_mode);
+        if self.color_mode == ColorMode::Compat {
+            let palette = self
+                .compat_palette
+                .unwrap_or_else(|| CompatPalette::at_boot(&mut self.mmu, self.boot_keys));
+            self.mmu.colors.load_compat_palette(&palette);
+        }
+    }
+
+    // CPU hung by an illegal opcode, see SystemBuilder::on_lock_up
+    pub fn lock_up(&self) -> Option<LockUp> {
+        self.cpu.lock_up()
+    }
+
+    pub fn cartridge(&self) -> &C {
+        &self.mmu.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut C {
+        &mut self.mmu.cartridge
+    }
+
+    pub fn registers(&self) -> &Registers {
+        self.cpu.regs()
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        self.cpu.regs_mut()
+    }
+
+    // Next byte sent over the link port (nothing is plugged in)
+    pub fn read_serial(&mut self) -> Option<u8> {
+        self.serial.read_output()
+    }
+}
+
+pub struct SystemBuilder<C: Cartridge> {
+    cartridge: C,
+    boot_rom: Option<BootRom>,
+    model: Option<Model>,
+    compat_palette: Option<CompatPalette>,
+    boot_keys: u8,
+    lock_up_hook: Option<Box<dyn FnMut(LockUp)>>,
+    trace: Option<Trace>,
+    stub_ly: bool,
+}
+
+impl<C: Cartridge> SystemBuilder<C> {
+    pub fn new(cartridge: C) -> Self {
+        Self {
+            cartridge,
+            boot_rom: None,
+            model: None,
+            compat_palette: None,
+            boot_keys: 0,
+            lock_up_hook: None,
+            trace: None,
+            stub_ly: false,
+        }
+    }
+
+    // Console to emulate. Defaults to CGB for games supporting it and DMG
+    // otherwise. A DMG game on a CGB model runs in DMG compatibility mode.
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    // Run the boot ROM from 0x0000 instead of starting at 0x0100
+    // with the registers already initialized.
+    pub fn boot_rom(mut self, boot_rom: BootRom) -> Self {
+        self.boot_rom = Some(boot_rom);
+        self
+    }
+
+    // Colors of a DMG game on a CGB model, instead of the one picked from
+    // the cartridge header. Without boot ROM only, which picks its own.
+    pub fn compat_palette(mut self, palette: CompatPalette) -> Self {
+        self.compat_palette = Some(palette);
+        self
+    }
+
+    // Keys held while the boot logo shows (KEY_* flags). On a CGB model, a
+    // direction with A or B picks the colors of a DMG game. Without boot ROM
+    // only, which reads the joypad itself.
+    pub fn boot_keys(mut self, keys: u8) -> Self {
+        self.boot_keys = keys;
+        self
+    }
+
+    // Called when the CPU locks up on an illegal opcode
+    pub fn on_lock_up(mut self, hook: impl FnMut(LockUp) + 'static) -> Self {
+        self.lock_up_hook = Some(Box::new(hook));
+        self
+    }
+
+    // Write a line to `sink` before each instruction, with the registers
+    // and the 4 bytes at PC. Interrupt dispatch and HALT are not logged.
+    pub fn trace(mut self, sink: impl core::fmt::Write + 'static, format: TraceFormat) -> Self {
+        self.trace = Some(Trace::new(Box::new(sink), format));
+        self
+    }
+
+    // LY always reads 0x90, as expected by Gameboy Doctor logs
+    pub fn stub_ly(mut self, stub: bool) -> Self {
+        self.stub_ly = stub;
+        self
+    }
+
+    pub fn build(self) -> System<C> {
+        let mut cartridge = self.cartridge;
+        let cgb_game = CgbFlag::from(cartridge.read(0x0143)).supports_cgb();
+        let model = self.model.unwrap_or(match cgb_game {
+            true => Model::Cgb,
+            false => Model::Dmg,
+        });
+        let cgb_boot = self.boot_rom.as_ref().is_some_and(|b| b.is_cgb());
+        // A CGB boot ROM starts in color mode and switches to compat through KEY0
+        let color_mode = match (model.is_cgb(), cgb_game || cgb_boot) {
+            (true, true) => ColorMode::Color,
+            (true, false) => ColorMode::Compat,
+            (false, _) => ColorMode::Mono,
+        };
 
         let mut cpu = cpu::Cpu::default();
-        let gpu = gpu::GPU::new(color_mode);
+        cpu.trace = self.trace;
+        let gpu = gpu::GPU::default();
         let hram = hram::HRAM::default();
         let wram = wram::WRAM::default();
         let unusable = unusable::Unusable::default();
         let vram = vram::VRAM::default();
         let oam = oam::OAM::default();
-        let mut mmu = MMU::new(cartridge, hram, wram, unusable, vram, oam);
+        let mut mmu = MMU::new(
+            cartridge,
+            hram,
+            wram,
+            unusable,
+            vram,
+            oam,
+            self.boot_rom.clone(),
+            color_mode,
+        );
+        mmu.ly_stub = self.stub_ly.then_some(0x90);
         let joypad = Joypad::default();
         let timers = Timers::default();
+        let serial = Serial::default();
         let oam_manager = OamDmaManager::default();
-        cpu.reset(&mut mmu);
 
-        Self {
+        let mut system = System {
             cpu,
             gpu,
             mmu,
             joypad,
             timers,
+            serial,
             oam_manager,
-        }
-    }
-
-    pub fn tick(&mut self, screen: &mut Screen, keys: &u8) {
-        let mut done = false;
-        // use std::time::Instant;
-        // let now = Instant::now();
-        self.joypad.handle_keys(&mut self.mmu, keys);
-
-        while !done {
-            self.joypad.tick(&mut self.mmu);
-            let ticks = self.cpu.tick(&mut self.mmu);
-
-            match self.gpu.tick(&mut self.mmu, ticks) {
-                State::Default => {}
-                State::Frame => done |= true,
-            };
-            self.timers.tick(&mut self.mmu, ticks);
-            self.oam_manager.tick(&mut self.mmu);
-        }
-        self.gpu.swap_screen(screen);
-        // let elapsed = now.elapsed();
-        // println!("Elapsed: {:.2?}", elapsed);
+            model,
+            color_mode,
+            boot_rom: self.boot_rom,
+            compat_palette: self.compat_palette,
+            boot_keys: self.boot_keys,
+            lock_up_hook: self.lock_up_hook,
+            tick_error: None,
+            cycles: 0,
+            debugger: Debugger::default(),
+        };
+        system.power_on();
+        system
     }
 }
 
@@ -120,3 +597,298 @@ impl Joypad {
         JoypadMemory::write(mmu, res);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::DynCartridge;
+    extern crate alloc;
+    use alloc::rc::Rc;
+    use alloc::vec;
+    use core::cell::Cell;
+
+    // 32 KiB ROM only cartridge running `code` from 0x0100, NOPs after it
+    fn cartridge(code: &[u8], cgb: bool) -> DynCartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x100 + code.len()].copy_from_slice(code);
+        if cgb {
+            rom[0x143] = 0x80;
+        }
+        DynCartridge::new(rom).unwrap()
+    }
+
+    fn system(code: &[u8], cgb: bool) -> System<DynCartridge> {
+        System::new(cartridge(code, cgb))
+    }
+
+    // CGB boot ROM writing `key0` to KEY0 then unmapping itself
+    fn cgb_boot(cgb_game: bool, key0: u8) -> System<DynCartridge> {
+        let mut boot = vec![0; 0x900];
+        // LD A,key0; LDH (KEY0),A; LD A,$01; LDH (BANK),A
+        boot[..8].copy_from_slice(&[0x3E, key0, 0xE0, 0x4C, 0x3E, 0x01, 0xE0, 0x50]);
+        let mut rom = vec![0; 0x8000];
+        if cgb_game {
+            rom[0x143] = 0x80;
+        }
+        let mut sys = SystemBuilder::new(DynCartridge::new(rom).unwrap())
+            .model(Model::Cgb)
+            .boot_rom(BootRom::new(&boot).unwrap())
+            .build();
+        for _ in 0..4 {
+            sys.step().unwrap();
+        }
+        sys
+    }
+
+    #[test]
+    fn boot_rom_selects_the_color_mode() {
+        assert_eq!(cgb_boot(false, 0x04).mmu.color_mode, ColorMode::Compat);
+        assert_eq!(cgb_boot(true, 0x80).mmu.color_mode, ColorMode::Color);
+
+        // KEY0 is locked after the boot
+        let mut sys = cgb_boot(true, 0x80);
+        sys.poke(0xFF4C, 0x04);
+        assert_eq!(sys.peek(0xFF4C), 0x80);
+    }
+
+    #[test]
+    fn speed_switch_pauses_the_cpu() {
+        // LD A,$01; LDH (KEY1),A; STOP
+        let mut sys = system(&[0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00], true);
+        while sys.registers().pc() != 0x0106 {
+            sys.step().unwrap();
+        }
+        assert_eq!(sys.peek(0xFF4D) & 0x81, 0x80);
+        let start = sys.cycles();
+        while sys.registers().pc() == 0x0106 {
+            sys.step().unwrap();
+        }
+        // The pause, then the NOP
+        assert_eq!(sys.cycles() - start, 2050 * 4 + 4);
+    }
+
+    #[test]
+    fn stop_holds_interrupts_and_blanks_the_lcd() {
+        // LD A,$04; LDH (IE),A; LDH (IF),A; EI; STOP
+        let mut sys = system(
+            &[0x3E, 0x04, 0xE0, 0xFF, 0xE0, 0x0F, 0xFB, 0x10, 0x00],
+            false,
+        );
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+        assert_eq!(sys.run_until_vblank().unwrap(), RunOutcome::Frame);
+        assert_eq!(sys.registers().pc(), 0x0109);
+        let mut screen = Screen {
+            frame_buffer: [0; crate::FRAME_BUFFER_SIZE],
+        };
+        sys.swap_screen(&mut screen);
+        assert!(screen.frame_buffer.iter().all(|&c| c == 0xFF));
+
+        // A key wakes the CPU up, the timer interrupt follows
+        sys.poke(0xFF00, 0x10);
+        sys.set_keys(crate::KEY_A);
+        sys.step().unwrap();
+        assert_eq!(sys.registers().pc(), 0x0050);
+    }
+
+    // Step until PC reaches `pc`, returns the last step
+    fn run_to(sys: &mut System<DynCartridge>, pc: u16) -> StepOutcome {
+        for _ in 0..0x10000 {
+            let step = sys.step().unwrap();
+            if sys.registers().pc() == pc {
+                return step;
+            }
+        }
+        panic!("PC never reached {:#06x}", pc);
+    }
+
+    // Return address pushed by the last call or interrupt
+    fn pushed_pc(sys: &mut System<DynCartridge>) -> u16 {
+        let sp = sys.registers().sp();
+        u16::from_le_bytes([sys.peek(sp), sys.peek(sp.wrapping_add(1))])
+    }
+
+    #[test]
+    fn ei_takes_effect_after_the_next_instruction() {
+        // LD A,$04; LDH (IE),A; LDH (IF),A; EI; NOP; NOP
+        let code = [0x3E, 0x04, 0xE0, 0xFF, 0xE0, 0x0F, 0xFB, 0x00, 0x00];
+        let mut sys = system(&code, false);
+        run_to(&mut sys, 0x0107);
+        sys.step().unwrap();
+        assert_eq!(sys.registers().pc(), 0x0108);
+        // 5 M-cycles dispatch
+        assert_eq!(sys.step().unwrap().cycles, 20);
+        assert_eq!(sys.registers().pc(), 0x0050);
+        assert_eq!(pushed_pc(&mut sys), 0x0108);
+
+        // EI; DI: no interrupt in between
+        let mut code = code;
+        code[7] = 0xF3;
+        let mut sys = system(&code, false);
+        run_to(&mut sys, 0x0108);
+        sys.step().unwrap();
+        assert_eq!(sys.registers().pc(), 0x0109);
+    }
+
+    #[test]
+    fn halt_bug_reads_the_next_byte_twice() {
+        // LD A,$04; LDH (IE),A; LDH (IF),A; XOR A; HALT; INC A
+        let mut sys = system(
+            &[0x3E, 0x04, 0xE0, 0xFF, 0xE0, 0x0F, 0xAF, 0x76, 0x3C],
+            false,
+        );
+        run_to(&mut sys, 0x0108);
+        sys.step().unwrap();
+        assert_eq!((sys.registers().pc(), sys.registers().a()), (0x0108, 0x01));
+        sys.step().unwrap();
+        assert_eq!((sys.registers().pc(), sys.registers().a()), (0x0109, 0x02));
+    }
+
+    #[test]
+    fn halt_exits_without_dispatch_when_ime_is_off() {
+        // LD A,$04; LDH (IE),A; LD A,$05; LDH (TAC),A; HALT; NOP
+        let mut sys = system(
+            &[0x3E, 0x04, 0xE0, 0xFF, 0x3E, 0x05, 0xE0, 0x07, 0x76],
+            false,
+        );
+        run_to(&mut sys, 0x0109);
+        // Halted until the timer overflows, then the NOP runs
+        let start = sys.cycles();
+        run_to(&mut sys, 0x010A);
+        assert!(sys.cycles() - start >= 4096 - 64);
+        assert_eq!(sys.peek(0xFF0F) & 0x04, 0x04);
+
+        // Dispatched with IME on, one more M-cycle to wake up
+        let mut sys = system(
+            &[0x3E, 0x04, 0xE0, 0xFF, 0x3E, 0x05, 0xE0, 0x07, 0xFB, 0x76],
+            false,
+        );
+        run_to(&mut sys, 0x010A);
+        assert_eq!(run_to(&mut sys, 0x0050).cycles, 24);
+        assert_eq!(pushed_pc(&mut sys), 0x010A);
+    }
+
+    #[test]
+    fn ie_write_during_dispatch_cancels_the_interrupt() {
+        // LD SP,$0000; LD A,$04; LDH (IE),A; LDH (IF),A; EI; NOP
+        let code = [
+            0x31, 0x00, 0x00, 0x3E, 0x04, 0xE0, 0xFF, 0xE0, 0x0F, 0xFB, 0x00,
+        ];
+        let mut sys = system(&code, false);
+        run_to(&mut sys, 0x010B);
+        // PC high byte pushed to IE (FFFF) disables the timer interrupt
+        sys.step().unwrap();
+        assert_eq!(sys.registers().pc(), 0x0000);
+        assert_eq!(sys.peek(0xFFFF), 0x01);
+        assert_eq!(sys.peek(0xFF0F) & 0x04, 0x04);
+    }
+
+    #[test]
+    fn reads_see_the_timer_of_their_m_cycle() {
+        // NOP; LDH A,(TIMA); LD B,A; LDH A,(TIMA)
+        let mut sys = system(&[0x00, 0xF0, 0x05, 0x47, 0xF0, 0x05], false);
+        // TIMA increments every 4 M-cycles from now
+        sys.timers = Timers::default();
+        sys.poke(0xFF07, 0x05);
+        sys.poke(0xFF05, 0x00);
+        // Both reads are on the last M-cycle of the LDH, the 4th and the
+        // 8th since the timer started: they see the increment of that cycle
+        run_to(&mut sys, 0x0106);
+        assert_eq!((sys.registers().b(), sys.registers().a()), (0x01, 0x02));
+    }
+
+    #[test]
+    fn oam_dma_copies_a_byte_per_m_cycle() {
+        // LD A,$C0; LDH (DMA),A; NOPs
+        let mut sys = system(&[0x3E, 0xC0, 0xE0, 0x46], false);
+        for i in 0..0xA0 {
+            sys.poke(0xC000 + i, i as u8 + 1);
+        }
+        run_to(&mut sys, 0x0104);
+        // Starts on the M-cycle after the write
+        sys.step().unwrap();
+        assert_eq!(sys.peek(0xFE00), 0x00);
+        for copied in 1..=0xA0 {
+            sys.step().unwrap();
+            assert_eq!(sys.peek(0xFE00 + copied - 1), copied as u8);
+            if copied < 0xA0 {
+                assert_eq!(sys.peek(0xFE00 + copied), 0x00);
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_opcodes_lock_up_until_reset() {
+        for opcode in [0xD3, 0xFD] {
+            let hooked = Rc::new(Cell::new(None));
+            let hook = hooked.clone();
+            // NOP; illegal opcode
+            let mut sys = SystemBuilder::new(cartridge(&[0x00, opcode], false))
+                .on_lock_up(move |lock_up| hook.set(Some(lock_up)))
+                .build();
+            let lock_up = LockUp { opcode, pc: 0x0101 };
+            sys.step().unwrap();
+            sys.step().unwrap();
+            assert_eq!(sys.lock_up(), Some(lock_up));
+            assert_eq!(hooked.take(), Some(lock_up));
+
+            let pc = sys.registers().pc();
+            for _ in 0..3 {
+                assert!(matches!(sys.step(), Err(CoreError::CpuLockUp(l)) if l == lock_up));
+                let mut screen = Screen::default();
+                assert!(matches!(
+                    sys.try_tick(&mut screen, &0),
+                    Err(CoreError::CpuLockUp(l)) if l == lock_up
+                ));
+            }
+            assert_eq!(sys.registers().pc(), pc);
+            // Reported once
+            assert_eq!(hooked.take(), None);
+
+            sys.reset();
+            assert_eq!(sys.lock_up(), None);
+            assert_eq!(sys.registers().pc(), 0x0100);
+            sys.step().unwrap();
+            assert_eq!(sys.registers().pc(), 0x0101);
+        }
+    }
+
+    // Out of range LY at the end of the next HBlank
+    fn corrupt_ly(sys: &mut System<DynCartridge>) {
+        while sys.peek(0xFF41) & 0x03 != 0 {
+            sys.step().unwrap();
+        }
+        sys.mmu.lcd.ff44_ly = 150;
+    }
+
+    #[test]
+    fn gpu_errors_are_reported() {
+        let mut sys = system(&[], false);
+        corrupt_ly(&mut sys);
+        let error = loop {
+            if let Err(e) = sys.step() {
+                break e;
+            }
+        };
+        assert!(matches!(error, CoreError::UnknownGPULY(151)));
+
+        let mut screen = Screen::default();
+        let mut sys = system(&[], false);
+        corrupt_ly(&mut sys);
+        assert!(matches!(
+            sys.try_tick(&mut screen, &0),
+            Err(CoreError::UnknownGPULY(151))
+        ));
+
+        // tick keeps the error until it is taken
+        let mut sys = system(&[], false);
+        sys.tick(&mut screen, &0);
+        assert!(sys.take_error().is_none());
+        corrupt_ly(&mut sys);
+        sys.tick(&mut screen, &0);
+        assert!(matches!(
+            sys.take_error(),
+            Some(CoreError::UnknownGPULY(151))
+        ));
+        assert!(sys.take_error().is_none());
+    }
+}
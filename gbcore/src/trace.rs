@@ -0,0 +1,103 @@
+use crate::{Memory, Registers, disasm};
+use core::fmt::{self, Write};
+
+extern crate alloc;
+use alloc::boxed::Box;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    // Gameboy Doctor:
+    // A:00 F:11 B:22 C:33 D:44 E:55 H:66 L:77 SP:8888 PC:9999 PCMEM:AA,BB,CC,DD
+    #[default]
+    Doctor,
+    // Gameboy Doctor line followed by the disassembled instruction
+    Disassembly,
+}
+
+// One line per instruction, written before it runs
+pub(crate) struct Trace {
+    sink: Box<dyn Write>,
+    format: TraceFormat,
+}
+
+impl Trace {
+    pub fn new(sink: Box<dyn Write>, format: TraceFormat) -> Self {
+        Self { sink, format }
+    }
+
+    pub fn log(&mut self, regs: &Registers, mem: &mut impl Memory) {
+        // The sink can not report errors to the CPU, a failed line is lost
+        let _ = self.write_line(regs, mem);
+    }
+
+    fn write_line(&mut self, regs: &Registers, mem: &mut impl Memory) -> fmt::Result {
+        let pc = regs.pc();
+        let pcmem = [0, 1, 2, 3].map(|i| mem.read(pc.wrapping_add(i)));
+        write!(
+            self.sink,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            regs.a(),
+            regs.af() & 0x00FF,
+            regs.b(),
+            regs.c(),
+            regs.d(),
+            regs.e(),
+            regs.h(),
+            regs.l(),
+            regs.sp(),
+            pc,
+            pcmem[0],
+            pcmem[1],
+            pcmem[2],
+            pcmem[3],
+        )?;
+        if self.format == TraceFormat::Disassembly {
+            write!(self.sink, " {}", disasm::decode(mem, pc))?;
+        }
+        writeln!(self.sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SystemBuilder, cartridge::DynCartridge};
+    use alloc::rc::Rc;
+    use alloc::string::String;
+    use alloc::vec;
+    use core::cell::RefCell;
+
+    // Sink still readable once given to the system
+    #[derive(Clone, Default)]
+    struct SharedString(Rc<RefCell<String>>);
+
+    impl Write for SharedString {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.borrow_mut().push_str(s);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn doctor_trace() {
+        // LD A,$12; LDH A,(LY); NOP
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x104].copy_from_slice(&[0x3E, 0x12, 0xF0, 0x44]);
+        let sink = SharedString::default();
+        let mut sys = SystemBuilder::new(DynCartridge::new(rom).unwrap())
+            .trace(sink.clone(), TraceFormat::Doctor)
+            .stub_ly(true)
+            .build();
+        for _ in 0..3 {
+            sys.step().unwrap();
+        }
+        // LY reads 0x90, whatever the line being drawn
+        assert_eq!(sys.peek(0xFF44), 0x90);
+        assert_eq!(
+            *sink.0.borrow(),
+            "A:01 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:3E,12,F0,44\n\
+             A:12 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0102 PCMEM:F0,44,00,00\n\
+             A:90 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0104 PCMEM:00,00,00,00\n"
+        );
+    }
+}
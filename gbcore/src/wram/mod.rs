@@ -2,32 +2,44 @@ use crate::Memory;
 
 pub struct WRAM {
     wram: [u8; 0xCFFF - 0xC000 + 1],
-    sram: [u8; 0xDFFF - 0xD000 + 1],
+    // Bank 1 in DMG mode, banks 1-7 in CGB mode
+    sram: [[u8; 0xDFFF - 0xD000 + 1]; 7],
+
+    // FF70 - SVBK - CGB Mode Only - WRAM Bank
+    pub ff70_svbk: u8,
 }
 
 impl Default for WRAM {
     fn default() -> Self {
         Self {
             wram: [0; 0xCFFF - 0xC000 + 1],
-            sram: [0; 0xDFFF - 0xD000 + 1],
+            sram: [[0; 0xDFFF - 0xD000 + 1]; 7],
+            ff70_svbk: 0,
         }
     }
 }
 
+impl WRAM {
+    fn bank(&self) -> usize {
+        // Bank 0 selects bank 1
+        (self.ff70_svbk & 0x07).max(1) as usize - 1
+    }
+}
+
 impl Memory for WRAM {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
             0xC000..=0xCFFF => self.wram[(addr - 0xC000) as usize],
-            0xD000..=0xDFFF => self.sram[(addr - 0xD000) as usize],
-            _ => panic!("WRAM read out {:#04x}", addr),
+            0xD000..=0xDFFF => self.sram[self.bank()][(addr - 0xD000) as usize],
+            _ => 0xFF,
         }
     }
 
     fn write(&mut self, addr: u16, value: u8) {
         match addr {
             0xC000..=0xCFFF => self.wram[(addr - 0xC000) as usize] = value,
-            0xD000..=0xDFFF => self.sram[(addr - 0xD000) as usize] = value,
-            _ => panic!("WRAM write out {:#04x}", addr),
+            0xD000..=0xDFFF => self.sram[self.bank()][(addr - 0xD000) as usize] = value,
+            _ => {}
         }
     }
 }
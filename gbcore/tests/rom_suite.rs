@@ -0,0 +1,279 @@
+// Accuracy test ROM suite.
+//
+// Test ROMs are not distributed with the repository. Put them under
+// `gbcore/tests/roms` (or point GBCORE_TEST_ROMS to another directory):
+//
+//   blargg/cpu_instrs/individual/*.gb     result printed on the serial port
+//   blargg/instr_timing/instr_timing.gb
+//   blargg/mem_timing/individual/*.gb
+//   mooneye/acceptance/**/*.gb            result in the registers after `LD B, B`
+//   acid2/dmg-acid2.gb + dmg-acid2.png    screenshot compared to the reference
+//   acid2/cgb-acid2.gbc + cgb-acid2.png
+//
+// Every ROM found is run and reported. ROMs listed in EXPECTED are checked
+// against their expected status so that regressions are caught. The suite is
+// skipped when the directory does not exist.
+
+use gbcore::cartridge::DynCartridge;
+use gbcore::{Screen, System};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pass,
+    Fail,
+    // Never run against the core yet: reported, not checked
+    Unknown,
+}
+
+const EXPECTED: &[(&str, Status)] = &[
+    ("blargg/cpu_instrs/individual/01-special.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/02-interrupts.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/03-op sp,hl.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/04-op r,imm.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/05-op rp.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/06-ld r,r.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/07-jr,jp,call,ret,rst.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/08-misc instrs.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/09-op r,r.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/10-bit ops.gb", Status::Pass),
+    ("blargg/cpu_instrs/individual/11-op a,(hl).gb", Status::Pass),
+    ("blargg/instr_timing/instr_timing.gb", Status::Pass),
+    ("blargg/mem_timing/individual/01-read_timing.gb", Status::Pass),
+    ("blargg/mem_timing/individual/02-write_timing.gb", Status::Pass),
+    ("blargg/mem_timing/individual/03-modify_timing.gb", Status::Pass),
+    // Interrupt timing, also covered by the unit tests in system.rs
+    ("mooneye/acceptance/ei_sequence.gb", Status::Unknown),
+    ("mooneye/acceptance/ei_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/rapid_di_ei.gb", Status::Unknown),
+    ("mooneye/acceptance/halt_ime0_ei.gb", Status::Unknown),
+    ("mooneye/acceptance/halt_ime0_nointr_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/halt_ime1_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/if_ie_registers.gb", Status::Unknown),
+    ("mooneye/acceptance/intr_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/interrupts/ie_push.gb", Status::Unknown),
+    // Memory access timing within instructions, see also the unit tests in
+    // system.rs
+    ("mooneye/acceptance/add_sp_e_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/call_cc_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/call_cc_timing2.gb", Status::Unknown),
+    ("mooneye/acceptance/call_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/call_timing2.gb", Status::Unknown),
+    ("mooneye/acceptance/di_timing-GS.gb", Status::Unknown),
+    ("mooneye/acceptance/div_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/jp_cc_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/jp_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/ld_hl_sp_e_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/oam_dma_restart.gb", Status::Unknown),
+    ("mooneye/acceptance/oam_dma_start.gb", Status::Unknown),
+    ("mooneye/acceptance/oam_dma_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/pop_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/push_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/ret_cc_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/ret_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/reti_timing.gb", Status::Unknown),
+    ("mooneye/acceptance/rst_timing.gb", Status::Unknown),
+    ("acid2/dmg-acid2.gb", Status::Fail),
+    ("acid2/cgb-acid2.gbc", Status::Fail),
+];
+
+// Emulated frames before giving up on a ROM
+const BLARGG_FRAMES: usize = 60 * 60;
+const MOONEYE_FRAMES: usize = 60 * 10;
+const ACID2_FRAMES: usize = 60;
+
+// Values loaded in B, C, D, E, H, L by the Mooneye tests
+const MOONEYE_PASS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAIL: [u8; 6] = [0x42; 6];
+
+const GB_SCREEN_WIDTH: usize = 160;
+const GB_SCREEN_HEIGHT: usize = 144;
+
+fn roms_dir() -> PathBuf {
+    match std::env::var_os("GBCORE_TEST_ROMS") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms"),
+    }
+}
+
+fn find_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_roms(&path, roms);
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("gb") | Some("gbc")
+        ) {
+            roms.push(path);
+        }
+    }
+}
+
+fn run_rom(root: &Path, rom: &Path) -> Result<(), String> {
+    let name = rom_name(root, rom);
+    let data = fs::read(rom).map_err(|e| e.to_string())?;
+    let cart = DynCartridge::new(data).map_err(|e| e.to_string())?;
+    let mut sys = System::new(cart);
+    let mut screen = Screen::default();
+
+    match name.split('/').next() {
+        Some("blargg") => run_blargg(&mut sys, &mut screen),
+        Some("mooneye") => run_mooneye(&mut sys, &mut screen),
+        Some("acid2") => run_acid2(&mut sys, &mut screen, &rom.with_extension("png")),
+        _ => Err("unknown test suite".into()),
+    }
+}
+
+fn run_blargg(sys: &mut System<DynCartridge>, screen: &mut Screen) -> Result<(), String> {
+    let mut output = Vec::new();
+    for _ in 0..BLARGG_FRAMES {
+        sys.try_tick(screen, &0).map_err(|e| e.to_string())?;
+        while let Some(byte) = sys.read_serial() {
+            output.push(byte);
+        }
+        let text = String::from_utf8_lossy(&output);
+        if text.contains("Passed") {
+            return Ok(());
+        }
+        if text.contains("Failed") {
+            return Err(text.trim().replace('\n', " "));
+        }
+    }
+    Err("timeout".into())
+}
+
+fn run_mooneye(sys: &mut System<DynCartridge>, screen: &mut Screen) -> Result<(), String> {
+    for _ in 0..MOONEYE_FRAMES {
+        sys.try_tick(screen, &0).map_err(|e| e.to_string())?;
+        let regs = sys.registers();
+        let values = [regs.b(), regs.c(), regs.d(), regs.e(), regs.h(), regs.l()];
+        if values == MOONEYE_PASS {
+            return Ok(());
+        }
+        if values == MOONEYE_FAIL {
+            return Err("failure reported".into());
+        }
+    }
+    Err("timeout".into())
+}
+
+fn run_acid2(
+    sys: &mut System<DynCartridge>,
+    screen: &mut Screen,
+    reference: &Path,
+) -> Result<(), String> {
+    let expected = load_png(reference)?;
+    let cgb = reference.to_string_lossy().contains("cgb");
+    for _ in 0..ACID2_FRAMES {
+        sys.try_tick(screen, &0).map_err(|e| e.to_string())?;
+    }
+
+    let mut mismatches = 0;
+    for (pixel, expected) in screen
+        .frame_buffer
+        .chunks_exact(3)
+        .zip(expected.chunks_exact(3))
+    {
+        if !same_color(pixel, expected, cgb) {
+            mismatches += 1;
+        }
+    }
+    match mismatches {
+        0 => Ok(()),
+        n => Err(format!("{} pixels differ", n)),
+    }
+}
+
+// The core renders DMG shades and CGB colors with its own values, map them to
+// the ones used by the reference images.
+fn same_color(pixel: &[u8], expected: &[u8], cgb: bool) -> bool {
+    if cgb {
+        // Black is drawn as the "color zero" shade
+        if pixel == [0xED, 0xED, 0xED] {
+            return expected.iter().all(|c| c >> 3 == 0);
+        }
+        return pixel.iter().zip(expected).all(|(p, e)| p >> 3 == e >> 3);
+    }
+    let shade = match pixel[0] {
+        0xED => 0xFF,
+        0x99 => 0xAA,
+        0x66 => 0x55,
+        _ => 0x00,
+    };
+    expected.iter().all(|e| *e == shade)
+}
+
+fn load_png(path: &Path) -> Result<Vec<u8>, String> {
+    let file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    if info.width as usize != GB_SCREEN_WIDTH || info.height as usize != GB_SCREEN_HEIGHT {
+        return Err(format!("bad reference size {}x{}", info.width, info.height));
+    }
+
+    let rgb = buf[..info.buffer_size()]
+        .chunks_exact(info.color_type.samples())
+        .flat_map(|px| match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => [px[0], px[0], px[0]],
+            _ => [px[0], px[1], px[2]],
+        })
+        .collect();
+    Ok(rgb)
+}
+
+fn rom_name(root: &Path, rom: &Path) -> String {
+    rom.strip_prefix(root)
+        .unwrap_or(rom)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+#[test]
+fn accuracy_suite() {
+    let root = roms_dir();
+    let mut roms = Vec::new();
+    find_roms(&root, &mut roms);
+    if roms.is_empty() {
+        println!("no test ROMs found in {}, skipping", root.display());
+        return;
+    }
+    roms.sort();
+
+    let mut regressions = Vec::new();
+    for rom in &roms {
+        let name = rom_name(&root, rom);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run_rom(&root, rom)))
+            .unwrap_or_else(|_| Err("panicked".into()));
+        let status = match result {
+            Ok(()) => Status::Pass,
+            Err(_) => Status::Fail,
+        };
+        let expected = EXPECTED.iter().find(|(n, _)| *n == name).map(|(_, s)| *s);
+
+        let note = match (expected, status) {
+            (None, _) => " (not listed)",
+            (Some(Status::Unknown), _) => " (status unknown, update EXPECTED)",
+            (Some(Status::Fail), Status::Pass) => " (unexpected pass, update EXPECTED)",
+            (Some(Status::Pass), Status::Fail) => {
+                regressions.push(name.clone());
+                " (REGRESSION)"
+            }
+            _ => "",
+        };
+        match result {
+            Ok(()) => println!("PASS {}{}", name, note),
+            Err(e) => println!("FAIL {}: {}{}", name, e, note),
+        }
+    }
+
+    assert!(regressions.is_empty(), "regressions: {:?}", regressions);
+}
@@ -0,0 +1,189 @@
+// Per-opcode CPU conformance tests.
+//
+// Runs the SM83 single step test vectors (one JSON file per opcode, e.g.
+// `00.json` ... `ff.json` and `cb 00.json` ... `cb ff.json`) against the CPU
+// wired to a flat 64 KiB memory. Each vector gives the initial registers and
+// RAM, the expected final state, and the memory accesses made per M-cycle.
+// The reads and writes are compared in order, internal M-cycles only count
+// in the total.
+//
+// The vectors are not distributed with the repository. Put them under
+// `gbcore/tests/sm83` (or point GBCORE_SM83_TESTS to another directory).
+// The test is skipped when the directory does not exist.
+
+use gbcore::{Cpu, Memory};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Memory access made by the CPU
+#[derive(Debug, PartialEq)]
+enum Access {
+    Read(u16, u8),
+    Write(u16, u8),
+}
+
+struct FlatMemory {
+    mem: Vec<u8>,
+    accesses: Vec<Access>,
+}
+
+impl Default for FlatMemory {
+    fn default() -> Self {
+        Self {
+            mem: vec![0; 0x10000],
+            accesses: Vec::new(),
+        }
+    }
+}
+
+impl Memory for FlatMemory {
+    fn read(&mut self, addr: u16) -> u8 {
+        let value = self.mem[addr as usize];
+        self.accesses.push(Access::Read(addr, value));
+        value
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.accesses.push(Access::Write(addr, value));
+        self.mem[addr as usize] = value;
+    }
+}
+
+fn vectors_dir() -> PathBuf {
+    match std::env::var_os("GBCORE_SM83_TESTS") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83"),
+    }
+}
+
+fn field(state: &Value, name: &str) -> u16 {
+    state[name].as_u64().unwrap_or(0) as u16
+}
+
+fn load(cpu: &mut Cpu, mem: &mut FlatMemory, state: &Value) {
+    let regs = cpu.regs_mut();
+    regs.set_pc(field(state, "pc"));
+    regs.set_sp(field(state, "sp"));
+    regs.set_af((field(state, "a") << 8) | field(state, "f"));
+    regs.set_bc((field(state, "b") << 8) | field(state, "c"));
+    regs.set_de((field(state, "d") << 8) | field(state, "e"));
+    regs.set_hl((field(state, "h") << 8) | field(state, "l"));
+    cpu.set_ime(field(state, "ime") != 0);
+
+    for cell in state["ram"].as_array().into_iter().flatten() {
+        mem.mem[cell[0].as_u64().unwrap() as usize] = cell[1].as_u64().unwrap() as u8;
+    }
+}
+
+// `[addr, value, "r-m" | "-wm" | "---"]` per M-cycle, null when idle
+fn expected_accesses(cycles: &[Value]) -> Vec<Access> {
+    cycles
+        .iter()
+        .filter_map(|cycle| {
+            let addr = cycle[0].as_u64()? as u16;
+            let value = cycle[1].as_u64()? as u8;
+            match cycle[2].as_str()?.as_bytes() {
+                [b'r', ..] => Some(Access::Read(addr, value)),
+                [_, b'w', ..] => Some(Access::Write(addr, value)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn compare(cpu: &Cpu, mem: &FlatMemory, state: &Value) -> Result<(), String> {
+    let regs = cpu.regs();
+    let values = [
+        ("pc", regs.pc()),
+        ("sp", regs.sp()),
+        ("a", regs.af() >> 8),
+        ("f", regs.af() & 0xFF),
+        ("b", regs.b() as u16),
+        ("c", regs.c() as u16),
+        ("d", regs.d() as u16),
+        ("e", regs.e() as u16),
+        ("h", regs.h() as u16),
+        ("l", regs.l() as u16),
+    ];
+    for (name, value) in values {
+        let expected = field(state, name);
+        if value != expected {
+            return Err(format!("{}: {:#06x} expected {:#06x}", name, value, expected));
+        }
+    }
+    if state.get("ime").is_some() && cpu.ime() != (field(state, "ime") != 0) {
+        return Err(format!("ime: {} expected {}", cpu.ime(), !cpu.ime()));
+    }
+
+    for cell in state["ram"].as_array().into_iter().flatten() {
+        let addr = cell[0].as_u64().unwrap() as u16;
+        let expected = cell[1].as_u64().unwrap() as u8;
+        let value = mem.mem[addr as usize];
+        if value != expected {
+            return Err(format!(
+                "ram[{:#06x}]: {:#04x} expected {:#04x}",
+                addr, value, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run_vector(test: &Value) -> Result<(), String> {
+    let mut cpu = Cpu::default();
+    let mut mem = FlatMemory::default();
+    load(&mut cpu, &mut mem, &test["initial"]);
+
+    let cycles = cpu.step(&mut mem);
+
+    compare(&cpu, &mem, &test["final"])?;
+    let expected = test["cycles"].as_array().map(Vec::as_slice).unwrap_or_default();
+    if cycles as usize != expected.len() * 4 {
+        return Err(format!("cycles: {} expected {}", cycles, expected.len() * 4));
+    }
+    let expected = expected_accesses(expected);
+    if mem.accesses != expected {
+        return Err(format!("accesses: {:x?} expected {:x?}", mem.accesses, expected));
+    }
+    Ok(())
+}
+
+#[test]
+fn single_step_vectors() {
+    let dir = vectors_dir();
+    let Ok(entries) = fs::read_dir(&dir) else {
+        println!("no test vectors found in {}, skipping", dir.display());
+        return;
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    files.sort();
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let name = file.file_stem().unwrap().to_string_lossy().into_owned();
+        let json: Value = serde_json::from_slice(&fs::read(file).unwrap())
+            .unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+
+        let tests = json.as_array().map(Vec::as_slice).unwrap_or_default();
+        let mut failed = 0;
+        let mut first_error = None;
+        for test in tests {
+            if let Err(e) = run_vector(test) {
+                failed += 1;
+                first_error.get_or_insert_with(|| format!("{}: {}", test["name"], e));
+            }
+        }
+        if let Some(e) = first_error {
+            println!("FAIL {} ({}/{}) {}", name, failed, tests.len(), e);
+            failures.push(name);
+        }
+    }
+
+    println!("{} opcodes, {} failing", files.len(), failures.len());
+    assert!(failures.is_empty(), "failing opcodes: {:?}", failures);
+}
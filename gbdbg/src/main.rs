@@ -0,0 +1,364 @@
+use gbcore::cartridge::DynCartridge;
+use gbcore::debugger::{Access, Breakpoint, Compare, Condition, Register, Watchpoint};
+use gbcore::{BootRom, Model, RunOutcome, System, SystemBuilder};
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+Addresses and values are hexadecimal, counts are decimal.
+  s, step [n]                     run n instructions
+  f, frame [n]                    run until the nth next VBlank
+  c, continue                     run until something breaks
+  r, regs                         show the registers
+  x, mem <addr> [len]             dump memory
+  d, dis [addr] [n]               disassemble n instructions (from PC)
+  b, break <[bank:]addr> [if <reg> <op> <value>]
+                                  break at addr, e.g. b 02:4000 if a == 12
+  w, watch <addr[-end]> [r|w|rw]  break on accesses (writes by default)
+  bi <vblank|stat|timer|serial|joypad|none>...
+                                  break on dispatched interrupts
+  bo <opcode>                     break before an opcode runs
+  l, list                         list breakpoints and watchpoints
+  del <b|w|o> <n|opcode>          delete a breakpoint, watchpoint or opcode
+  reset                           power cycle the console
+  q, quit";
+
+const INTERRUPTS: [&str; 5] = ["vblank", "stat", "timer", "serial", "joypad"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(rom_file) = args.get(1) else {
+        eprintln!("usage: gbdbg path/to/rom.gb [--boot boot.bin] [--model dmg|cgb...]");
+        std::process::exit(1);
+    };
+    let rom_data = fs::read(rom_file).unwrap();
+    let cart = match DynCartridge::new(rom_data) {
+        Ok(cart) => cart,
+        Err(e) => {
+            eprintln!("{}: {}", rom_file, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut builder = SystemBuilder::new(cart);
+    if let Some(boot_file) = arg_value(&args, "--boot") {
+        let boot_data = fs::read(boot_file).unwrap();
+        builder = builder.boot_rom(BootRom::new(&boot_data).unwrap());
+    }
+    if let Some(model) = arg_value(&args, "--model") {
+        builder = builder.model(match model.as_str() {
+            "dmg" => Model::Dmg,
+            "mgb" => Model::Mgb,
+            "sgb" => Model::Sgb,
+            "cgb" => Model::Cgb,
+            "agb" => Model::Agb,
+            _ => panic!("Unknown model {}", model),
+        });
+    }
+    let mut sys = builder.build();
+
+    println!("Type h for help.");
+    print_current(&mut sys);
+    let stdin = io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        // An empty line repeats the last command
+        let line = match line.trim() {
+            "" => last.clone(),
+            line => line.to_string(),
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.first() {
+            None => continue,
+            Some(&"q") | Some(&"quit") => break,
+            Some(_) => {}
+        }
+        if let Err(e) = run_command(&mut sys, &words) {
+            println!("{}", e);
+        }
+        last = line;
+    }
+}
+
+fn run_command(sys: &mut System<DynCartridge>, words: &[&str]) -> Result<(), String> {
+    let arg = |i: usize| words.get(i).copied();
+    match words[0] {
+        "s" | "step" => {
+            let count = count(arg(1))?;
+            let mut outcome = RunOutcome::Done;
+            for _ in 0..count {
+                outcome = sys.step_instruction().map_err(|e| e.to_string())?;
+                if !matches!(outcome, RunOutcome::Done | RunOutcome::Frame) {
+                    break;
+                }
+            }
+            report(sys, outcome);
+        }
+        "f" | "frame" => {
+            let count = count(arg(1))?;
+            let mut outcome = RunOutcome::Done;
+            for _ in 0..count {
+                outcome = sys.run_until_vblank().map_err(|e| e.to_string())?;
+                if outcome != RunOutcome::Frame {
+                    break;
+                }
+            }
+            report(sys, outcome);
+        }
+        "c" | "continue" => loop {
+            let outcome = sys.run_until_vblank().map_err(|e| e.to_string())?;
+            if outcome != RunOutcome::Frame {
+                report(sys, outcome);
+                break;
+            }
+        },
+        "r" | "regs" => print_registers(sys),
+        "x" | "mem" => {
+            let addr = hex(arg(1).ok_or("missing address")?)?;
+            let len = match arg(2) {
+                Some(len) => len.parse::<u32>().map_err(|e| e.to_string())?,
+                None => 0x40,
+            };
+            dump(sys, addr, len);
+        }
+        "d" | "dis" => {
+            let mut addr = match arg(1) {
+                Some(addr) => hex(addr)?,
+                None => sys.registers().pc(),
+            };
+            let lines = match arg(2) {
+                Some(_) => count(arg(2))?,
+                None => 10,
+            };
+            let pc = sys.registers().pc();
+            for _ in 0..lines {
+                let instr = sys.disassemble(addr);
+                let marker = if addr == pc { "=>" } else { "  " };
+                let bytes: Vec<String> =
+                    instr.bytes().iter().map(|b| format!("{:02X}", b)).collect();
+                println!("{} {:04X}: {:<9} {}", marker, addr, bytes.join(" "), instr);
+                addr = instr.next_addr();
+            }
+        }
+        "b" | "break" => {
+            let breakpoint = parse_breakpoint(&words[1..])?;
+            println!("Breakpoint {}: {}", sys.breakpoints().len(), breakpoint);
+            sys.add_breakpoint(breakpoint);
+        }
+        "w" | "watch" => {
+            let range = arg(1).ok_or("missing address")?;
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (hex(start)?, hex(end)?),
+                None => (hex(range)?, hex(range)?),
+            };
+            let access = match arg(2).unwrap_or("w") {
+                "r" => Access::Read,
+                "w" => Access::Write,
+                "rw" => Access::ReadWrite,
+                a => return Err(format!("unknown access {}", a)),
+            };
+            let watchpoint = Watchpoint::new(start.min(end), start.max(end), access);
+            println!("Watchpoint {}: {}", sys.watchpoints().len(), watchpoint);
+            sys.add_watchpoint(watchpoint);
+        }
+        "bi" => {
+            let mut mask = 0;
+            for name in &words[1..] {
+                match INTERRUPTS.iter().position(|i| i == name) {
+                    Some(bit) => mask |= 1 << bit,
+                    None if *name == "none" => {}
+                    None => return Err(format!("unknown interrupt {}", name)),
+                }
+            }
+            sys.set_interrupt_breaks(mask);
+        }
+        "bo" => sys.break_on_opcode(hex(arg(1).ok_or("missing opcode")?)? as u8),
+        "l" | "list" => list(sys),
+        "del" => {
+            let value = arg(2).ok_or("missing number")?;
+            let deleted = match arg(1) {
+                Some("b") => sys.remove_breakpoint(index(value)?).is_some(),
+                Some("w") => sys.remove_watchpoint(index(value)?).is_some(),
+                Some("o") => {
+                    sys.clear_opcode_break(hex(value)? as u8);
+                    true
+                }
+                _ => return Err("del b|w|o <n|opcode>".into()),
+            };
+            if !deleted {
+                return Err(format!("no entry {}", value));
+            }
+        }
+        "reset" => {
+            sys.reset();
+            print_current(sys);
+        }
+        "h" | "help" => println!("{}", HELP),
+        cmd => return Err(format!("unknown command {}, h for help", cmd)),
+    }
+    Ok(())
+}
+
+// [bank:]addr [if <reg> <op> <value>]
+fn parse_breakpoint(words: &[&str]) -> Result<Breakpoint, String> {
+    let location = words.first().ok_or("missing address")?;
+    let mut breakpoint = match location.split_once(':') {
+        Some((bank, addr)) => Breakpoint::new(hex(addr)?).bank(hex(bank)? as usize),
+        None => Breakpoint::new(hex(location)?),
+    };
+    match words.get(1..) {
+        None | Some([]) => {}
+        Some(["if", register, compare, value]) => {
+            breakpoint = breakpoint.condition(Condition {
+                register: register_named(register)?,
+                compare: match *compare {
+                    "==" => Compare::Eq,
+                    "!=" => Compare::Ne,
+                    "<" => Compare::Lt,
+                    "<=" => Compare::Le,
+                    ">" => Compare::Gt,
+                    ">=" => Compare::Ge,
+                    c => return Err(format!("unknown comparison {}", c)),
+                },
+                value: hex(value)?,
+            })
+        }
+        Some(_) => return Err("expected: if <reg> <op> <value>".into()),
+    }
+    Ok(breakpoint)
+}
+
+fn register_named(name: &str) -> Result<Register, String> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "a" => Register::A,
+        "f" => Register::F,
+        "b" => Register::B,
+        "c" => Register::C,
+        "d" => Register::D,
+        "e" => Register::E,
+        "h" => Register::H,
+        "l" => Register::L,
+        "af" => Register::AF,
+        "bc" => Register::BC,
+        "de" => Register::DE,
+        "hl" => Register::HL,
+        "sp" => Register::SP,
+        "pc" => Register::PC,
+        _ => return Err(format!("unknown register {}", name)),
+    })
+}
+
+fn report(sys: &mut System<DynCartridge>, outcome: RunOutcome) {
+    match outcome {
+        RunOutcome::Done | RunOutcome::Frame => {}
+        RunOutcome::Breakpoint(addr) => println!("Breakpoint at {:04X}", addr),
+        RunOutcome::Watchpoint(hit) => match hit.write {
+            true => println!("Watchpoint: {:02X} written to {:04X}", hit.value, hit.addr),
+            false => println!("Watchpoint: {:02X} read from {:04X}", hit.value, hit.addr),
+        },
+        RunOutcome::Interrupt(n) => println!("Interrupt {}", INTERRUPTS[n as usize]),
+        RunOutcome::Opcode(opcode) => println!("Opcode {:02X}", opcode),
+        RunOutcome::LockUp(lock_up) => println!(
+            "CPU locked up: illegal opcode {:02X} at {:04X}",
+            lock_up.opcode, lock_up.pc
+        ),
+    }
+    print_current(sys);
+}
+
+fn print_current(sys: &mut System<DynCartridge>) {
+    let pc = sys.registers().pc();
+    println!("{:04X}: {}", pc, sys.disassemble(pc));
+}
+
+fn print_registers(sys: &mut System<DynCartridge>) {
+    let regs = sys.registers();
+    let f = regs.af() as u8;
+    let flags: String = [(0x80, 'Z'), (0x40, 'N'), (0x20, 'H'), (0x10, 'C')]
+        .iter()
+        .map(|&(bit, name)| if f & bit != 0 { name } else { '-' })
+        .collect();
+    println!(
+        "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X} [{}]",
+        regs.af(),
+        regs.bc(),
+        regs.de(),
+        regs.hl(),
+        regs.sp(),
+        regs.pc(),
+        flags
+    );
+    println!(
+        "IE={:02X} IF={:02X} LY={:02X} cycles={}",
+        sys.peek(0xFFFF),
+        sys.peek(0xFF0F),
+        sys.peek(0xFF44),
+        sys.cycles()
+    );
+}
+
+fn dump(sys: &mut System<DynCartridge>, addr: u16, len: u32) {
+    for row in (0..len).step_by(16) {
+        let start = addr.wrapping_add(row as u16);
+        let bytes: Vec<u8> = (0..16.min(len - row))
+            .map(|i| sys.peek(start.wrapping_add(i as u16)))
+            .collect();
+        let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        let text: String = bytes
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+            .collect();
+        println!("{:04X}: {:<47}  {}", start, hex.join(" "), text);
+    }
+}
+
+fn list(sys: &System<DynCartridge>) {
+    for (i, breakpoint) in sys.breakpoints().iter().enumerate() {
+        println!("Breakpoint {}: {}", i, breakpoint);
+    }
+    for (i, watchpoint) in sys.watchpoints().iter().enumerate() {
+        println!("Watchpoint {}: {}", i, watchpoint);
+    }
+    let interrupts: Vec<&str> = (0..5)
+        .filter(|bit| sys.interrupt_breaks() & (1 << bit) != 0)
+        .map(|bit| INTERRUPTS[bit])
+        .collect();
+    if !interrupts.is_empty() {
+        println!("Interrupts: {}", interrupts.join(" "));
+    }
+    let opcodes: Vec<String> = sys.opcode_breaks().map(|o| format!("{:02X}", o)).collect();
+    if !opcodes.is_empty() {
+        println!("Opcodes: {}", opcodes.join(" "));
+    }
+}
+
+// $1234, 0x1234 or 1234
+fn hex(value: &str) -> Result<u16, String> {
+    let digits = value
+        .trim_start_matches('$')
+        .trim_start_matches("0x")
+        .trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("bad hexadecimal value {}", value))
+}
+
+fn count(value: Option<&str>) -> Result<u32, String> {
+    match value {
+        Some(value) => value.parse().map_err(|_| format!("bad count {}", value)),
+        None => Ok(1),
+    }
+}
+
+fn index(value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("bad number {}", value))
+}
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    let idx = args.iter().position(|a| a == name)?;
+    args.get(idx + 1)
+}
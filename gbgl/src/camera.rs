@@ -0,0 +1,93 @@
+use gbcore::cartridge::{CAMERA_HEIGHT, CAMERA_WIDTH, ImageSource};
+use std::fs;
+
+// Still picture loaded from a binary PGM (P5) file, scaled to the sensor
+pub struct PgmImage {
+    pixels: Vec<u8>,
+}
+
+impl PgmImage {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        // Header: "P5" width height maxval, separated by whitespace,
+        // then a single whitespace before the pixels
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while fields.len() < 4 {
+            while pos < data.len() && data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if data.get(pos) == Some(&b'#') {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(format!("{}: truncated PGM header", path));
+            }
+            fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+        pos += 1;
+
+        if fields[0] != "P5" {
+            return Err(format!("{}: not a binary PGM file", path));
+        }
+        let parse = |s: &str| s.parse::<usize>().map_err(|e| format!("{}: {}", path, e));
+        let (width, height, max) = (parse(&fields[1])?, parse(&fields[2])?, parse(&fields[3])?);
+        if max == 0 || max > 255 {
+            return Err(format!("{}: only 8 bit PGM files are supported", path));
+        }
+        if width == 0 || height == 0 {
+            return Err(format!("{}: empty PGM image", path));
+        }
+        let end = width
+            .checked_mul(height)
+            .and_then(|size| size.checked_add(pos))
+            .ok_or(format!("{}: PGM image too large", path))?;
+        let raw = data
+            .get(pos..end)
+            .ok_or(format!("{}: truncated PGM data", path))?;
+
+        // Nearest neighbour scaling
+        let mut pixels = Vec::with_capacity(CAMERA_WIDTH * CAMERA_HEIGHT);
+        for y in 0..CAMERA_HEIGHT {
+            for x in 0..CAMERA_WIDTH {
+                let value = raw[(y * height / CAMERA_HEIGHT) * width + x * width / CAMERA_WIDTH];
+                pixels.push((value as usize * 255 / max) as u8);
+            }
+        }
+        Ok(Self { pixels })
+    }
+}
+
+impl ImageSource for PgmImage {
+    fn capture(&mut self, frame: &mut [u8; CAMERA_WIDTH * CAMERA_HEIGHT]) {
+        frame.copy_from_slice(&self.pixels);
+    }
+}
+
+// Moving gradient with a checkerboard, when no picture is given
+#[derive(Default)]
+pub struct TestPattern {
+    frame: usize,
+}
+
+impl ImageSource for TestPattern {
+    fn capture(&mut self, frame: &mut [u8; CAMERA_WIDTH * CAMERA_HEIGHT]) {
+        self.frame += 1;
+        for (idx, pixel) in frame.iter_mut().enumerate() {
+            let (x, y) = (idx % CAMERA_WIDTH, idx / CAMERA_WIDTH);
+            let gradient = ((x + self.frame) % CAMERA_WIDTH) * 255 / CAMERA_WIDTH;
+            *pixel = match (x / 16 + y / 16) % 2 {
+                0 => gradient as u8,
+                _ => 255 - gradient as u8,
+            };
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use gbcore::cartridge::Cartridge;
+use gbcore::gdb::GdbStub;
+use gbcore::{Screen, System};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+// GDB connection over TCP, the game only runs when GDB continues it
+pub struct GdbServer {
+    stream: TcpStream,
+    stub: GdbStub,
+}
+
+impl GdbServer {
+    // Wait for GDB to connect on the local port
+    pub fn accept(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("Waiting for GDB on port {} (target remote :{})", port, port);
+        let (stream, _) = listener.accept()?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            stub: GdbStub::default(),
+        })
+    }
+
+    // One host frame: handle the packets received and run the game until
+    // VBlank if GDB continued it. Returns false once GDB is gone.
+    pub fn frame<C: Cartridge>(
+        &mut self,
+        sys: &mut System<C>,
+        screen: &mut Screen,
+        keys: u8,
+    ) -> io::Result<bool> {
+        let mut out = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.stub.receive(sys, &buf[..n], &mut out),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.stub.running() {
+            sys.set_keys(keys);
+            let outcome = sys.run_until_vblank();
+            sys.swap_screen(screen);
+            self.stub.stopped(outcome, &mut out);
+        }
+
+        self.stream.set_nonblocking(false)?;
+        self.stream.write_all(&out)?;
+        self.stream.set_nonblocking(true)?;
+        Ok(!self.stub.detached())
+    }
+}
@@ -1,13 +1,22 @@
-use gbcore::cartridge::DynCartridge;
-use gbcore::{Screen, System};
+mod camera;
+mod gdb;
+
+use camera::{PgmImage, TestPattern};
+use gbcore::cartridge::{Clock, DynCartridge, ImageSource, Peripherals, Tilt};
+use gbcore::{BootRom, Model, Screen, SystemBuilder, TraceFormat};
+use gdb::GdbServer;
 use gl_matrix::common::*;
 use gl_matrix::mat4;
 use glfw::{Context, WindowEvent};
+use std::cell::Cell;
 use std::convert::TryInto;
 use std::ffi::CString;
 use std::fs;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const SCREEN_COLORS_DEPTH: u32 = 3;
 const GB_SCREEN_WIDTH: u32 = 160;
@@ -22,8 +31,30 @@ fn main() {
     let rom_file = args.get(1).unwrap();
     let rom_data = fs::read(rom_file).unwrap();
 
-    let dyn_cart = DynCartridge::new(rom_data).unwrap();
+    let camera: Box<dyn ImageSource> = match arg_value(&args, "--camera") {
+        Some(file) => Box::new(PgmImage::load(file).unwrap()),
+        None => Box::new(TestPattern::default()),
+    };
+    let tilt = Rc::new(Cell::new((0.0, 0.0)));
+    let peripherals = Peripherals {
+        clock: Some(Box::new(SystemClock)),
+        tilt: Some(Box::new(HostTilt(tilt.clone()))),
+        camera: Some(camera),
+        ..Default::default()
+    };
+    let mouse_tilt = args.iter().any(|a| a == "--mouse-tilt");
+    let mut dyn_cart = match DynCartridge::with_peripherals(rom_data, peripherals) {
+        Ok(cart) => cart,
+        Err(e) => {
+            eprintln!("{}: {}", rom_file, e);
+            std::process::exit(1);
+        }
+    };
 
+    println!("Title: {}", dyn_cart.header.title);
+    for warning in dyn_cart.header.warnings() {
+        println!("Header warning: {:?}", warning);
+    }
     println!("Cart type: {:?}", dyn_cart.cart_type);
     println!(
         "ROM type: {:?} ({} banks)",
@@ -36,8 +67,57 @@ fn main() {
         dyn_cart.ram_type.nb_bank(),
     );
 
+    // Battery backed RAM is kept next to the ROM, followed by the clock
+    let save_file = std::path::Path::new(rom_file).with_extension("sav");
+    if let Ok(save) = fs::read(&save_file) {
+        let mut rtc = &save[..];
+        if let Some(ram) = dyn_cart.battery_ram_mut() {
+            let len = ram.len().min(save.len());
+            ram[..len].copy_from_slice(&save[..len]);
+            rtc = &save[len..];
+        }
+        dyn_cart.set_battery_rtc(rtc);
+    }
+
+    let mut builder = SystemBuilder::new(dyn_cart);
+    if let Some(boot_file) = arg_value(&args, "--boot") {
+        let boot_data = fs::read(boot_file).unwrap();
+        builder = builder.boot_rom(BootRom::new(&boot_data).unwrap());
+    }
+    if let Some(model) = arg_value(&args, "--model") {
+        builder = builder.model(match model.as_str() {
+            "dmg" => Model::Dmg,
+            "mgb" => Model::Mgb,
+            "sgb" => Model::Sgb,
+            "cgb" => Model::Cgb,
+            "agb" => Model::Agb,
+            _ => panic!("Unknown model {}", model),
+        });
+    }
+
+    // Gameboy Doctor log, with LY stubbed so it can be diffed against its
+    // reference logs
+    if let Some(trace_file) = arg_value(&args, "--trace") {
+        let file = fs::File::create(trace_file).unwrap();
+        builder = builder
+            .trace(TraceFile(BufWriter::new(file)), TraceFormat::Doctor)
+            .stub_ly(true);
+    }
+
+    builder = builder.on_lock_up(|lock_up| {
+        eprintln!(
+            "CPU locked up: illegal opcode {:#04x} at {:#06x} (F5 to reset)",
+            lock_up.opcode, lock_up.pc
+        )
+    });
+
     let mut screen = Screen::default();
-    let mut sys = System::new(dyn_cart);
+    let mut sys = builder.build();
+    // The game waits for GDB to connect and continue it
+    let mut gdb = arg_value(&args, "--gdb").map(|port| {
+        let port = port.parse().expect("Invalid GDB port");
+        GdbServer::accept(port).unwrap()
+    });
     ////////////////////////////////////////////////////////////////////////
 
     let mut glfw = glfw::init_no_callbacks().unwrap();
@@ -201,6 +281,7 @@ fn main() {
     let mut last_update = Instant::now();
     const FPS_UPDATE_RATE: Duration = Duration::from_millis(1000);
     let mut keys: u8 = 0;
+    let mut tilt_keys: (f32, f32) = (0.0, 0.0);
     while !window.should_close() {
         start_frame = Instant::now();
         glfw.poll_events();
@@ -216,6 +297,11 @@ fn main() {
                         glfw::Key::A => keys |= gbcore::KEY_LEFT,   // LEFT
                         glfw::Key::W => keys |= gbcore::KEY_UP,     // UP
                         glfw::Key::S => keys |= gbcore::KEY_DOWN,   // DOWN
+                        glfw::Key::J => tilt_keys.0 = -1.0,         // TILT LEFT
+                        glfw::Key::L => tilt_keys.0 = 1.0,          // TILT RIGHT
+                        glfw::Key::I => tilt_keys.1 = 1.0,          // TILT UP
+                        glfw::Key::K => tilt_keys.1 = -1.0,         // TILT DOWN
+                        glfw::Key::F5 => sys.reset(),               // RESET
                         _ => {}
                     },
                     glfw::Action::Release => match key {
@@ -227,6 +313,8 @@ fn main() {
                         glfw::Key::A => keys &= !gbcore::KEY_LEFT,   // LEFT
                         glfw::Key::W => keys &= !gbcore::KEY_UP,     // UP
                         glfw::Key::S => keys &= !gbcore::KEY_DOWN,   // DOWN
+                        glfw::Key::J | glfw::Key::L => tilt_keys.0 = 0.0,
+                        glfw::Key::I | glfw::Key::K => tilt_keys.1 = 0.0,
                         _ => {}
                     },
 
@@ -235,7 +323,33 @@ fn main() {
             }
         }
 
-        sys.tick(&mut screen, &keys);
+        // Mouse offset from the window center, 1 g at the edges
+        if mouse_tilt {
+            let (x, y) = window.get_cursor_pos();
+            let (width, height) = window.get_size();
+            let x = (x / width as f64 * 2.0 - 1.0).clamp(-1.0, 1.0) as f32;
+            let y = (1.0 - y / height as f64 * 2.0).clamp(-1.0, 1.0) as f32;
+            tilt.set((x, y));
+        } else {
+            tilt.set(tilt_keys);
+        }
+
+        match &mut gdb {
+            Some(server) => match server.frame(&mut sys, &mut screen, keys) {
+                Ok(true) => {}
+                Ok(false) => gdb = None,
+                Err(e) => {
+                    eprintln!("GDB connection lost: {}", e);
+                    gdb = None;
+                }
+            },
+            None => {
+                sys.tick(&mut screen, &keys);
+                if let Some(e) = sys.take_error() {
+                    eprintln!("Emulation error: {}", e);
+                }
+            }
+        }
 
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
@@ -277,6 +391,47 @@ fn main() {
             std::thread::sleep(frame_duration - elapsed);
         }
     }
+
+    let cart = sys.cartridge();
+    if cart.battery_ram().is_some() || cart.battery_rtc().is_some() {
+        let mut save = cart.battery_ram().unwrap_or_default().to_vec();
+        save.extend(cart.battery_rtc().unwrap_or_default());
+        fs::write(&save_file, save).unwrap();
+    }
+}
+
+// MBC7 accelerometer, set from the keys or the mouse
+struct HostTilt(Rc<Cell<(f32, f32)>>);
+
+impl Tilt for HostTilt {
+    fn tilt(&mut self) -> (f32, f32) {
+        self.0.get()
+    }
+}
+
+// Cartridge clocks follow the host time
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+}
+
+// Instruction trace written to a file
+struct TraceFile(BufWriter<fs::File>);
+
+impl std::fmt::Write for TraceFile {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0.write_all(s.as_bytes()).map_err(|_| std::fmt::Error)
+    }
+}
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    let idx = args.iter().position(|a| a == name)?;
+    args.get(idx + 1)
 }
 
 pub fn gl_get_string<'a>(name: gl::types::GLenum) -> &'a str {